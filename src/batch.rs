@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::Overrides;
+use crate::engine;
+use crate::report::RunReport;
+
+const CONFIG_FILE: &str = "nado.toml";
+
+/// Runs every config under `paths` in turn and prints one line per problem
+/// with its candidates' verdicts. Fails if any problem failed or errored.
+pub fn run(paths: &[PathBuf], overrides: &Overrides) -> Result<i32> {
+    let mut configs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let before = configs.len();
+            find_configs(path, &mut configs)?;
+            if configs.len() == before {
+                bail!("no {CONFIG_FILE} found under {}", path.display());
+            }
+        } else if path.exists() {
+            configs.push(path.clone());
+        } else {
+            bail!("config not found: {}", path.display());
+        }
+    }
+
+    let mut outcomes = Vec::new();
+    for (idx, config_path) in configs.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        println!("== {} ==", config_path.display());
+        let outcome = engine::run_reported(config_path, overrides);
+        if let Err(error) = &outcome {
+            println!("error: {error:#}");
+        }
+        outcomes.push(outcome);
+    }
+
+    println!();
+    println!("batch summary:");
+    let mut failed = 0;
+    for (config_path, outcome) in configs.iter().zip(&outcomes) {
+        let line = match outcome {
+            Ok((0, report)) => format!("PASS{}", candidate_matrix(report)),
+            Ok((_, report)) => format!("FAIL{}", candidate_matrix(report)),
+            // The full message was printed under the config's header.
+            Err(error) => {
+                let message = format!("{error:#}");
+                format!("ERROR ({})", message.lines().next().unwrap_or_default())
+            }
+        };
+        if !matches!(outcome, Ok((0, _))) {
+            failed += 1;
+        }
+        println!("- {}: {line}", config_path.display());
+    }
+
+    if failed == 0 {
+        println!("PASS: all {} config(s) passed", configs.len());
+        return Ok(0);
+    }
+    println!("FAIL: {failed} / {} config(s) failed", configs.len());
+    Ok(1)
+}
+
+fn candidate_matrix(report: &RunReport) -> String {
+    let verdicts = report
+        .candidates
+        .iter()
+        .map(|candidate| format!("{} {}", candidate.name, candidate.verdict.label()))
+        .collect::<Vec<_>>();
+    format!(" ({})", verdicts.join(", "))
+}
+
+/// Collects every `nado.toml` below `dir` in path order, skipping hidden
+/// directories such as `.nado` and `.git`, and cargo's `target`.
+fn find_configs(dir: &Path, configs: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read directory: {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .with_context(|| format!("failed to read directory: {}", dir.display()))?;
+    entries.sort();
+
+    for path in entries {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                find_configs(&path, configs)?;
+            }
+        } else if name == CONFIG_FILE {
+            configs.push(path);
+        }
+    }
+    Ok(())
+}
@@ -1,17 +1,317 @@
 use std::path::PathBuf;
 
 use anyhow::{bail, Context, Result};
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::{Overrides, Seed};
+use crate::report::Format;
 
 #[derive(Parser, Debug)]
 #[command(
     name = "nado",
     version,
-    about = "Local differential tester for algorithm solutions"
+    about = "Local differential tester for algorithm solutions",
+    args_conflicts_with_subcommands = true
 )]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    #[command(flatten)]
+    pub run: RunFlags,
+
+    #[command(flatten)]
+    pub single: SingleRunFlags,
+
+    /// Re-run the first failure recorded by the previous run with full tracing
+    #[arg(long)]
+    pub explain_failure: bool,
+}
+
+impl Cli {
+    pub fn overrides(&self) -> Overrides {
+        self.single.overrides(&self.run, &self.config.overrides)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run origin over every generated case and write numbered .in/.out pairs
+    Export(ExportArgs),
+    /// Print one generated case, exactly as programs receive it, to check the input layout
+    Sample(SampleArgs),
+    /// Print every generated case without running any program
+    Gen(GenArgs),
+    /// Run several configs in sequence and print a pass/fail matrix
+    Run(Box<RunArgs>),
+    /// Check the config and environment (commands, mounts, Docker, limits) without running cases
+    Doctor(DoctorArgs),
+    /// Write a commented nado.toml template into the current directory
+    Init(InitArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// Directory to write the test files into (created if missing)
+    #[arg(long)]
+    pub to: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct SampleArgs {
     /// Optional path to nado TOML config (defaults to ./nado.toml)
     pub config: Option<PathBuf>,
+
+    /// Generate the case from this seed instead of the fixed preview seed
+    #[arg(long)]
+    pub seed: Option<Seed>,
+}
+
+#[derive(Args, Debug)]
+pub struct GenArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// Write the cases to this file instead of stdout
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// Overwrite an existing nado.toml
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Optional path to nado TOML config (defaults to ./nado.toml)
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Config files, or directories searched recursively for nado.toml
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub overrides: OverrideArgs,
+
+    #[command(flatten)]
+    pub run: RunFlags,
+}
+
+impl RunArgs {
+    pub fn overrides(&self) -> Overrides {
+        self.run.overrides(&self.overrides)
+    }
+}
+
+/// Config selection and overrides shared by every command.
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Optional path to nado TOML config (defaults to ./nado.toml)
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub overrides: OverrideArgs,
+}
+
+impl ConfigArgs {
+    pub fn resolve_path(&self) -> Result<PathBuf> {
+        resolve_config_path(self.config.clone())
+    }
+
+    pub fn overrides(&self) -> Overrides {
+        self.overrides.overrides()
+    }
+}
+
+/// Flags that adjust a loaded config, for every command that loads one.
+#[derive(Args, Debug)]
+pub struct OverrideArgs {
+    /// Disable seeded edge/partition cases and generate only random inputs
+    #[arg(long)]
+    pub no_pbt: bool,
+
+    /// Override engine.seed (an unsigned integer or "random")
+    #[arg(long)]
+    pub seed: Option<Seed>,
+
+    /// Override engine.cases
+    #[arg(long, value_name = "N")]
+    pub cases: Option<usize>,
+
+    /// Override engine.workers (at least 1)
+    #[arg(long, value_name = "N", value_parser = parse_positive::<usize>)]
+    pub workers: Option<usize>,
+
+    /// Override engine.timeout_ms, and limits.wall_ms if set
+    #[arg(long, value_name = "MS", value_parser = parse_positive::<u64>)]
+    pub timeout_ms: Option<u64>,
+
+    /// Print the resolved command line of every program before running
+    #[arg(long)]
+    pub show_commands: bool,
+
+    /// Report which declared edge values the generated cases exercised
+    #[arg(long)]
+    pub coverage: bool,
+}
+
+/// Flags that only matter when candidates run, so `export` and `gen` leave
+/// them out.
+#[derive(Args, Debug)]
+pub struct RunFlags {
+    /// Skip candidates that passed last run and have not changed since
+    #[arg(long)]
+    pub since_last: bool,
+
+    /// Run the whole suite N times and report whether verdicts ever differ
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub repeat_run: usize,
+
+    /// Only run candidates in group NAME (repeatable)
+    #[arg(long = "group", value_name = "NAME")]
+    pub groups: Vec<String>,
+
+    /// Only run the candidate named NAME (repeatable)
+    #[arg(long = "candidate", value_name = "NAME")]
+    pub candidates: Vec<String>,
+
+    /// Do not run the candidate named NAME (repeatable)
+    #[arg(long = "skip-candidate", value_name = "NAME")]
+    pub skip_candidates: Vec<String>,
+
+    /// Pass a candidate that matches at least RATIO (0.0-1.0) of the cases
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub fail_under: Option<f64>,
+
+    /// Start at most N cases per second across all workers
+    #[arg(long, value_name = "N", value_parser = parse_rate)]
+    pub max_cases_per_second: Option<f64>,
+}
+
+/// Run flags that name a single output file, case or format, so they apply
+/// only to a run of one config and `nado run` leaves them out.
+#[derive(Args, Debug)]
+pub struct SingleRunFlags {
+    /// Compare the working-tree version of FILE against its last committed version
+    #[arg(long, value_name = "FILE")]
+    pub candidates_from_git: Option<PathBuf>,
+
+    /// Write a JSON report of per-candidate verdicts to FILE
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Compare verdicts against a report saved earlier with --report
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+
+    /// On failure, write a shell script that reproduces the first failing case to FILE
+    #[arg(long, value_name = "FILE")]
+    pub emit_repro: Option<PathBuf>,
+
+    /// Write a JUnit XML report with one test case per candidate to FILE
+    #[arg(long, value_name = "FILE")]
+    pub junit: Option<PathBuf>,
+
+    /// Print results as "text" or as one "json" object on stdout
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub format: Format,
+
+    /// Run exactly this input instead of generating cases
+    #[arg(long, value_name = "TEXT", conflicts_with = "input_file")]
+    pub input: Option<String>,
+
+    /// Run exactly the contents of FILE instead of generating cases
+    #[arg(long, value_name = "FILE")]
+    pub input_file: Option<PathBuf>,
+
+    /// Run only case N (as numbered in `FAIL at case #N`) and print every output
+    #[arg(long, value_name = "N", conflicts_with_all = ["input", "input_file"])]
+    pub only_case: Option<usize>,
+}
+
+impl OverrideArgs {
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            no_pbt: self.no_pbt,
+            seed: self.seed,
+            cases: self.cases,
+            workers: self.workers,
+            timeout_ms: self.timeout_ms,
+            show_commands: self.show_commands,
+            coverage: self.coverage,
+            ..Overrides::default()
+        }
+    }
+}
+
+impl RunFlags {
+    /// `shared` with these flags added.
+    pub fn overrides(&self, shared: &OverrideArgs) -> Overrides {
+        Overrides {
+            since_last: self.since_last,
+            repeat_run: self.repeat_run,
+            groups: self.groups.clone(),
+            candidates: self.candidates.clone(),
+            skip_candidates: self.skip_candidates.clone(),
+            fail_under: self.fail_under,
+            max_cases_per_second: self.max_cases_per_second,
+            ..shared.overrides()
+        }
+    }
+}
+
+impl SingleRunFlags {
+    /// `run` and `shared` with these flags added.
+    pub fn overrides(&self, run: &RunFlags, shared: &OverrideArgs) -> Overrides {
+        Overrides {
+            candidates_from_git: self.candidates_from_git.clone(),
+            report: self.report.clone(),
+            baseline: self.baseline.clone(),
+            emit_repro: self.emit_repro.clone(),
+            junit: self.junit.clone(),
+            format: self.format,
+            input: self.input.clone(),
+            input_file: self.input_file.clone(),
+            only_case: self.only_case,
+            ..run.overrides(shared)
+        }
+    }
+}
+
+fn parse_ratio(raw: &str) -> Result<f64, String> {
+    match raw.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("expected a ratio between 0.0 and 1.0, got `{raw}`")),
+    }
+}
+
+fn parse_positive<T>(raw: &str) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + From<u8>,
+{
+    match raw.parse::<T>() {
+        Ok(value) if value >= T::from(1) => Ok(value),
+        _ => Err(format!("expected a positive integer, got `{raw}`")),
+    }
+}
+
+fn parse_rate(raw: &str) -> Result<f64, String> {
+    match raw.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("expected a positive number, got `{raw}`")),
+    }
 }
 
 pub fn resolve_config_path(cli_config: Option<PathBuf>) -> Result<PathBuf> {
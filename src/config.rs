@@ -1,16 +1,28 @@
 use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 
+use anyhow::{bail, Context, Result};
+use regex::Regex;
 use serde::de::Deserializer;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+use crate::expr::{self, Template};
+use crate::report::Format;
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     #[allow(dead_code)]
     pub version: Option<u32>,
     pub problem: Problem,
-    pub origin: Program,
-    #[serde(default, deserialize_with = "deserialize_candidates")]
+    #[serde(default, deserialize_with = "deserialize_programs")]
+    pub origin: Vec<Program>,
+    #[serde(default, deserialize_with = "deserialize_programs")]
     pub candidate: Vec<Program>,
+    pub judge: Option<Program>,
+    /// Decides whether a candidate's answer is acceptable, instead of
+    /// comparing it to the origin's output.
+    pub checker: Option<Program>,
     #[serde(default)]
     pub engine: Engine,
     #[serde(default)]
@@ -21,16 +33,766 @@ pub struct Config {
     pub normalize: Normalize,
 }
 
-#[derive(Debug, Deserialize)]
+impl Config {
+    pub fn origin_names(&self) -> Vec<String> {
+        if let [origin] = self.origin.as_slice() {
+            return vec![origin.name.clone().unwrap_or_else(|| "origin".to_string())];
+        }
+
+        self.origin
+            .iter()
+            .enumerate()
+            .map(|(idx, origin)| {
+                origin
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("origin-{}", idx + 1))
+            })
+            .collect()
+    }
+
+    /// Wall-clock budget for one run of `program`: its own `timeout_ms`, else
+    /// `limits.wall_ms`, else `engine.timeout_ms`.
+    pub fn timeout_ms(&self, program: &Program) -> u64 {
+        program
+            .timeout_ms
+            .or(self.limits.wall_ms)
+            .unwrap_or(self.engine.timeout_ms)
+    }
+
+    /// Whether a program that exited with `status` ran cleanly, per
+    /// `engine.success_exit_codes`; an empty list accepts any exit.
+    pub fn exited_ok(&self, status: &ExitStatus) -> bool {
+        let codes = &self.engine.success_exit_codes;
+        codes.is_empty()
+            || crate::runner::exit_code(status).is_some_and(|code| codes.contains(&code))
+    }
+
+    /// A status [`Self::exited_ok`] accepts, for outputs no program exited
+    /// with: formula origins, examples, and socket programs stopped once they
+    /// answered.
+    pub fn clean_exit(&self) -> ExitStatus {
+        let code = self.engine.success_exit_codes.first().copied().unwrap_or(0);
+        crate::runner::exit_status(code)
+    }
+
+    /// Every program of the config: origins, candidates, then the judge and
+    /// the checker.
+    pub fn programs(&self) -> impl Iterator<Item = &Program> {
+        self.origin
+            .iter()
+            .chain(&self.candidate)
+            .chain(self.judge.as_ref())
+            .chain(self.checker.as_ref())
+    }
+
+    /// Whether every case is one of `problem.examples`, so no origin is needed.
+    pub fn examples_only(&self) -> bool {
+        self.engine.cases == 0 && !self.problem.examples.is_empty()
+    }
+
+    pub fn candidate_names(&self) -> Vec<String> {
+        self.candidate
+            .iter()
+            .enumerate()
+            .map(|(idx, candidate)| {
+                candidate
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("candidate-{}", idx + 1))
+            })
+            .collect()
+    }
+}
+
+/// Command-line adjustments applied on top of a loaded config.
+#[derive(Debug, Default, Clone)]
+pub struct Overrides {
+    pub no_pbt: bool,
+    pub seed: Option<Seed>,
+    pub cases: Option<usize>,
+    pub workers: Option<usize>,
+    pub timeout_ms: Option<u64>,
+    pub candidates_from_git: Option<PathBuf>,
+    pub show_commands: bool,
+    pub coverage: bool,
+    pub report: Option<PathBuf>,
+    pub baseline: Option<PathBuf>,
+    pub since_last: bool,
+    pub repeat_run: usize,
+    pub groups: Vec<String>,
+    /// Names of the only candidates to run; empty runs them all.
+    pub candidates: Vec<String>,
+    pub skip_candidates: Vec<String>,
+    pub fail_under: Option<f64>,
+    pub emit_repro: Option<PathBuf>,
+    pub max_cases_per_second: Option<f64>,
+    pub input: Option<String>,
+    pub input_file: Option<PathBuf>,
+    pub format: Format,
+    pub junit: Option<PathBuf>,
+    /// One-based index of the only case to run.
+    pub only_case: Option<usize>,
+}
+
+impl Overrides {
+    pub fn apply(&self, config: &mut Config) {
+        if self.no_pbt {
+            config.pbt.enabled = false;
+        }
+        if let Some(seed) = self.seed {
+            config.engine.seed = seed;
+        }
+        if let Some(cases) = self.cases {
+            config.engine.cases = cases;
+        }
+        if let Some(workers) = self.workers {
+            config.engine.workers = workers;
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            // Both are the default wall-clock limit; the flag replaces either.
+            config.engine.timeout_ms = timeout_ms;
+            config.limits.wall_ms = None;
+        }
+        if self.fail_under.is_some() {
+            // Pass ratios need every case of every candidate.
+            config.engine.stop_on_first_fail = false;
+        }
+        if self.input.is_some() || self.input_file.is_some() {
+            // The given case replaces every other case.
+            config.problem.examples.clear();
+        }
+    }
+}
+
+/// Reads and parses the config, returning it with its canonical directory.
+pub fn load(config_path: &Path) -> Result<(Config, PathBuf)> {
+    let config_dir = config_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let config_dir = std::fs::canonicalize(&config_dir).unwrap_or(config_dir);
+
+    let raw = std::fs::read_to_string(config_path)
+        .with_context(|| format!("failed to read config: {}", config_path.display()))?;
+    let mut config: Config = toml::from_str(&raw)
+        .map_err(|error| anyhow::anyhow!(describe_toml_error(config_path, &raw, &error)))?;
+    interpolate_env(&mut config)?;
+    check_exprs(&config)?;
+    compile_output_regex(&mut config)?;
+    if config.judge.is_some()
+        && config
+            .programs()
+            .any(|program| program.input_transform_cmd.is_some())
+    {
+        bail!("input_transform_cmd is not supported for interactive problems");
+    }
+    check_transports(&config)?;
+    input_file(&config)?;
+    check_arg_placeholders(&config)?;
+    check_multi_test(&config)?;
+    if config.origin.iter().any(|origin| origin.weight == Some(0)) {
+        bail!("origin weight must be positive");
+    }
+    if config
+        .candidate
+        .iter()
+        .chain(config.judge.as_ref())
+        .chain(config.checker.as_ref())
+        .any(|program| program.weight.is_some())
+    {
+        bail!("weight is only supported on origins");
+    }
+    if let Some(checker) = &config.checker {
+        if config.judge.is_some() {
+            bail!("[checker] is not supported for interactive problems; the judge decides");
+        }
+        if checker.image.is_some() {
+            bail!("checker must run locally, image is not supported");
+        }
+        if checker.transport == Transport::Socket {
+            bail!("transport = \"socket\" is not supported on the checker");
+        }
+        if checker.input_transform_cmd.is_some() {
+            bail!("input_transform_cmd is not supported on the checker");
+        }
+        if config.problem.output_schema.is_some() {
+            bail!("problem.output_schema and [checker] both judge answers; use one");
+        }
+    }
+    if config.judge.is_some() && !config.problem.examples.is_empty() {
+        bail!("problem.examples are not supported for interactive problems");
+    }
+    if config.engine.total_memory_mb.is_some() && config.limits.memory_mb.is_none() {
+        bail!(
+            "engine.total_memory_mb needs limits.memory_mb to tell how much each program may use"
+        );
+    }
+    if config.engine.workers == 0 {
+        bail!("engine.workers must be at least 1");
+    }
+    if config.engine.max_reported_failures == 0 {
+        bail!("engine.max_reported_failures must be at least 1");
+    }
+    if let Some(modulus) = config.normalize.modulus.filter(|&m| m <= 0) {
+        bail!("normalize.mod must be positive, got {modulus}");
+    }
+    if let Some(source) = &config.problem.origin_input_template {
+        if config.judge.is_some() {
+            bail!("problem.origin_input_template is not supported for interactive problems");
+        }
+        let template = Template::parse(source, &config.problem)
+            .context("invalid problem.origin_input_template")?;
+        config.problem.origin_template = Some(template);
+    }
+    if let Some(schema) = &config.problem.output_schema {
+        check_schema(schema)?;
+    }
+    for (key, tolerance) in [
+        ("float_tolerance", config.normalize.float_tolerance),
+        ("float_rel_tolerance", config.normalize.float_rel_tolerance),
+    ] {
+        if tolerance.is_some_and(|t| !(t.is_finite() && t >= 0.0)) {
+            bail!("normalize.{key} must be a non-negative number");
+        }
+    }
+    if config.normalize.tokenize {
+        for (key, set) in [
+            ("per_line", config.normalize.per_line),
+            ("sort_lines", config.normalize.sort_lines),
+        ] {
+            if set {
+                bail!("normalize.{key} needs line breaks, which normalize.tokenize removes");
+            }
+        }
+    }
+    if let Some(LineCount::Input(name)) = &config.normalize.expect_lines {
+        match config.problem.inputs.get(name) {
+            Some(spec) if spec.kind == "integer" => {}
+            _ => bail!("normalize.expect_lines must name an integer input, got `{name}`"),
+        }
+    }
+
+    Ok((config, config_dir))
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` in every program's `cmd`, `mounts`
+/// and `image`, so one config works across machines with different paths.
+fn interpolate_env(config: &mut Config) -> Result<()> {
+    let lookup = |name: &str| std::env::var(name).ok();
+    let programs = config
+        .origin
+        .iter_mut()
+        .chain(&mut config.candidate)
+        .chain(config.judge.as_mut())
+        .chain(config.checker.as_mut());
+    for program in programs {
+        for value in program.cmd.iter_mut().chain(&mut program.mounts) {
+            *value = expand_env(value, lookup)?;
+        }
+        if let Some(image) = &mut program.image {
+            *image = expand_env(image, lookup)?;
+        }
+    }
+    Ok(())
+}
+
+fn expand_env(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            bail!("unterminated `${{` in config value `{value}`");
+        };
+        let body = &rest[start + 2..start + 2 + len];
+        let (name, default) = match body.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (body, None),
+        };
+        match (lookup(name), default) {
+            (Some(var), _) => expanded.push_str(&var),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => {
+                bail!("environment variable `{name}` is not set (used in `{value}`)")
+            }
+        }
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn check_schema(schema: &OutputSchema) -> Result<()> {
+    if schema.columns.is_empty() {
+        bail!("problem.output_schema needs at least one column");
+    }
+    for column in &schema.columns {
+        match (column.compare, column.tolerance) {
+            (ColumnCompare::Float, None) => {
+                bail!(
+                    "output_schema column `{}` compares floats but has no tolerance",
+                    column.name
+                )
+            }
+            (ColumnCompare::Float, Some(_)) | (_, None) => {}
+            (_, Some(_)) => {
+                bail!(
+                    "output_schema column `{}` sets a tolerance without compare = \"float\"",
+                    column.name
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_transports(config: &Config) -> Result<()> {
+    let sockets = config
+        .programs()
+        .filter(|program| program.transport == Transport::Socket)
+        .collect::<Vec<_>>();
+    if sockets.is_empty() {
+        return Ok(());
+    }
+    if !cfg!(unix) {
+        bail!("transport = \"socket\" needs Unix domain sockets, which this platform lacks");
+    }
+    if config.judge.is_some() {
+        bail!("transport = \"socket\" is not supported for interactive problems");
+    }
+    if sockets.iter().any(|program| program.image.is_some()) {
+        bail!("transport = \"socket\" is only supported for local programs, not `image`");
+    }
+    Ok(())
+}
+
+/// The case file name for `problem.input_mode = "file"`, after checking that
+/// every origin and candidate has a `{input}` to receive its path.
+fn input_file(config: &Config) -> Result<Option<String>> {
+    let problem = &config.problem;
+    if problem.input_mode == InputMode::Stdin {
+        if problem.input_filename.is_some() {
+            bail!("problem.input_filename needs problem.input_mode = \"file\"");
+        }
+        return Ok(None);
+    }
+    if config.judge.is_some() {
+        bail!("problem.input_mode = \"file\" is not supported for interactive problems");
+    }
+    if config
+        .programs()
+        .any(|program| program.transport == Transport::Socket)
+    {
+        bail!(
+            "problem.input_mode = \"file\" and transport = \"socket\" both replace stdin; use one"
+        );
+    }
+
+    let name = problem.input_file().unwrap_or_default();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        bail!("problem.input_filename must be a plain file name, got `{name}`");
+    }
+    let programs = config
+        .origin
+        .iter()
+        .map(|program| (program, "origin"))
+        .chain(
+            config
+                .candidate
+                .iter()
+                .map(|program| (program, "candidate")),
+        );
+    for (program, role) in programs {
+        if program.expr.is_none() && !program.cmd.iter().any(|arg| arg.contains("{input}")) {
+            bail!(
+                "problem.input_mode = \"file\" needs `{{input}}` in the cmd of {}",
+                program.name.as_deref().unwrap_or(role)
+            );
+        }
+    }
+    Ok(Some(name.to_string()))
+}
+
+/// Checks that `{N}` in the `cmd` of origins and candidates names a scalar
+/// input and that the program can take its case as arguments.
+fn check_arg_placeholders(config: &Config) -> Result<()> {
+    let inputs = config.problem.inputs.iter().collect::<Vec<_>>();
+    let programs = config
+        .origin
+        .iter()
+        .map(|program| (program, "origin"))
+        .chain(
+            config
+                .candidate
+                .iter()
+                .map(|program| (program, "candidate")),
+        );
+    for (program, role) in programs {
+        let positions = expr::arg_positions(&program.cmd);
+        let Some(&last) = positions.last() else {
+            continue;
+        };
+        let name = program.name.as_deref().unwrap_or(role);
+        if last >= inputs.len() {
+            bail!(
+                "cmd of {name} uses `{{{last}}}`, but [problem.inputs] declares {} input(s)",
+                inputs.len()
+            );
+        }
+        for position in positions {
+            let (input, spec) = inputs[position];
+            if matches!(spec.kind.as_str(), "array" | "grid" | "graph") {
+                bail!("cmd of {name} uses `{{{position}}}` for {} input `{input}`; only single-token inputs fit in one argument", spec.kind);
+            }
+        }
+        if config.judge.is_some() {
+            bail!("`{{N}}` arguments are not supported for interactive problems");
+        }
+        if config.problem.input_mode == InputMode::File {
+            bail!("cmd of {name} takes its inputs as arguments, so problem.input_mode = \"file\" has nothing to write");
+        }
+        if program.transport == Transport::Socket {
+            bail!("cmd of {name} takes its inputs as arguments, so transport = \"socket\" has nothing to send");
+        }
+        if program.input_transform_cmd.is_some() {
+            bail!("cmd of {name} takes its inputs as arguments, so input_transform_cmd has nothing to transform");
+        }
+        if role == "origin" && config.problem.origin_input_template.is_some() {
+            bail!("cmd of {name} takes its inputs as arguments, so problem.origin_input_template has nothing to shape");
+        }
+    }
+    Ok(())
+}
+
+/// Batched inputs no longer line up with `[problem.inputs]`, so nothing
+/// that reads single inputs out of a case can be used with them.
+fn check_multi_test(config: &Config) -> Result<()> {
+    let problem = &config.problem;
+    if !problem.multi_test {
+        if problem.t_min.is_some() || problem.t_max.is_some() {
+            bail!("problem.t_min and problem.t_max need problem.multi_test = true");
+        }
+        return Ok(());
+    }
+    let Some(t_max) = problem.t_max else {
+        bail!("problem.multi_test needs problem.t_max");
+    };
+    let t_min = problem.t_min.unwrap_or(1);
+    if t_min == 0 || t_min > t_max {
+        bail!("problem.t_min must be between 1 and problem.t_max ({t_max}), got {t_min}");
+    }
+    if problem.origin_input_template.is_some() {
+        bail!("problem.origin_input_template is not supported with problem.multi_test");
+    }
+    if matches!(config.normalize.expect_lines, Some(LineCount::Input(_))) {
+        bail!("normalize.expect_lines cannot name an input with problem.multi_test");
+    }
+    for program in config.origin.iter().chain(&config.candidate) {
+        let name = program.name.as_deref().unwrap_or("program");
+        if program.expr.is_some() {
+            bail!("origin {name} uses expr, which is not supported with problem.multi_test");
+        }
+        if !crate::expr::arg_positions(&program.cmd).is_empty() {
+            bail!("cmd of {name} takes its inputs as arguments, which is not supported with problem.multi_test");
+        }
+    }
+    Ok(())
+}
+
+fn check_exprs(config: &Config) -> Result<()> {
+    for origin in &config.origin {
+        let Some(source) = &origin.expr else {
+            continue;
+        };
+        if !origin.cmd.is_empty() || origin.image.is_some() {
+            bail!("origin sets both expr and cmd/image; use one");
+        }
+        if origin.input_transform_cmd.is_some() {
+            bail!("origin sets both expr and input_transform_cmd; expr reads the inputs directly");
+        }
+        crate::expr::parse(source).with_context(|| format!("invalid origin expr `{source}`"))?;
+    }
+    if config
+        .candidate
+        .iter()
+        .chain(config.judge.as_ref())
+        .chain(config.checker.as_ref())
+        .any(|program| program.expr.is_some())
+    {
+        bail!("expr is only supported on origins");
+    }
+    Ok(())
+}
+
+fn compile_output_regex(config: &mut Config) -> Result<()> {
+    if config
+        .origin
+        .iter()
+        .chain(&config.candidate)
+        .chain(config.judge.as_ref())
+        .any(|program| program.output_regex.is_some())
+    {
+        bail!("output_regex is only supported on the checker");
+    }
+    let Some(checker) = &mut config.checker else {
+        return Ok(());
+    };
+    let Some(source) = &checker.output_regex else {
+        return Ok(());
+    };
+    if !checker.cmd.is_empty() || checker.image.is_some() {
+        bail!("checker sets both output_regex and cmd/image; use one");
+    }
+    let pattern =
+        Regex::new(source).with_context(|| format!("invalid checker output_regex `{source}`"))?;
+    checker.output_pattern = Some(pattern);
+    Ok(())
+}
+
+/// Renders a parse error as `path:line:col: message` followed by the
+/// offending line with the span underlined, so typos are easy to locate.
+fn describe_toml_error(path: &Path, raw: &str, error: &toml::de::Error) -> String {
+    let message = error.message().trim_end();
+    // Document-wide spans (e.g. a missing top-level field) point nowhere useful.
+    let Some(span) = error
+        .span()
+        .filter(|span| span.start > 0 || span.end < raw.trim_end().len())
+    else {
+        return format!("invalid config {}: {message}", path.display());
+    };
+
+    let line_start = raw[..span.start].rfind('\n').map_or(0, |idx| idx + 1);
+    let line_end = raw[span.start..]
+        .find('\n')
+        .map_or(raw.len(), |idx| span.start + idx);
+    let line_no = raw[..span.start].matches('\n').count() + 1;
+    let column = raw[line_start..span.start].chars().count() + 1;
+    let line = raw[line_start..line_end].trim_end_matches('\r');
+    let width = raw[span.start..span.end.min(line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    let gutter = " ".repeat(line_no.to_string().len());
+    format!(
+        "invalid config {}:{line_no}:{column}: {message}\n\
+         {gutter} |\n\
+         {line_no} | {line}\n\
+         {gutter} | {}{}",
+        path.display(),
+        " ".repeat(column - 1),
+        "^".repeat(width)
+    )
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct Problem {
+    #[serde(default)]
     pub inputs: BTreeMap<String, InputSpec>,
+    #[serde(default = "default_true")]
+    pub trailing_newline: bool,
+    #[serde(default)]
+    pub probe_trailing_newline: bool,
+    /// Shape every candidate output must have, checked before comparison.
+    pub output_format: Option<OutputFormat>,
+    /// Per-column comparison rules for record-per-line outputs.
+    pub output_schema: Option<OutputSchema>,
+    /// What origins read instead of the generated case; candidates still get
+    /// the case itself.
+    pub origin_input_template: Option<String>,
+    /// `origin_input_template`, parsed on load.
+    #[serde(skip)]
+    pub origin_template: Option<Template>,
+    /// Fixed cases with known answers, run before the generated ones.
+    #[serde(default)]
+    pub examples: Vec<Example>,
+    /// How origins and candidates receive each case.
+    #[serde(default)]
+    pub input_mode: InputMode,
+    /// Name of the case file under `input_mode = "file"` (default `input.txt`).
+    pub input_filename: Option<String>,
+    /// Each input is a batch: a line with T, then T generated cases.
+    #[serde(default)]
+    pub multi_test: bool,
+    /// Fewest cases in a batch under `multi_test` (default 1).
+    pub t_min: Option<usize>,
+    /// Most cases in a batch under `multi_test`.
+    pub t_max: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    /// Case on stdin.
+    #[default]
+    Stdin,
+    /// Case written to a file whose path replaces `{input}` in `cmd`; stdin
+    /// is empty.
+    File,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+pub struct Example {
+    pub input: String,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Exactly one integer.
+    SingleInt,
+    /// One integer on every line.
+    IntLines,
+    /// Lines with the same number of tokens; single-token lines the same width.
+    Grid,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutputSchema {
+    pub columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Column {
+    pub name: String,
+    #[serde(default)]
+    pub compare: ColumnCompare,
+    /// Absolute tolerance, for `compare = "float"` only.
+    pub tolerance: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnCompare {
+    #[default]
+    Exact,
+    IgnoreCase,
+    Float,
+    Ignore,
+}
+
+impl Problem {
+    /// Name of the file each case is written to under `input_mode = "file"`;
+    /// `None` pipes it to stdin.
+    pub fn input_file(&self) -> Option<&str> {
+        match self.input_mode {
+            InputMode::Stdin => None,
+            InputMode::File => Some(self.input_filename.as_deref().unwrap_or("input.txt")),
+        }
+    }
+}
+
+impl Default for Problem {
+    fn default() -> Self {
+        Self {
+            inputs: BTreeMap::new(),
+            trailing_newline: true,
+            probe_trailing_newline: false,
+            output_format: None,
+            output_schema: None,
+            origin_input_template: None,
+            origin_template: None,
+            examples: Vec::new(),
+            input_mode: InputMode::Stdin,
+            input_filename: None,
+            multi_test: false,
+            t_min: None,
+            t_max: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct InputSpec {
     #[serde(rename = "type")]
     pub kind: String,
     pub range: Option<String>,
+    pub min: Option<Bound>,
+    pub max: Option<Bound>,
+    pub distribution: Option<String>,
+    pub skew: Option<f64>,
+    pub values: Option<Vec<String>>,
+    pub weights: Option<Vec<f64>>,
+    pub nodes: Option<Count>,
+    pub edges: Option<Count>,
+    #[serde(default)]
+    pub directed: bool,
+    #[serde(default)]
+    pub acyclic: bool,
+    #[serde(default)]
+    pub connected: bool,
+    #[serde(default)]
+    pub self_loops: bool,
+    #[serde(default)]
+    pub multi_edges: bool,
+    pub edge_weights: Option<RangeSpec>,
+    /// First label of index-valued inputs, 0 or 1: where graph nodes start
+    /// (default 1), or how far an array's 0-based element range is shifted.
+    pub indexing: Option<u8>,
+    /// Digits after the decimal point of a float input (default 6).
+    pub decimals: Option<u32>,
+    /// Characters of a string input, with ranges such as `"a-z0-9"` (default `"a-z"`).
+    pub charset: Option<String>,
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    /// Structures of the longest strings added to a string input's seeded cases.
+    pub shapes: Option<Vec<StringShape>>,
+    /// Name of the integer input that gives an array input's length.
+    pub len: Option<String>,
+    /// Names of the integer inputs that give a grid input's size.
+    pub rows: Option<String>,
+    pub cols: Option<String>,
+    /// Bounds of each element of an array input, or each cell of a grid.
+    pub element: Option<Box<InputSpec>>,
+    /// Array input whose elements must all differ.
+    #[serde(default)]
+    pub distinct: bool,
+}
+
+/// A structure seeded string cases take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StringShape {
+    /// Reads the same backwards.
+    Palindrome,
+    /// A block of at most half the length, repeated.
+    Periodic,
+    /// One character throughout.
+    AllSame,
+}
+
+/// An input's `min` or `max`: a number, or an expression over other
+/// integer inputs such as `"n"` or `"n / 2"`, evaluated per case.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Bound {
+    Value(i64),
+    /// Only valid for float inputs.
+    Float(f64),
+    Expr(String),
+}
+
+impl From<i64> for Bound {
+    fn from(value: i64) -> Self {
+        Bound::Value(value)
+    }
+}
+
+/// A graph's `nodes` or `edges`: bounds, or the name of an integer input
+/// holding the count in each case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Count {
+    Range(RangeSpec),
+    Input(String),
+}
+
+/// Inclusive `{ min = .., max = .. }` bounds of a structured input's size or values.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct RangeSpec {
     pub min: Option<i64>,
     pub max: Option<i64>,
 }
@@ -38,20 +800,65 @@ pub struct InputSpec {
 #[derive(Debug, Clone, Deserialize)]
 pub struct Program {
     pub name: Option<String>,
+    #[serde(default)]
     pub cmd: Vec<String>,
+    /// Origin only: a formula over the inputs evaluated instead of running `cmd`.
+    pub expr: Option<String>,
+    /// Prefix such as `["valgrind", "--error-exitcode=1", "-q"]` run in front of `cmd`.
+    pub wrapper: Option<Vec<String>>,
+    /// Local command the generated case is piped through before it reaches
+    /// this program, for solutions with a different input convention.
+    pub input_transform_cmd: Option<Vec<String>>,
     pub image: Option<String>,
     pub timeout_ms: Option<u64>,
     #[serde(default)]
     pub mounts: Vec<String>,
+    /// Working directory relative to the config dir; mounts resolve against it.
+    pub workdir: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Family of interchangeable candidates, selected with `--group`.
+    pub group: Option<String>,
+    /// Candidate is known to be broken; the run passes only if it fails.
+    #[serde(default)]
+    pub expect_fail: bool,
+    /// Origin only: relative share of cases it judges under
+    /// `engine.rotate_origins` (default 1).
+    pub weight: Option<u32>,
+    /// How the case reaches the program and its answer comes back.
+    #[serde(default)]
+    pub transport: Transport,
+    /// Checker only: a pattern the normalized output must match, checked
+    /// instead of running `cmd`.
+    pub output_regex: Option<String>,
+    /// `output_regex`, compiled on load.
+    #[serde(skip)]
+    pub output_pattern: Option<Regex>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    /// Case on stdin, answer on stdout.
+    #[default]
+    Stdio,
+    /// Case and answer over a Unix socket nado listens on; its path is passed
+    /// as `NADO_SOCKET` and replaces `{socket}` in `cmd`.
+    Socket,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct Limits {
     pub cpu_seconds: Option<u64>,
+    /// Wall-clock kill time for programs without their own `timeout_ms`,
+    /// in place of `engine.timeout_ms`.
+    pub wall_ms: Option<u64>,
     pub memory_mb: Option<u64>,
     pub file_size_kb: Option<u64>,
     pub nofile: Option<u64>,
     pub nproc: Option<u64>,
+    /// Scheduling niceness (-20..=19); Docker programs get matching `--cpu-shares`.
+    pub nice: Option<i32>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -64,6 +871,11 @@ pub struct Pbt {
     pub partition_ratio: f64,
     #[serde(default = "default_max_cartesian_cases")]
     pub max_cartesian_cases: usize,
+    #[serde(default)]
+    pub adversarial_hash: bool,
+    /// Seed `k-1, k, k+1` clusters around each integer input's min, midpoint and max.
+    #[serde(default)]
+    pub off_by_one: bool,
 }
 
 impl Default for Pbt {
@@ -73,29 +885,31 @@ impl Default for Pbt {
             edge_case_ratio: default_edge_case_ratio(),
             partition_ratio: default_partition_ratio(),
             max_cartesian_cases: default_max_cartesian_cases(),
+            adversarial_hash: false,
+            off_by_one: false,
         }
     }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
-enum CandidateField {
-    One(Program),
+enum ProgramField {
+    One(Box<Program>),
     Many(Vec<Program>),
 }
 
-fn deserialize_candidates<'de, D>(deserializer: D) -> std::result::Result<Vec<Program>, D::Error>
+fn deserialize_programs<'de, D>(deserializer: D) -> std::result::Result<Vec<Program>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let field = Option::<CandidateField>::deserialize(deserializer)?;
+    let field = Option::<ProgramField>::deserialize(deserializer)?;
     let Some(field) = field else {
         return Ok(Vec::new());
     };
 
     match field {
-        CandidateField::One(program) => Ok(vec![program]),
-        CandidateField::Many(programs) => Ok(programs),
+        ProgramField::One(program) => Ok(vec![*program]),
+        ProgramField::Many(programs) => Ok(programs),
     }
 }
 
@@ -104,13 +918,81 @@ pub struct Engine {
     #[serde(default = "default_cases")]
     pub cases: usize,
     #[serde(default = "default_seed")]
-    pub seed: u64,
+    pub seed: Seed,
     #[serde(default = "default_workers")]
     pub workers: usize,
     #[serde(default = "default_timeout_ms")]
     pub timeout_ms: u64,
     #[serde(default = "default_stop_on_first_fail")]
     pub stop_on_first_fail: bool,
+    #[serde(default = "default_max_input_bytes")]
+    pub max_input_bytes: u64,
+    #[serde(default = "default_image_pull_retries")]
+    pub image_pull_retries: u32,
+    /// Pin every spawned program to a CPU chosen by its worker (Linux only).
+    #[serde(default)]
+    pub pin_cpus: bool,
+    /// Minimum gap between two process spawns, across all workers.
+    #[serde(default)]
+    pub spawn_stagger_ms: u64,
+    /// Memory all concurrently running programs may use together; fewer
+    /// workers run when `workers` × `limits.memory_mb` would exceed it.
+    pub total_memory_mb: Option<u64>,
+    /// Cap on the whole run; when it passes, running programs are killed and
+    /// the summary covers the cases that completed.
+    pub hard_deadline_ms: Option<u64>,
+    /// How long `--explain-failure` lets a timed-out candidate run to see
+    /// whether it ever halts; defaults to ten times its timeout.
+    pub termination_probe_ms: Option<u64>,
+    /// Cap on normalizing and comparing one candidate output; overrunning it
+    /// is an engine failure rather than a hang.
+    pub compare_timeout_ms: Option<u64>,
+    /// Judge each case with one origin picked by weight instead of a
+    /// majority vote of all of them.
+    #[serde(default)]
+    pub rotate_origins: bool,
+    /// Treat a program that exits without reading all of its input as
+    /// failing instead of ignoring the broken pipe.
+    #[serde(default)]
+    pub strict_stdin: bool,
+    /// Where to write a JUnit XML report, relative to the config's directory.
+    pub junit_path: Option<PathBuf>,
+    /// Directory, relative to the config's, that every failing case is
+    /// written to as input, expected and actual output files.
+    pub save_failures: Option<PathBuf>,
+    /// Container engine that runs `image` programs.
+    #[serde(default)]
+    pub container_runtime: ContainerRuntime,
+    /// Failing cases printed in full per candidate, one from each failure
+    /// signature in turn, largest first.
+    #[serde(default = "default_max_reported_failures")]
+    pub max_reported_failures: usize,
+    /// Times a candidate that mismatches or times out is re-run on the same
+    /// case before it fails.
+    #[serde(default)]
+    pub retries: u32,
+    /// Exit codes that count as a clean run for origins and candidates.
+    #[serde(default = "default_success_exit_codes")]
+    pub success_exit_codes: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    /// Accepts the same `run` flags as Docker; rootless installs work.
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// Executable looked up on `PATH`.
+    pub fn binary(self) -> &'static str {
+        match self {
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+        }
+    }
 }
 
 impl Default for Engine {
@@ -121,6 +1003,71 @@ impl Default for Engine {
             workers: default_workers(),
             timeout_ms: default_timeout_ms(),
             stop_on_first_fail: default_stop_on_first_fail(),
+            max_input_bytes: default_max_input_bytes(),
+            image_pull_retries: default_image_pull_retries(),
+            pin_cpus: false,
+            spawn_stagger_ms: 0,
+            total_memory_mb: None,
+            hard_deadline_ms: None,
+            termination_probe_ms: None,
+            compare_timeout_ms: None,
+            rotate_origins: false,
+            container_runtime: ContainerRuntime::Docker,
+            strict_stdin: false,
+            junit_path: None,
+            save_failures: None,
+            max_reported_failures: default_max_reported_failures(),
+            retries: 0,
+            success_exit_codes: default_success_exit_codes(),
+        }
+    }
+}
+
+/// Generation seed: a fixed number, or `"random"` to draw one per run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "SeedField")]
+pub enum Seed {
+    Fixed(u64),
+    Random,
+}
+
+impl Seed {
+    /// Returns the fixed seed, drawing one from system entropy for `random`.
+    pub fn resolve(self) -> u64 {
+        match self {
+            Seed::Fixed(seed) => seed,
+            Seed::Random => rand::random(),
+        }
+    }
+}
+
+impl std::str::FromStr for Seed {
+    type Err = String;
+
+    fn from_str(raw: &str) -> std::result::Result<Self, Self::Err> {
+        if raw == "random" {
+            return Ok(Seed::Random);
+        }
+        raw.parse()
+            .map(Seed::Fixed)
+            .map_err(|_| format!("seed must be an unsigned integer or \"random\", got {raw:?}"))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SeedField {
+    Number(u64),
+    Text(String),
+}
+
+impl TryFrom<SeedField> for Seed {
+    type Error = String;
+
+    fn try_from(field: SeedField) -> std::result::Result<Self, Self::Error> {
+        match field {
+            SeedField::Number(seed) => Ok(Seed::Fixed(seed)),
+            SeedField::Text(raw) => raw.parse(),
         }
     }
 }
@@ -131,6 +1078,53 @@ pub struct Normalize {
     pub trim_trailing_ws: bool,
     #[serde(default = "default_true")]
     pub ignore_final_newline: bool,
+    #[serde(default)]
+    pub int_multiset: bool,
+    pub round_decimals: Option<u32>,
+    /// Reduce integer tokens into `[0, mod)` before comparing.
+    #[serde(rename = "mod")]
+    pub modulus: Option<i64>,
+    /// Canonicalize yes/no style tokens before comparing.
+    #[serde(default)]
+    pub boolean: bool,
+    /// Absolute tolerance for numeric tokens; other tokens compare exactly.
+    pub float_tolerance: Option<f64>,
+    /// Relative tolerance for numeric tokens, scaled by the expected value.
+    pub float_rel_tolerance: Option<f64>,
+    /// Compare line by line, requiring equal line counts.
+    #[serde(default)]
+    pub per_line: bool,
+    /// Number of lines a candidate must print, checked before comparing.
+    pub expect_lines: Option<LineCount>,
+    /// Zero-based token positions to keep on every line; the rest are ignored.
+    pub compare_tokens: Option<Vec<usize>>,
+    /// Sort the tokens of every line, keeping the order of the lines.
+    #[serde(default)]
+    pub sort_tokens_in_line: bool,
+    /// Sort the lines, for answers that may be printed in any order.
+    #[serde(default)]
+    pub sort_lines: bool,
+    /// Collapse all whitespace, line breaks included, into single spaces.
+    #[serde(default)]
+    pub tokenize: bool,
+    /// Lowercase both outputs before comparing them.
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// A fixed line count, or the name of an integer input holding it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum LineCount {
+    Fixed(usize),
+    Input(String),
+}
+
+impl Normalize {
+    /// Whether numeric tokens are compared by value within a tolerance.
+    pub fn tolerant(&self) -> bool {
+        self.float_tolerance.is_some() || self.float_rel_tolerance.is_some()
+    }
 }
 
 impl Default for Normalize {
@@ -138,6 +1132,19 @@ impl Default for Normalize {
         Self {
             trim_trailing_ws: true,
             ignore_final_newline: true,
+            int_multiset: false,
+            round_decimals: None,
+            modulus: None,
+            boolean: false,
+            float_tolerance: None,
+            float_rel_tolerance: None,
+            per_line: false,
+            expect_lines: None,
+            compare_tokens: None,
+            sort_tokens_in_line: false,
+            sort_lines: false,
+            tokenize: false,
+            ignore_case: false,
         }
     }
 }
@@ -146,8 +1153,8 @@ fn default_cases() -> usize {
     1000
 }
 
-fn default_seed() -> u64 {
-    42
+fn default_seed() -> Seed {
+    Seed::Fixed(42)
 }
 
 fn default_workers() -> usize {
@@ -160,10 +1167,26 @@ fn default_timeout_ms() -> u64 {
     1000
 }
 
+fn default_max_reported_failures() -> usize {
+    1
+}
+
+fn default_success_exit_codes() -> Vec<i32> {
+    vec![0]
+}
+
 fn default_stop_on_first_fail() -> bool {
     true
 }
 
+fn default_max_input_bytes() -> u64 {
+    16 * 1024 * 1024
+}
+
+fn default_image_pull_retries() -> u32 {
+    3
+}
+
 fn default_true() -> bool {
     true
 }
@@ -179,3 +1202,129 @@ fn default_partition_ratio() -> f64 {
 fn default_max_cartesian_cases() -> usize {
     128
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{compile_output_regex, expand_env, input_file, Config, Overrides};
+
+    #[test]
+    fn expands_env_vars_with_defaults() {
+        let lookup = |name: &str| (name == "DIR").then(|| "/work".to_string());
+
+        assert_eq!(
+            expand_env("${DIR}/main.py", lookup).unwrap(),
+            "/work/main.py"
+        );
+        assert_eq!(
+            expand_env("${IMAGE:-python:3.12}", lookup).unwrap(),
+            "python:3.12"
+        );
+        assert_eq!(expand_env("plain $DIR", lookup).unwrap(), "plain $DIR");
+        assert!(expand_env("${MISSING}/x", lookup)
+            .unwrap_err()
+            .to_string()
+            .contains("`MISSING` is not set"));
+        assert!(expand_env("${DIR", lookup).is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_beat_the_config() {
+        let mut config: Config = toml::from_str(
+            "[problem]\n[engine]\ncases = 50\nworkers = 2\ntimeout_ms = 300\n\
+             [limits]\nwall_ms = 900\n",
+        )
+        .expect("config parses");
+        let workers = config.engine.workers;
+
+        Overrides::default().apply(&mut config);
+        assert_eq!(config.engine.cases, 50);
+        assert_eq!(config.engine.workers, workers);
+        assert_eq!(config.limits.wall_ms, Some(900));
+
+        let overrides = Overrides {
+            cases: Some(100000),
+            workers: Some(8),
+            timeout_ms: Some(5000),
+            ..Overrides::default()
+        };
+        overrides.apply(&mut config);
+        assert_eq!(config.engine.cases, 100000);
+        assert_eq!(config.engine.workers, 8);
+        assert_eq!(config.engine.timeout_ms, 5000);
+        assert_eq!(config.limits.wall_ms, None);
+    }
+
+    #[test]
+    fn input_file_needs_a_placeholder_in_every_program() {
+        let config = |extra: &str| -> Config {
+            toml::from_str(&format!(
+                "[problem]\ninput_mode = \"file\"\n{extra}\n\
+                 [[origin]]\ncmd = [\"./origin\", \"{{input}}\"]\n\
+                 [[candidate]]\nname = \"fast\"\ncmd = [\"./fast\", \"--in={{input}}\"]\n"
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(
+            input_file(&config("")).unwrap().as_deref(),
+            Some("input.txt")
+        );
+        assert_eq!(
+            input_file(&config("input_filename = \"case.in\""))
+                .unwrap()
+                .as_deref(),
+            Some("case.in")
+        );
+        assert!(input_file(&config("input_filename = \"../case.in\"")).is_err());
+
+        let mut missing = config("");
+        missing.candidate[0].cmd = vec!["./fast".to_string()];
+        assert!(input_file(&missing)
+            .unwrap_err()
+            .to_string()
+            .contains("cmd of fast"));
+    }
+
+    #[test]
+    fn checker_output_regex_replaces_the_checker_cmd() {
+        let config = |checker: &str| -> Config {
+            toml::from_str(&format!(
+                "[problem]\n[[origin]]\ncmd = [\"./origin\"]\n[checker]\n{checker}\n"
+            ))
+            .unwrap()
+        };
+
+        let mut valid = config("output_regex = \"^[LR]+$\"");
+        compile_output_regex(&mut valid).unwrap();
+        let pattern = valid.checker.unwrap().output_pattern.unwrap();
+        assert!(pattern.is_match("LRL") && !pattern.is_match("LX"));
+
+        let error = compile_output_regex(&mut config("output_regex = \"[\""))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("invalid checker output_regex"), "{error}");
+
+        let error = compile_output_regex(&mut config("output_regex = \"L\"\ncmd = [\"./check\"]"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("use one"), "{error}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn success_exit_codes_decide_clean_exits() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+
+        let mut config: Config =
+            toml::from_str("[problem]\n[engine]\nsuccess_exit_codes = [0, 42, 139]\n").unwrap();
+        let exit = |code: i32| ExitStatus::from_raw(code << 8);
+        assert!(config.exited_ok(&exit(0)) && config.exited_ok(&exit(42)));
+        assert!(!config.exited_ok(&exit(1)));
+        assert!(config.exited_ok(&ExitStatus::from_raw(libc::SIGSEGV)));
+        assert!(!config.exited_ok(&ExitStatus::from_raw(libc::SIGABRT)));
+
+        config.engine.success_exit_codes.clear();
+        assert!(config.exited_ok(&exit(1)));
+    }
+}
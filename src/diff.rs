@@ -0,0 +1,248 @@
+use std::fmt::Write;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+/// Diff lines printed before the rest is summarized, so a candidate that
+/// prints garbage does not flood the terminal.
+const MAX_LINES: usize = 60;
+/// Largest table the line-matching pass fills; bigger changed regions are
+/// shown as removed then added wholesale.
+const MAX_CELLS: usize = 1 << 20;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Same,
+    Removed,
+    Added,
+}
+
+/// One diff line with its 1-based line numbers in the expected and the
+/// actual output.
+struct Line<'a> {
+    op: Op,
+    old: Option<usize>,
+    new: Option<usize>,
+    text: &'a str,
+}
+
+/// Unified diff from `expected` to `actual`: hunks of changed lines with
+/// their line numbers and a little context, or empty when the lines are the
+/// same. With `color`, removed lines are red, added ones green, and the first
+/// differing line bold.
+pub fn unified(expected: &str, actual: &str, color: bool) -> String {
+    let lines = diff_lines(expected, actual);
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.op != Op::Same)
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    let Some(&first_change) = changed.first() else {
+        return String::new();
+    };
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changed {
+        let start = idx.saturating_sub(CONTEXT);
+        let end = (idx + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let width = lines
+        .iter()
+        .filter_map(|line| line.old.max(line.new))
+        .max()
+        .unwrap_or(0)
+        .to_string()
+        .len();
+    let paint = |code: &str, text: String| {
+        if color {
+            format!("{code}{text}{RESET}")
+        } else {
+            text
+        }
+    };
+
+    let mut out = String::new();
+    let mut printed = 0;
+    let total = hunks.iter().map(|(start, end)| end - start).sum::<usize>();
+    'hunks: for &(start, end) in &hunks {
+        let hunk = &lines[start..end];
+        let first_number =
+            |side: fn(&Line) -> Option<usize>| hunk.iter().find_map(side).unwrap_or(0);
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            first_number(|line| line.old),
+            hunk.iter().filter(|line| line.op != Op::Added).count(),
+            first_number(|line| line.new),
+            hunk.iter().filter(|line| line.op != Op::Removed).count(),
+        );
+        let _ = writeln!(out, "{}", paint(CYAN, header));
+
+        for (offset, line) in hunk.iter().enumerate() {
+            if printed == MAX_LINES {
+                let _ = writeln!(out, "... {} more diff line(s)", total - printed);
+                break 'hunks;
+            }
+            printed += 1;
+            let number = |n: Option<usize>| n.map_or(String::new(), |n| n.to_string());
+            let sign = match line.op {
+                Op::Same => ' ',
+                Op::Removed => '-',
+                Op::Added => '+',
+            };
+            let text = format!(
+                "{sign} {:>width$} {:>width$} | {}",
+                number(line.old),
+                number(line.new),
+                line.text
+            );
+            let text = match line.op {
+                Op::Same => text,
+                Op::Removed => paint(RED, text),
+                Op::Added => paint(GREEN, text),
+            };
+            let first = start + offset == first_change;
+            let _ = writeln!(out, "{}", if first { paint(BOLD, text) } else { text });
+        }
+    }
+    out
+}
+
+/// Whether the outputs differ, but only in whitespace at line ends or in
+/// trailing blank lines.
+pub fn only_trailing_whitespace(expected: &str, actual: &str) -> bool {
+    fn content(output: &str) -> Vec<&str> {
+        let mut lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+        while lines.last() == Some(&"") {
+            lines.pop();
+        }
+        lines
+    }
+    expected != actual && content(expected) == content(actual)
+}
+
+fn diff_lines<'a>(expected: &'a str, actual: &'a str) -> Vec<Line<'a>> {
+    let old = expected.lines().collect::<Vec<_>>();
+    let new = actual.lines().collect::<Vec<_>>();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops = vec![Op::Same; prefix];
+    ops.extend(middle_ops(old_mid, new_mid));
+    ops.extend(std::iter::repeat_n(Op::Same, suffix));
+
+    let (mut i, mut j) = (0, 0);
+    ops.into_iter()
+        .map(|op| {
+            let line = match op {
+                Op::Same => Line {
+                    op,
+                    old: Some(i + 1),
+                    new: Some(j + 1),
+                    text: old[i],
+                },
+                Op::Removed => Line {
+                    op,
+                    old: Some(i + 1),
+                    new: None,
+                    text: old[i],
+                },
+                Op::Added => Line {
+                    op,
+                    old: None,
+                    new: Some(j + 1),
+                    text: new[j],
+                },
+            };
+            i += usize::from(op != Op::Added);
+            j += usize::from(op != Op::Removed);
+            line
+        })
+        .collect()
+}
+
+/// Edit script between the differing middles, from a longest common
+/// subsequence of their lines.
+fn middle_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    if old.len().saturating_mul(new.len()) > MAX_CELLS {
+        let mut ops = vec![Op::Removed; old.len()];
+        ops.extend(std::iter::repeat_n(Op::Added, new.len()));
+        return ops;
+    }
+
+    // common[i][j]: LCS length of old[i..] and new[j..].
+    let cols = new.len() + 1;
+    let mut common = vec![0u32; (old.len() + 1) * cols];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i * cols + j] = if old[i] == new[j] {
+                common[(i + 1) * cols + j + 1] + 1
+            } else {
+                common[(i + 1) * cols + j].max(common[i * cols + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(Op::Same);
+            i += 1;
+            j += 1;
+        } else if j == new.len()
+            || (i < old.len() && common[(i + 1) * cols + j] >= common[i * cols + j + 1])
+        {
+            ops.push(Op::Removed);
+            i += 1;
+        } else {
+            ops.push(Op::Added);
+            j += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{only_trailing_whitespace, unified};
+
+    #[test]
+    fn unified_diff_numbers_lines_and_keeps_context() {
+        let expected = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let actual = "1\n2\n3\n4\n5\nsix\n7\n8\n9\n";
+
+        assert_eq!(
+            unified(expected, actual, false),
+            "@@ -3,6 +3,7 @@\n  3 3 | 3\n  4 4 | 4\n  5 5 | 5\n- 6   | 6\n+   6 | six\n  7 7 | 7\n  8 8 | 8\n+   9 | 9\n"
+        );
+        assert_eq!(unified(expected, expected, false), "");
+        assert!(unified("a\n", "b\n", true).contains("\x1b[1m\x1b[31m- 1   | a"));
+    }
+
+    #[test]
+    fn detects_trailing_whitespace_only_differences() {
+        assert!(only_trailing_whitespace("1 2\n3\n", "1 2 \n3\n\n"));
+        assert!(!only_trailing_whitespace("1 2\n", "1 2\n"));
+        assert!(!only_trailing_whitespace("1 2\n", "1  2\n"));
+    }
+}
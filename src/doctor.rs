@@ -0,0 +1,277 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::config::{self, Config, Program};
+use crate::engine::generate_cases;
+use crate::runner::{
+    check_runtime, image_present, local_argv, mount_host_paths, program_dir, unapplicable_limits,
+    RunSettings,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// Checklist of preflight results, printed as they come in.
+#[derive(Default)]
+struct Checklist {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Checklist {
+    fn report(&mut self, status: Status, message: impl AsRef<str>) {
+        let label = match status {
+            Status::Ok => "OK  ",
+            Status::Warn => {
+                self.warnings += 1;
+                "WARN"
+            }
+            Status::Fail => {
+                self.failures += 1;
+                "FAIL"
+            }
+        };
+        println!("{label} {}", message.as_ref());
+    }
+}
+
+/// Checks that the config loads and that every program could be started,
+/// without running any cases. Returns 1 if any check failed.
+pub fn run(config_path: &Path) -> Result<i32> {
+    let mut checks = Checklist::default();
+    println!("doctor: {}", config_path.display());
+
+    let (config, config_dir) = match config::load(config_path) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            checks.report(Status::Fail, format!("config: {error:#}"));
+            return Ok(finish(&checks));
+        }
+    };
+    checks.report(
+        Status::Ok,
+        format!(
+            "config parses ({} origin(s), {} candidate(s){})",
+            config.origin.len(),
+            config.candidate.len(),
+            if config.judge.is_some() {
+                ", judge"
+            } else if config.checker.is_some() {
+                ", checker"
+            } else {
+                ""
+            }
+        ),
+    );
+
+    let mut preview = config.clone();
+    preview.engine.cases = 1;
+    match generate_cases(&preview) {
+        Ok(_) => checks.report(Status::Ok, "inputs generate"),
+        Err(error) => checks.report(Status::Fail, format!("inputs: {error:#}")),
+    }
+
+    let mut programs = Vec::new();
+    programs.extend(config.origin_names().into_iter().zip(&config.origin));
+    programs.extend(config.candidate_names().into_iter().zip(&config.candidate));
+    programs.extend(
+        config
+            .judge
+            .iter()
+            .map(|judge| ("judge".to_string(), judge)),
+    );
+    programs.extend(
+        config
+            .checker
+            .iter()
+            .map(|checker| ("checker".to_string(), checker)),
+    );
+    for (name, program) in &programs {
+        check_program(&mut checks, &config, &config_dir, name, program);
+    }
+
+    check_images(&mut checks, &config);
+
+    let problems = unapplicable_limits(&config.limits);
+    for problem in &problems {
+        checks.report(Status::Warn, problem);
+    }
+    if problems.is_empty() {
+        checks.report(Status::Ok, "limits can be applied");
+    }
+
+    Ok(finish(&checks))
+}
+
+fn check_program(
+    checks: &mut Checklist,
+    config: &Config,
+    config_dir: &Path,
+    name: &str,
+    program: &Program,
+) {
+    if let Some(source) = &program.expr {
+        checks.report(Status::Ok, format!("{name}: formula `{source}`"));
+        return;
+    }
+    if let Some(source) = &program.output_regex {
+        checks.report(Status::Ok, format!("{name}: output regex `{source}`"));
+        return;
+    }
+
+    let dir = program_dir(program, config_dir);
+    if !dir.is_dir() {
+        checks.report(
+            Status::Fail,
+            format!("{name}: workdir {} does not exist", dir.display()),
+        );
+        return;
+    }
+
+    match mount_host_paths(program, config_dir) {
+        Ok(paths) => {
+            for path in paths.iter().filter(|path| !path.exists()) {
+                checks.report(
+                    Status::Fail,
+                    format!("{name}: mount source {} does not exist", path.display()),
+                );
+            }
+        }
+        Err(error) => checks.report(Status::Fail, format!("{name}: {error:#}")),
+    }
+
+    match local_argv(program, config_dir, &RunSettings::new(config)) {
+        Ok(Some((argv, dir))) => check_executable(checks, name, &argv[0], &dir),
+        Ok(None) => {}
+        Err(error) => checks.report(Status::Fail, format!("{name}: {error:#}")),
+    }
+    if let Some(transform) = program
+        .input_transform_cmd
+        .as_ref()
+        .and_then(|cmd| cmd.first())
+    {
+        check_executable(
+            checks,
+            &format!("{name} input_transform_cmd"),
+            transform,
+            &dir,
+        );
+    }
+}
+
+fn check_executable(checks: &mut Checklist, name: &str, command: &str, dir: &Path) {
+    match find_executable(command, dir) {
+        Some(path) => checks.report(
+            Status::Ok,
+            format!("{name}: `{command}` found at {}", path.display()),
+        ),
+        None if command.contains('/') => checks.report(
+            Status::Fail,
+            format!(
+                "{name}: `{command}` is not an executable file (relative to {})",
+                dir.display()
+            ),
+        ),
+        None => checks.report(
+            Status::Fail,
+            format!("{name}: `{command}` not found on PATH"),
+        ),
+    }
+}
+
+/// Resolves `command` the way the spawned process will: a path is taken
+/// relative to `dir`, a bare name is searched on `PATH`.
+fn find_executable(command: &str, dir: &Path) -> Option<PathBuf> {
+    if command.contains('/') {
+        let path = dir.join(command);
+        return is_executable(&path).then_some(path);
+    }
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|entry| entry.join(command))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+fn check_images(checks: &mut Checklist, config: &Config) {
+    let mut images = config
+        .programs()
+        .filter_map(|program| program.image.as_deref())
+        .collect::<Vec<_>>();
+    images.sort_unstable();
+    images.dedup();
+    if images.is_empty() {
+        return;
+    }
+
+    let runtime = config.engine.container_runtime;
+    if let Err(error) = check_runtime(runtime) {
+        checks.report(Status::Fail, format!("{}: {error:#}", runtime.binary()));
+        return;
+    }
+    checks.report(Status::Ok, format!("{} is available", runtime.binary()));
+    for image in images {
+        match image_present(image, runtime) {
+            Ok(true) => checks.report(Status::Ok, format!("image {image} is present")),
+            Ok(false) => checks.report(
+                Status::Warn,
+                format!("image {image} is not present locally; it is pulled before the first run"),
+            ),
+            Err(error) => checks.report(Status::Fail, format!("image {image}: {error:#}")),
+        }
+    }
+}
+
+fn finish(checks: &Checklist) -> i32 {
+    println!();
+    println!(
+        "doctor: {} failure(s), {} warning(s)",
+        checks.failures, checks.warnings
+    );
+    i32::from(checks.failures > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_executable;
+
+    #[test]
+    fn resolves_commands_like_the_spawned_process() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(find_executable("sh", dir.path()).is_some());
+        assert!(find_executable("./solve", dir.path()).is_none());
+
+        let script = dir.path().join("solve");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        assert!(find_executable("./solve", dir.path()).is_none());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+            assert_eq!(
+                find_executable("./solve", dir.path()),
+                Some(dir.path().join("./solve"))
+            );
+        }
+    }
+}
@@ -1,15 +1,42 @@
 use anyhow::{bail, Context, Result};
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use rayon::prelude::*;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::config::{Config, Limits, Normalize};
-use crate::generator::{generate_inputs, parse_problem_inputs};
-use crate::runner::run_program;
+use crate::config::{self, Config, Example, LineCount, Normalize, Overrides, Program, Seed};
+use crate::diff;
+use crate::explain::print_stream;
+use crate::expr;
+use crate::generator::{
+    batch_sizes, check_input_size, edge_coverage, format_case, generate_values,
+    parse_problem_inputs, EdgeCoverage, Value,
+};
+use crate::git;
+use crate::junit;
+use crate::normalize::{
+    check_output_format, compare_outputs, compare_with_schema, normalize_output, LineChecker,
+};
+use crate::report::{
+    self, CandidateReport, FailureReport, Format, JsonCandidate, JsonReport, JsonSummary,
+    RunReport, Verdict,
+};
+use crate::repro;
+use crate::runner::{
+    self, cpu_limit_exceeded, describe_command, describe_status, ensure_image, program_files,
+    run_checker, run_interactive, run_program, run_program_checked, InteractiveOutput, RunOutput,
+    RunSettings,
+};
+use crate::state::{self, FailureRecord, Fingerprint};
 
 #[derive(Debug, Clone)]
 struct Failure {
@@ -17,331 +44,2178 @@ struct Failure {
     input: String,
     candidate_index: Option<usize>,
     candidate_name: String,
+    kind: FailureKind,
     reason: String,
+    /// Origin that judged the case when `engine.rotate_origins` picked one.
+    origin_name: Option<String>,
     origin_stdout: String,
     candidate_stdout: String,
     origin_stderr: String,
     candidate_stderr: String,
+    /// The candidate was killed at its first wrong line, so its stdout is
+    /// only a prefix.
+    candidate_stopped_early: bool,
 }
 
-pub fn run(config_path: &Path) -> Result<i32> {
-    let config_dir = config_path
-        .parent()
-        .map(Path::to_path_buf)
-        .unwrap_or_else(|| PathBuf::from("."));
-    let config_dir = std::fs::canonicalize(&config_dir).unwrap_or(config_dir);
+/// Verdict class of a failed case, as counted under `verdicts:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FailureKind {
+    /// Wrong or malformed output.
+    WrongAnswer,
+    /// An answer the checker or judge rejected; counted as `WA`.
+    Rejected,
+    /// Killed at the wall timeout or the CPU limit.
+    TimeLimit,
+    /// Exited with a status outside `success_exit_codes`.
+    RuntimeError,
+    /// nado could not run or judge the program.
+    Error,
+}
+
+impl FailureKind {
+    /// Histogram labels, in the order they are listed.
+    const LABELS: [&'static str; 4] = ["WA", "TLE", "RE", "ERR"];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::WrongAnswer | Self::Rejected => "WA",
+            Self::TimeLimit => "TLE",
+            Self::RuntimeError => "RE",
+            Self::Error => "ERR",
+        }
+    }
+
+    /// Whether `engine.retries` re-runs the candidate: a mismatch or a
+    /// timeout may be flaky, while a crash or a rejection is taken as final.
+    fn retryable(self) -> bool {
+        matches!(self, Self::WrongAnswer | Self::TimeLimit)
+    }
+}
+
+/// Why a candidate failed a case; displays as the reason.
+#[derive(Debug, Clone)]
+pub(crate) struct Rejection {
+    pub kind: FailureKind,
+    pub reason: String,
+}
+
+impl Rejection {
+    fn new(kind: FailureKind, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            reason: reason.into(),
+        }
+    }
+
+    fn wrong_answer(reason: impl Into<String>) -> Self {
+        Self::new(FailureKind::WrongAnswer, reason)
+    }
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+struct CaseContext<'a> {
+    config: &'a Config,
+    config_dir: &'a Path,
+    origin_names: &'a [String],
+    candidate_names: &'a [String],
+    failed_candidates: Option<&'a [AtomicBool]>,
+    warnings: &'a Mutex<Vec<Warning>>,
+    /// Hash of every candidate's normalized output, by candidate then case.
+    output_hashes: &'a Mutex<Vec<Vec<Option<String>>>>,
+    /// Spaces out case starts for `--max-cases-per-second`.
+    throttle: Option<&'a CaseThrottle>,
+    /// Print every origin and candidate output, for `--only-case`.
+    show_outputs: bool,
+    /// Time and memory each candidate used, as measured by the runner.
+    candidate_usage: &'a [CandidateUsage],
+    /// Owned copy of `config` for comparisons bounded by
+    /// `engine.compare_timeout_ms`, which run on their own thread.
+    compare_config: Option<Arc<Config>>,
+}
+
+/// A non-fatal finding about a candidate, reported after the summary.
+#[derive(Debug)]
+struct Warning {
+    case_index: usize,
+    candidate_index: usize,
+    message: String,
+}
+
+pub fn run(config_path: &Path, overrides: &Overrides) -> Result<i32> {
+    Ok(run_reported(config_path, overrides)?.0)
+}
+
+/// [`run`], also returning the verdicts; with `--repeat-run` those of the
+/// first run.
+pub(crate) fn run_reported(config_path: &Path, overrides: &Overrides) -> Result<(i32, RunReport)> {
+    // A deadline reached by an earlier config in `nado run` must not cut
+    // this one short.
+    runner::reset_abort();
+    if overrides.repeat_run <= 1 {
+        return run_once(config_path, overrides);
+    }
+    if overrides.since_last {
+        bail!("--repeat-run cannot be combined with --since-last");
+    }
+    if overrides.format == Format::Json {
+        bail!("--repeat-run cannot be combined with --format json");
+    }
+
+    // Every run must see the same cases, so a random seed is drawn once.
+    let (config, _) = config::load(config_path)?;
+    let mut overrides = overrides.clone();
+    if overrides.seed.unwrap_or(config.engine.seed) == Seed::Random {
+        let seed = Seed::Random.resolve();
+        println!("using seed={seed} for every run");
+        overrides.seed = Some(Seed::Fixed(seed));
+    }
+
+    let runs = overrides.repeat_run;
+    println!("== run 1/{runs} ==");
+    let (first_code, first_report) = run_once(config_path, &overrides)?;
+    for run in 2..=runs {
+        println!();
+        println!("== run {run}/{runs} ==");
+        let (_, report) = run_once(config_path, &overrides)?;
+
+        let changes = run_divergence(&first_report, &report);
+        if !changes.is_empty() {
+            println!();
+            println!("repeat-run: run {run} diverged from run 1:");
+            for change in changes {
+                println!("- {change}");
+            }
+            return Ok((1, first_report));
+        }
+    }
+
+    println!();
+    println!("repeat-run: results were stable across {runs} runs");
+    Ok((first_code, first_report))
+}
+
+/// Keeps only the candidates whose `group` is one of `groups`.
+fn select_groups(config: &mut Config, groups: &[String]) -> Result<()> {
+    let known = config
+        .candidate
+        .iter()
+        .filter_map(|candidate| candidate.group.as_deref())
+        .collect::<BTreeSet<_>>();
+    if let Some(unknown) = groups.iter().find(|group| !known.contains(group.as_str())) {
+        let known = known.into_iter().collect::<Vec<_>>();
+        bail!(
+            "no candidate is in group `{unknown}` (groups: {})",
+            if known.is_empty() {
+                "none".to_string()
+            } else {
+                known.join(", ")
+            }
+        );
+    }
+
+    config.candidate.retain(|candidate| {
+        candidate
+            .group
+            .as_ref()
+            .is_some_and(|group| groups.contains(group))
+    });
+    Ok(())
+}
+
+/// Keeps the candidates named in `only` (all of them when it is empty),
+/// minus those named in `skip`.
+fn select_candidates(config: &mut Config, only: &[String], skip: &[String]) -> Result<()> {
+    let known = config
+        .candidate
+        .iter()
+        .filter_map(|candidate| candidate.name.as_deref())
+        .collect::<Vec<_>>();
+    if let Some(unknown) = only
+        .iter()
+        .chain(skip)
+        .find(|name| !known.contains(&name.as_str()))
+    {
+        bail!(
+            "no candidate is named `{unknown}` (candidates: {})",
+            known.join(", ")
+        );
+    }
+
+    config.candidate.retain(|candidate| {
+        let name = candidate.name.as_ref();
+        (only.is_empty() || name.is_some_and(|name| only.contains(name)))
+            && !name.is_some_and(|name| skip.contains(name))
+    });
+    if config.candidate.is_empty() {
+        bail!("--candidate and --skip-candidate leave no candidate to run");
+    }
+    Ok(())
+}
+
+/// Verdict changes from `first` to `later`, plus candidates whose failure
+/// count moved while the verdict held.
+fn run_divergence(first: &RunReport, later: &RunReport) -> Vec<String> {
+    let mut changes = report::diff(first, later);
+    for candidate in &later.candidates {
+        let Some(before) = first.candidates.iter().find(|c| c.name == candidate.name) else {
+            continue;
+        };
+        if before.verdict == candidate.verdict && before.failures != candidate.failures {
+            changes.push(format!(
+                "{}: {} failure(s), was {}",
+                candidate.name, candidate.failures, before.failures
+            ));
+        }
+    }
+    changes
+}
+
+fn run_once(config_path: &Path, overrides: &Overrides) -> Result<(i32, RunReport)> {
+    let text = overrides.format == Format::Text;
+    if !text && (overrides.show_commands || overrides.coverage || overrides.baseline.is_some()) {
+        bail!("--show-commands, --coverage and --baseline print text; they cannot be combined with --format json");
+    }
+    let (mut config, config_dir) = config::load(config_path)?;
+    overrides.apply(&mut config);
+    let _watchdog = config.engine.hard_deadline_ms.map(Watchdog::start);
+    let _head_checkout = match &overrides.candidates_from_git {
+        Some(file) => Some(git::compare_with_head(&mut config, &config_dir, file)?),
+        None => None,
+    };
+
+    if config.origin.is_empty() && !config.examples_only() {
+        bail!("at least one origin is required");
+    }
+    if config.candidate.is_empty() {
+        bail!("at least one candidate is required");
+    }
+
+    // Pin default names first so skipping candidates does not renumber the rest.
+    let names = config.candidate_names();
+    for (candidate, name) in config.candidate.iter_mut().zip(names) {
+        candidate.name.get_or_insert(name);
+    }
+    if !overrides.groups.is_empty() {
+        select_groups(&mut config, &overrides.groups)?;
+    }
+    if !overrides.candidates.is_empty() || !overrides.skip_candidates.is_empty() {
+        select_candidates(
+            &mut config,
+            &overrides.candidates,
+            &overrides.skip_candidates,
+        )?;
+    }
+
+    let (generated_inputs, coverage) = case_inputs(&config, overrides)?;
+    // Every case is still generated, so case N is byte for byte the one a
+    // full run with the same seed produces.
+    let selected = match overrides.only_case {
+        Some(case) if case == 0 || case > generated_inputs.len() => bail!(
+            "--only-case {case} is out of range; this config has {} case(s)",
+            generated_inputs.len()
+        ),
+        Some(case) => case - 1..case,
+        None => 0..generated_inputs.len(),
+    };
+    // Only the cases that run may vouch for a pass.
+    let mut fingerprints =
+        candidate_fingerprints(&config, &config_dir, &generated_inputs[selected.clone()])?;
+    let mut passed = state::load_passed(&config_dir).unwrap_or_else(|error| {
+        eprintln!("warning: {error:#}");
+        state::PassedCandidates::default()
+    });
+    let mut cached = Vec::new();
+    if overrides.since_last {
+        let (skip, keep): (Vec<_>, Vec<_>) = config
+            .candidate
+            .drain(..)
+            .zip(fingerprints)
+            .partition(|(candidate, fingerprint)| {
+                fingerprint.is_some()
+                    && candidate
+                        .name
+                        .as_ref()
+                        .and_then(|name| passed.candidates.get(name))
+                        == fingerprint.as_ref()
+            });
+        cached = skip
+            .into_iter()
+            .filter_map(|(candidate, _)| candidate.name)
+            .collect::<Vec<_>>();
+        (config.candidate, fingerprints) = keep.into_iter().unzip();
+    }
+    if config.candidate.is_empty() {
+        if text {
+            println!(
+                "PASS: all {} candidate(s) unchanged since they last passed",
+                cached.len()
+            );
+            print_candidate_summary(&[], &[], &[], false, &cached, None, None, &[]);
+        } else {
+            report::print_json(&JsonReport {
+                summary: JsonSummary {
+                    verdict: Verdict::Pass,
+                    cases: generated_inputs.len(),
+                    completed: 0,
+                    candidates: cached.len(),
+                    workers: config.engine.workers,
+                },
+                candidates: json_candidates(&[], &[], &[], &cached),
+                engine_failure: None,
+            })?;
+        }
+        if let Some(path) = junit_path(&config, &config_dir, overrides) {
+            write_junit(
+                &path,
+                &config_dir,
+                generated_inputs.len(),
+                &[],
+                &[],
+                &[],
+                &[],
+                &cached,
+            )?;
+        }
+        let report = RunReport {
+            cases: generated_inputs.len(),
+            candidates: cached_reports(&cached).collect(),
+        };
+        if let Some(path) = &overrides.report {
+            report::save(path, &report)?;
+        }
+        return Ok((0, report));
+    }
+
+    apply_memory_budget(&mut config)?;
+    let pool = build_pool(&config)?;
+    let origin_names = config.origin_names();
+    let candidate_names = config.candidate_names();
+    if overrides.show_commands {
+        print_commands(&config, &config_dir, &origin_names, &candidate_names)?;
+    }
+    prepare_images(&config)?;
+
+    if text {
+        println!(
+            "nado: cases={}, candidates={}, workers={}, timeout={}ms",
+            selected.len(),
+            config.candidate.len(),
+            config.engine.workers,
+            config.limits.wall_ms.unwrap_or(config.engine.timeout_ms)
+        );
+    }
+    let progress = build_progress_bar(selected.len(), text);
+    let failed_candidates = config.engine.stop_on_first_fail.then(|| {
+        Arc::new(
+            (0..config.candidate.len())
+                .map(|_| AtomicBool::new(false))
+                .collect::<Vec<_>>(),
+        )
+    });
+
+    let throttle = overrides.max_cases_per_second.map(CaseThrottle::new);
+    let warnings = Mutex::new(Vec::new());
+    let output_hashes = Mutex::new(vec![
+        vec![None; generated_inputs.len()];
+        config.candidate.len()
+    ]);
+    let candidate_usage = (0..config.candidate.len())
+        .map(|_| CandidateUsage::default())
+        .collect::<Vec<_>>();
+    let ctx = CaseContext {
+        candidate_usage: &candidate_usage,
+        config: &config,
+        config_dir: &config_dir,
+        origin_names: &origin_names,
+        candidate_names: &candidate_names,
+        failed_candidates: failed_candidates.as_ref().map(|flags| flags.as_slice()),
+        warnings: &warnings,
+        output_hashes: &output_hashes,
+        throttle: throttle.as_ref(),
+        show_outputs: overrides.only_case.is_some() && text,
+        compare_config: config
+            .engine
+            .compare_timeout_ms
+            .map(|_| Arc::new(config.clone())),
+    };
+
+    let progress = progress.clone();
+    let completed = AtomicUsize::new(0);
+    let mut failures = pool.install(|| {
+        generated_inputs
+            .par_iter()
+            .enumerate()
+            .filter(|(idx, _)| selected.contains(idx))
+            .flat_map_iter(|(idx, input)| {
+                if runner::aborted() {
+                    return Vec::new();
+                }
+                let result = run_case_or_failure(idx, input, &ctx);
+                // A case cut off by the deadline has no trustworthy verdict.
+                if runner::aborted() {
+                    return Vec::new();
+                }
+                completed.fetch_add(1, Ordering::Relaxed);
+                progress.inc(1);
+                result
+            })
+            .collect::<Vec<_>>()
+    });
+
+    progress.finish_and_clear();
+    let completed = completed.into_inner();
+    let deadline_reached = config.engine.hard_deadline_ms.is_some() && runner::aborted();
+    failures.sort_by_key(|f| (f.case_index, f.candidate_index.unwrap_or(usize::MAX)));
+    if let Some(dir) = &config.engine.save_failures {
+        let dir = config_dir.join(dir);
+        save_failures(&dir, &failures)?;
+        if !failures.is_empty() && text {
+            println!(
+                "{} failing case(s) saved to {}",
+                failures.len(),
+                dir.display()
+            );
+        }
+    }
+
+    let mut infra_failures = Vec::new();
+    let mut candidate_failures = vec![Vec::new(); config.candidate.len()];
+    for failure in failures {
+        if let Some(candidate_idx) = failure.candidate_index {
+            if config.engine.stop_on_first_fail && !candidate_failures[candidate_idx].is_empty() {
+                continue;
+            }
+            candidate_failures[candidate_idx].push(failure);
+        } else {
+            infra_failures.push(failure);
+        }
+    }
+
+    let expect_fail = config
+        .candidate
+        .iter()
+        .map(|candidate| candidate.expect_fail)
+        .collect::<Vec<_>>();
+    let has_infra_failure = !infra_failures.is_empty();
+    let fail_under = overrides.fail_under.map(|threshold| (threshold, completed));
+    let histogram_cases = (!config.engine.stop_on_first_fail).then_some(completed);
+    let verdicts = candidate_failures
+        .iter()
+        .zip(&expect_fail)
+        .map(|(per_candidate, &expect_fail)| {
+            overall_verdict(
+                per_candidate.len(),
+                expect_fail,
+                has_infra_failure,
+                fail_under,
+            )
+        })
+        .collect::<Vec<_>>();
+    let failed_count = verdicts.iter().filter(|v| **v == Verdict::Fail).count();
+    // Failures of `expect_fail` candidates are the desired outcome, and those
+    // tolerated by `--fail-under` are within budget; neither is news.
+    let unexpected_failures = candidate_failures
+        .iter()
+        .zip(&expect_fail)
+        .zip(&verdicts)
+        .filter(|((_, &expect_fail), &verdict)| !expect_fail && verdict == Verdict::Fail)
+        .map(|((per_candidate, _), _)| per_candidate);
+
+    let first_failure = infra_failures
+        .iter()
+        .chain(unexpected_failures.clone().flatten())
+        .min_by_key(|f| f.case_index);
+    let recorded = match first_failure {
+        Some(failure) => state::save_last_failure(
+            &config_dir,
+            &FailureRecord {
+                case_index: failure.case_index,
+                candidate_name: failure.candidate_name.clone(),
+                reason: failure.reason.clone(),
+                input: failure.input.clone(),
+            },
+        ),
+        None => state::clear_last_failure(&config_dir),
+    };
+    if let Err(error) = recorded {
+        eprintln!("warning: {error:#}");
+    }
+    if let (Some(path), Some(failure)) = (&overrides.emit_repro, first_failure) {
+        let candidate = failure.candidate_index.map(|idx| &config.candidate[idx]);
+        repro::write(
+            path,
+            &config,
+            &config_dir,
+            failure.case_index,
+            candidate,
+            &failure.reason,
+            &failure.input,
+        )?;
+        // Under --format json, stdout holds nothing but the report.
+        if text {
+            println!("reproducer written to {}", path.display());
+        } else {
+            eprintln!("reproducer written to {}", path.display());
+        }
+    }
+    for ((name, verdict), fingerprint) in candidate_names.iter().zip(&verdicts).zip(fingerprints) {
+        match fingerprint {
+            Some(fingerprint) if *verdict == Verdict::Pass => {
+                passed.candidates.insert(name.clone(), fingerprint);
+            }
+            _ => {
+                passed.candidates.remove(name);
+            }
+        }
+    }
+    if let Err(error) = state::save_passed(&config_dir, &passed) {
+        eprintln!("warning: {error:#}");
+    }
+
+    let report = RunReport {
+        cases: completed,
+        candidates: candidate_names
+            .iter()
+            .zip(&verdicts)
+            .zip(&candidate_failures)
+            .map(|((name, verdict), failures)| CandidateReport {
+                name: name.clone(),
+                verdict: *verdict,
+                failures: failures.len(),
+            })
+            .chain(cached_reports(&cached))
+            .collect(),
+    };
+    let baseline_changes = match &overrides.baseline {
+        Some(path) => Some(report::diff(&report::load(path)?, &report)),
+        None => None,
+    };
+    if let Some(path) = &overrides.report {
+        report::save(path, &report)?;
+    }
+    if let Some(path) = junit_path(&config, &config_dir, overrides) {
+        let times = candidate_usage
+            .iter()
+            .map(CandidateUsage::total)
+            .collect::<Vec<_>>();
+        write_junit(
+            &path,
+            &config_dir,
+            completed,
+            &candidate_names,
+            &verdicts,
+            &candidate_failures,
+            &times,
+            &cached,
+        )?;
+    }
+
+    if !text {
+        let passed = failed_count == 0 && !has_infra_failure;
+        report::print_json(&JsonReport {
+            summary: JsonSummary {
+                verdict: if passed { Verdict::Pass } else { Verdict::Fail },
+                cases: generated_inputs.len(),
+                completed,
+                candidates: candidate_names.len() + cached.len(),
+                workers: config.engine.workers,
+            },
+            candidates: json_candidates(&candidate_names, &verdicts, &candidate_failures, &cached),
+            engine_failure: infra_failures.first().map(failure_report),
+        })?;
+        return Ok((i32::from(!passed), report));
+    }
+
+    if deadline_reached {
+        println!(
+            "deadline reached: engine.hard_deadline_ms={} passed after {completed} of {} case(s); \
+             verdicts cover only the completed cases",
+            config.engine.hard_deadline_ms.unwrap_or_default(),
+            generated_inputs.len()
+        );
+    }
+    if failed_count == 0 && !has_infra_failure {
+        if expect_fail.contains(&true) {
+            println!("PASS: all candidates behaved as expected");
+        } else if let Some(threshold) = overrides.fail_under {
+            println!(
+                "PASS: all candidates matched at least {:.2}% of cases",
+                threshold * 100.0
+            );
+        } else {
+            println!("PASS: all candidates matched origin");
+        }
+        print_candidate_summary(
+            &candidate_names,
+            &candidate_failures,
+            &expect_fail,
+            false,
+            &cached,
+            fail_under,
+            histogram_cases,
+            &candidate_usage,
+        );
+        print_warnings(&candidate_names, warnings);
+        print_identical_candidates(&candidate_names, output_hashes);
+        print_run_extras(overrides, &coverage, baseline_changes.as_deref());
+        return Ok((0, report));
+    }
+
+    println!(
+        "FAIL: {} / {} candidate(s) failed",
+        failed_count,
+        candidate_names.len()
+    );
+    print_candidate_summary(
+        &candidate_names,
+        &candidate_failures,
+        &expect_fail,
+        has_infra_failure,
+        &cached,
+        fail_under,
+        histogram_cases,
+        &candidate_usage,
+    );
+    print_warnings(&candidate_names, warnings);
+    print_identical_candidates(&candidate_names, output_hashes);
+    print_run_extras(overrides, &coverage, baseline_changes.as_deref());
+
+    if let Some(first_infra) = infra_failures.first() {
+        println!();
+        println!("origin/engine failure (candidate verdict may be incomplete):");
+        print_failure(first_infra, &config);
+    }
+
+    for per_candidate in unexpected_failures.filter(|f| !f.is_empty()) {
+        if per_candidate.len() == 1 {
+            println!();
+            print_failure(&per_candidate[0], &config);
+            continue;
+        }
+
+        let clusters = cluster_failures(per_candidate);
+        println!();
+        println!(
+            "{}: {} failure(s) across {} distinct signature(s)",
+            per_candidate[0].candidate_name,
+            per_candidate.len(),
+            clusters.len()
+        );
+        for (signature, members) in &clusters {
+            println!("- {}x {}", members.len(), signature);
+        }
+        for failure in reported_failures(&clusters, config.engine.max_reported_failures) {
+            println!();
+            print_failure(failure, &config);
+        }
+    }
+
+    Ok((1, report))
+}
+
+/// Hands out case start times at least `1 / rate` seconds apart, whichever
+/// worker asks. Unlike the worker count, this caps throughput, not
+/// concurrency.
+struct CaseThrottle {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl CaseThrottle {
+    fn new(cases_per_second: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / cases_per_second),
+            next: Mutex::new(None),
+        }
+    }
+
+    fn acquire(&self) {
+        let slot = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next.map_or(now, |next| next.max(now));
+            *next = Some(slot + self.interval);
+            slot
+        };
+        thread::sleep(slot.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// Aborts the run once `engine.hard_deadline_ms` passes, unless dropped first.
+struct Watchdog {
+    done: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    fn start(deadline_ms: u64) -> Self {
+        runner::reset_abort();
+        let (done, finished) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let timeout = Duration::from_millis(deadline_ms);
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                runner::abort_all();
+            }
+        });
+        Self {
+            done: Some(done),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // Hanging up wakes the thread without aborting.
+        self.done.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        runner::reset_abort();
+    }
+}
+
+/// Fingerprint of each candidate together with everything its verdict
+/// depends on: the generated inputs, limits, comparison settings, and the
+/// origins and judge it is checked against. `None` where a program has no
+/// files to hash, since its changes could not be noticed.
+fn candidate_fingerprints(
+    config: &Config,
+    config_dir: &Path,
+    inputs: &[String],
+) -> Result<Vec<Option<String>>> {
+    let settings = RunSettings::new(config);
+    let mut shared = Fingerprint::default();
+    for input in inputs {
+        shared.update(input.as_bytes());
+    }
+    shared.update(format!("{:?}", config.problem.examples).as_bytes());
+    shared.update(format!("{:?}", config.normalize).as_bytes());
+    shared.update(format!("{settings:?}").as_bytes());
+    shared.update(&config.engine.timeout_ms.to_le_bytes());
+
+    let mut references_known = true;
+    let references = config
+        .origin
+        .iter()
+        .chain(config.judge.as_ref())
+        .chain(config.checker.as_ref());
+    for program in references {
+        references_known &= fingerprint_program(&mut shared, program, config_dir, &settings)?;
+    }
+
+    config
+        .candidate
+        .iter()
+        .map(|candidate| {
+            let mut fingerprint = shared;
+            let known = fingerprint_program(&mut fingerprint, candidate, config_dir, &settings)?;
+            Ok((references_known && known).then(|| fingerprint.hex()))
+        })
+        .collect()
+}
+
+/// Feeds `program`'s settings and file contents into `fingerprint`; returns
+/// whether any file was found.
+fn fingerprint_program(
+    fingerprint: &mut Fingerprint,
+    program: &Program,
+    config_dir: &Path,
+    settings: &RunSettings,
+) -> Result<bool> {
+    fingerprint.update(format!("{program:?}").as_bytes());
+    if program.expr.is_some() || program.output_regex.is_some() {
+        return Ok(true);
+    }
+    let files = program_files(program, config_dir, settings)?;
+    for file in &files {
+        let contents =
+            std::fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+        fingerprint.update(file.to_string_lossy().as_bytes());
+        fingerprint.update(&contents);
+    }
+    Ok(!files.is_empty())
+}
+
+/// Writes every failure as `case-<N>-<candidate>.txt` holding its input, next
+/// to `.expected` and `.actual` files with the origin's and the candidate's
+/// output.
+fn save_failures(dir: &Path, failures: &[Failure]) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    for failure in failures {
+        let name = failure
+            .candidate_name
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+        let stem = format!("case-{}-{name}", failure.case_index + 1);
+        for (extension, content) in [
+            ("txt", &failure.input),
+            ("expected", &failure.origin_stdout),
+            ("actual", &failure.candidate_stdout),
+        ] {
+            // Not `with_extension`: a name such as `sol.py` already has a dot.
+            let path = dir.join(format!("{stem}.{extension}"));
+            std::fs::write(&path, content)
+                .with_context(|| format!("failed to save failing case: {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// `--junit`, else `engine.junit_path` resolved against the config's directory.
+fn junit_path(config: &Config, config_dir: &Path, overrides: &Overrides) -> Option<PathBuf> {
+    overrides.junit.clone().or_else(|| {
+        config
+            .engine
+            .junit_path
+            .as_ref()
+            .map(|path| config_dir.join(path))
+    })
+}
+
+/// Writes the JUnit report: one test case per candidate, cached ones skipped.
+#[allow(clippy::too_many_arguments)]
+fn write_junit(
+    path: &Path,
+    config_dir: &Path,
+    cases: usize,
+    candidate_names: &[String],
+    verdicts: &[Verdict],
+    candidate_failures: &[Vec<Failure>],
+    times: &[Duration],
+    cached: &[String],
+) -> Result<()> {
+    let suite = config_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "nado".to_string());
+    let ran = candidate_names
+        .iter()
+        .zip(verdicts)
+        .zip(candidate_failures)
+        .zip(times)
+        .map(|(((name, verdict), failures), time)| {
+            let outcome = match (verdict, failures.first()) {
+                (Verdict::Pass, _) => junit::Outcome::Passed,
+                (_, None) => junit::Outcome::Unknown,
+                (_, Some(first)) => junit::Outcome::Failed {
+                    failures: failures.len(),
+                    case: first.case_index + 1,
+                    reason: first.reason.clone(),
+                    input: first.input.clone(),
+                    expected: first.origin_stdout.clone(),
+                    actual: first.candidate_stdout.clone(),
+                },
+            };
+            junit::TestCase {
+                name: name.clone(),
+                time: *time,
+                outcome,
+            }
+        });
+    let cached = cached.iter().map(|name| junit::TestCase {
+        name: name.clone(),
+        time: Duration::ZERO,
+        outcome: junit::Outcome::Cached,
+    });
+    junit::write(path, &suite, cases, &ran.chain(cached).collect::<Vec<_>>())
+}
+
+/// Per-candidate entries of a `--format json` report, cached candidates last.
+fn json_candidates(
+    candidate_names: &[String],
+    verdicts: &[Verdict],
+    candidate_failures: &[Vec<Failure>],
+    cached: &[String],
+) -> Vec<JsonCandidate> {
+    let ran = candidate_names
+        .iter()
+        .zip(verdicts)
+        .zip(candidate_failures)
+        .map(|((name, verdict), failures)| JsonCandidate {
+            name: name.clone(),
+            verdict: *verdict,
+            mismatches: failures.len(),
+            cached: false,
+            first_failure: failures.first().map(failure_report),
+        });
+    let cached = cached.iter().map(|name| JsonCandidate {
+        name: name.clone(),
+        verdict: Verdict::Pass,
+        mismatches: 0,
+        cached: true,
+        first_failure: None,
+    });
+    ran.chain(cached).collect()
+}
+
+fn failure_report(failure: &Failure) -> FailureReport {
+    FailureReport {
+        case: failure.case_index + 1,
+        candidate: failure.candidate_name.clone(),
+        origin: failure.origin_name.clone(),
+        reason: failure.reason.clone(),
+        input: failure.input.clone(),
+        origin_stdout: failure.origin_stdout.clone(),
+        candidate_stdout: failure.candidate_stdout.clone(),
+        origin_stderr: failure.origin_stderr.clone(),
+        candidate_stderr: failure.candidate_stderr.clone(),
+    }
+}
+
+fn cached_reports(cached: &[String]) -> impl Iterator<Item = CandidateReport> + '_ {
+    cached.iter().map(|name| CandidateReport {
+        name: name.clone(),
+        verdict: Verdict::Pass,
+        failures: 0,
+    })
+}
+
+/// The cases to run: the one given with `--input`/`--input-file`, or
+/// `problem.examples` followed by the generated ones.
+pub(crate) fn case_inputs(
+    config: &Config,
+    overrides: &Overrides,
+) -> Result<(Vec<String>, Vec<EdgeCoverage>)> {
+    let input = match (&overrides.input, &overrides.input_file) {
+        (Some(input), _) => input.clone(),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read input file: {}", path.display()))?,
+        (None, None) => {
+            let (generated, coverage) = match config.examples_only() {
+                true => (Vec::new(), Vec::new()),
+                false => generate_cases(config)?,
+            };
+            let inputs = config
+                .problem
+                .examples
+                .iter()
+                .map(|example| literal_input(config, example.input.clone()))
+                .chain(generated)
+                .collect();
+            return Ok((inputs, coverage));
+        }
+    };
+    Ok((vec![literal_input(config, input)], Vec::new()))
+}
+
+/// A hand-written case as programs should receive it: literals typed on the
+/// command line or in TOML usually lack the final newline.
+pub(crate) fn literal_input(config: &Config, mut input: String) -> String {
+    if config.problem.trailing_newline && !input.ends_with('\n') {
+        input.push('\n');
+    }
+    input
+}
+
+/// Generates the case inputs, along with which declared edge values they cover.
+pub(crate) fn generate_cases(config: &Config) -> Result<(Vec<String>, Vec<EdgeCoverage>)> {
+    let parsed_inputs = parse_problem_inputs(&config.problem)
+        .context("failed to parse [problem.inputs] constraints")?;
+    let problem = &config.problem;
+    let t_range = problem
+        .multi_test
+        .then(|| (problem.t_min.unwrap_or(1), problem.t_max.unwrap_or(1)));
+    let per_input = t_range.map_or(1, |(_, t_max)| t_max);
+    check_input_size(&parsed_inputs, config.engine.max_input_bytes, per_input)?;
+    let seed = config.engine.seed.resolve();
+    // On stderr: stdout carries JSON reports and `nado sample` cases.
+    if config.engine.seed == Seed::Random {
+        eprintln!("using seed={seed}");
+    }
+
+    // Batches draw their cases from one stream, so edge and partition seeds
+    // are spread over the batches instead of repeated in each.
+    let batches = match t_range {
+        Some((t_min, t_max)) => batch_sizes(config.engine.cases, t_min, t_max, seed)?,
+        None => vec![1; config.engine.cases],
+    };
+    let values = generate_values(&parsed_inputs, batches.iter().sum(), seed, &config.pbt)
+        .context("failed to generate test inputs")?;
+    let coverage = edge_coverage(&parsed_inputs, &values)?;
+    let mut cases = values.iter().map(|case| format_case(case));
+    let mut inputs = batches
+        .iter()
+        .map(|&size| {
+            let mut input = if problem.multi_test {
+                format!("{size}\n")
+            } else {
+                String::new()
+            };
+            input.extend(cases.by_ref().take(size));
+            input
+        })
+        .collect::<Vec<_>>();
+
+    if !config.problem.trailing_newline {
+        for input in &mut inputs {
+            if input.ends_with('\n') {
+                input.pop();
+            }
+        }
+    }
+
+    Ok((inputs, coverage))
+}
+
+pub(crate) fn print_coverage(coverage: &[EdgeCoverage]) {
+    println!("edge coverage:");
+    for input in coverage {
+        let total = input.hit.len() + input.missed.len();
+        let missed = if input.missed.is_empty() {
+            String::new()
+        } else {
+            let values = input.missed.iter().map(Value::label).collect::<Vec<_>>();
+            format!(" (missing: {})", values.join(", "))
+        };
+        println!("- {}: {}/{total}{missed}", input.name, input.hit.len());
+    }
+}
+
+pub(crate) fn prepare_images(config: &Config) -> Result<()> {
+    let images = config
+        .programs()
+        .filter_map(|program| program.image.as_deref())
+        .collect::<BTreeSet<_>>();
+
+    for image in images {
+        ensure_image(
+            image,
+            config.engine.image_pull_retries,
+            config.engine.container_runtime,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Logs the resolved command of every program once, so path and quoting
+/// mistakes are visible before any case runs.
+pub(crate) fn print_commands(
+    config: &Config,
+    config_dir: &Path,
+    origin_names: &[String],
+    candidate_names: &[String],
+) -> Result<()> {
+    let settings = RunSettings::new(config);
+    let labelled = origin_names
+        .iter()
+        .zip(&config.origin)
+        .chain(candidate_names.iter().zip(&config.candidate));
+    for (name, program) in labelled {
+        if let Some(source) = &program.expr {
+            println!("command {name}: expr {source}");
+            continue;
+        }
+        let command = describe_command(program, config_dir, &settings)
+            .with_context(|| format!("failed to resolve command for {name}"))?;
+        println!("command {name}: {command}");
+    }
+    if let Some(judge) = &config.judge {
+        let command = describe_command(judge, config_dir, &settings)
+            .context("failed to resolve command for judge")?;
+        println!("command judge: {command} <case-file>");
+    }
+    Ok(())
+}
+
+/// Lowers `engine.workers` so that every worker's programs running at once
+/// stay within `engine.total_memory_mb`, and says so when it does.
+pub(crate) fn apply_memory_budget(config: &mut Config) -> Result<()> {
+    let (Some(total_mb), Some(memory_mb)) =
+        (config.engine.total_memory_mb, config.limits.memory_mb)
+    else {
+        return Ok(());
+    };
+
+    // An interactive case runs the program and the judge side by side.
+    let per_worker_mb = memory_mb * if config.judge.is_some() { 2 } else { 1 };
+    let fit = usize::try_from(total_mb / per_worker_mb.max(1)).unwrap_or(usize::MAX);
+    if fit == 0 {
+        bail!(
+            "engine.total_memory_mb={total_mb} is below the {per_worker_mb}MB a single worker may use"
+        );
+    }
+    if fit < config.engine.workers {
+        // On stderr, so it stays out of a `--format json` report.
+        eprintln!(
+            "memory budget: engine.total_memory_mb={total_mb} fits {fit} worker(s) at \
+             {per_worker_mb}MB each; running {fit} instead of {}",
+            config.engine.workers
+        );
+        config.engine.workers = fit;
+    }
+    Ok(())
+}
+
+pub(crate) fn build_pool(config: &Config) -> Result<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.engine.workers)
+        .build()
+        .context("failed to build worker pool")
+}
+
+/// Runs every origin on `input` in declaration order.
+pub(crate) fn run_origins(
+    config: &Config,
+    config_dir: &Path,
+    origin_names: &[String],
+    input: &str,
+) -> Result<Vec<RunOutput>> {
+    let mut outputs = Vec::with_capacity(config.origin.len());
+
+    for (origin_idx, origin) in config.origin.iter().enumerate() {
+        let output = run_origin(config, config_dir, origin, input)
+            .with_context(|| format!("{} execution failed", origin_names[origin_idx]))?;
+        outputs.push(output);
+    }
+
+    Ok(outputs)
+}
+
+/// What a command origin reads for the generated case `input`: the case
+/// itself, or `problem.origin_input_template` rendered with it.
+pub(crate) fn origin_input<'a>(config: &Config, input: &'a str) -> Result<Cow<'a, str>> {
+    match &config.problem.origin_template {
+        Some(template) => Ok(Cow::Owned(
+            template
+                .render(&config.problem, input)
+                .context("failed to render origin_input_template")?,
+        )),
+        None => Ok(Cow::Borrowed(input)),
+    }
+}
+
+/// `program` with its `{N}` arguments filled from the case `input`, and what
+/// it then reads on stdin: nothing when it takes its inputs as arguments,
+/// otherwise `input` itself.
+pub(crate) fn case_program<'a>(
+    config: &Config,
+    program: &'a Program,
+    input: &'a str,
+) -> Result<(Cow<'a, Program>, &'a str)> {
+    if expr::arg_positions(&program.cmd).is_empty() {
+        return Ok((Cow::Borrowed(program), input));
+    }
+    let mut filled = program.clone();
+    filled.cmd = expr::fill_args(&config.problem, &program.cmd, input)
+        .context("failed to fill command-line arguments from the case")?;
+    Ok((Cow::Owned(filled), ""))
+}
+
+/// Runs `origin` on `input`, or evaluates its `expr` in-process.
+pub(crate) fn run_origin(
+    config: &Config,
+    config_dir: &Path,
+    origin: &Program,
+    input: &str,
+) -> Result<RunOutput> {
+    let Some(source) = &origin.expr else {
+        let timeout_ms = config.timeout_ms(origin);
+        let (origin, input) = case_program(config, origin, input)?;
+        let input = origin_input(config, input)?;
+        return run_program(
+            &origin,
+            &input,
+            config_dir,
+            timeout_ms,
+            &RunSettings::new(config),
+        );
+    };
+
+    let vars = expr::bind_inputs(&config.problem, input)?;
+    let value = expr::parse(source)?.eval(&vars)?;
+    Ok(RunOutput {
+        status: config.clean_exit(),
+        stdout: format!("{value}\n"),
+        stderr: String::new(),
+        timed_out: false,
+        stopped_early: false,
+        duration: Duration::ZERO,
+        max_rss_kb: None,
+    })
+}
+
+fn run_case_or_failure(idx: usize, input: &str, ctx: &CaseContext) -> Vec<Failure> {
+    run_case(idx, input, ctx).unwrap_or_else(|e| vec![engine_failure(idx, input, &e)])
+}
+
+fn engine_failure(idx: usize, input: &str, error: &anyhow::Error) -> Failure {
+    Failure {
+        case_index: idx,
+        input: input.to_string(),
+        candidate_index: None,
+        candidate_name: "engine".to_string(),
+        kind: FailureKind::Error,
+        reason: format!("runner error: {error:#}"),
+        origin_name: None,
+        origin_stdout: String::new(),
+        candidate_stdout: String::new(),
+        origin_stderr: String::new(),
+        candidate_stderr: String::new(),
+        candidate_stopped_early: false,
+    }
+}
+
+fn origin_failure(idx: usize, input: &str, reason: String, origin: &RunOutput) -> Failure {
+    Failure {
+        case_index: idx,
+        input: input.to_string(),
+        candidate_index: None,
+        candidate_name: "origin".to_string(),
+        kind: FailureKind::Error,
+        reason,
+        origin_name: None,
+        origin_stdout: origin.stdout.clone(),
+        candidate_stdout: String::new(),
+        origin_stderr: origin.stderr.clone(),
+        candidate_stderr: String::new(),
+        candidate_stopped_early: false,
+    }
+}
+
+fn candidate_failure(
+    idx: usize,
+    input: &str,
+    candidate_idx: usize,
+    ctx: &CaseContext,
+    rejection: Rejection,
+    origin: &RunOutput,
+    got: Option<RunOutput>,
+) -> Failure {
+    let (candidate_stdout, candidate_stderr, candidate_stopped_early) = got
+        .map(|got| (got.stdout, got.stderr, got.stopped_early))
+        .unwrap_or_default();
+
+    Failure {
+        case_index: idx,
+        input: input.to_string(),
+        candidate_index: Some(candidate_idx),
+        candidate_name: ctx.candidate_names[candidate_idx].clone(),
+        kind: rejection.kind,
+        reason: rejection.reason,
+        origin_name: None,
+        origin_stdout: origin.stdout.clone(),
+        candidate_stdout,
+        origin_stderr: origin.stderr.clone(),
+        candidate_stderr,
+        candidate_stopped_early,
+    }
+}
+
+fn run_case(idx: usize, input: &str, ctx: &CaseContext) -> Result<Vec<Failure>> {
+    let config = ctx.config;
+    if let Some(throttle) = ctx.throttle {
+        throttle.acquire();
+    }
+    if let Some(judge) = &config.judge {
+        return run_interactive_case(idx, input, judge, ctx);
+    }
+
+    let rotated = rotated_origin(config, idx, input);
+    let origin_name = match config.problem.examples.get(idx) {
+        Some(_) => Some(format!("example #{}", idx + 1)),
+        None => rotated.map(|pick| ctx.origin_names[pick].clone()),
+    };
+    let (origin_names, mut origins) = match (config.problem.examples.get(idx), rotated) {
+        (Some(example), _) => (
+            std::slice::from_ref(origin_name.as_ref().expect("examples are named")),
+            vec![example_output(example, config)],
+        ),
+        (None, Some(pick)) => {
+            let name = &ctx.origin_names[pick];
+            let output = run_origin(config, ctx.config_dir, &config.origin[pick], input)
+                .with_context(|| format!("{name} execution failed"))?;
+            (std::slice::from_ref(name), vec![output])
+        }
+        (None, None) => (
+            ctx.origin_names,
+            run_origins(config, ctx.config_dir, ctx.origin_names, input)?,
+        ),
+    };
+
+    if ctx.show_outputs {
+        println!("case #{} input:\n{}", idx + 1, input.trim_end());
+        for (name, output) in origin_names.iter().zip(&origins) {
+            print_output(name, output);
+        }
+    }
+    let (expected, origin) = match settle_expected(origin_names, &origins, config) {
+        Ok((expected, representative)) => (expected, origins.swap_remove(representative)),
+        Err(reason) => {
+            let failure = origin_failure(idx, input, reason, &origins[0]);
+            return Ok(with_origin_name(vec![failure], origin_name));
+        }
+    };
+    let mut failures = Vec::new();
+
+    'candidates: for (candidate_idx, candidate) in config.candidate.iter().enumerate() {
+        if should_skip_candidate(ctx.failed_candidates, candidate_idx) {
+            continue;
+        }
+
+        let candidate_timeout_ms = config.timeout_ms(candidate);
+        // The origin's answer is settled once; only the candidate re-runs
+        // under `engine.retries`.
+        let mut retries = 0;
+        let (got, verdict) = loop {
+            // Schema columns may match tokens that differ, and a checker may
+            // accept any answer, so only the whole output can be judged.
+            let checker = match (&config.problem.output_schema, &config.checker) {
+                (None, None) => LineChecker::new(&expected, &config.normalize),
+                _ => None,
+            };
+            let run =
+                case_program(config, candidate, input).and_then(
+                    |(candidate, stdin)| match checker {
+                        Some(mut checker) => run_program_checked(
+                            &candidate,
+                            stdin,
+                            ctx.config_dir,
+                            candidate_timeout_ms,
+                            &RunSettings::new(config),
+                            Box::new(move |line| checker.accept(line)),
+                        ),
+                        None => run_program(
+                            &candidate,
+                            stdin,
+                            ctx.config_dir,
+                            candidate_timeout_ms,
+                            &RunSettings::new(config),
+                        ),
+                    },
+                );
+            let got = match run {
+                Ok(output) => output,
+                Err(error) => {
+                    let rejection = Rejection::new(
+                        FailureKind::Error,
+                        format!("candidate runner error: {error:#}"),
+                    );
+                    failures.push(candidate_failure(
+                        idx,
+                        input,
+                        candidate_idx,
+                        ctx,
+                        rejection,
+                        &origin,
+                        None,
+                    ));
+                    mark_candidate_failed(ctx.failed_candidates, candidate_idx);
+                    continue 'candidates;
+                }
+            };
+            let verdict = match bounded_verdict(&got, &expected, input, ctx) {
+                Ok(verdict) => verdict,
+                Err(error) => {
+                    ctx.candidate_usage[candidate_idx].record(&got);
+                    let rejection = Rejection::new(
+                        FailureKind::Error,
+                        format!("candidate judging error: {error:#}"),
+                    );
+                    failures.push(candidate_failure(
+                        idx,
+                        input,
+                        candidate_idx,
+                        ctx,
+                        rejection,
+                        &origin,
+                        Some(got),
+                    ));
+                    mark_candidate_failed(ctx.failed_candidates, candidate_idx);
+                    continue 'candidates;
+                }
+            };
+            let retryable = verdict
+                .as_ref()
+                .is_some_and(|rejection| rejection.kind.retryable());
+            if retryable && retries < config.engine.retries {
+                // Counted on the first retry, before any way out of the loop.
+                if retries == 0 {
+                    ctx.candidate_usage[candidate_idx].record_retry();
+                }
+                retries += 1;
+                continue;
+            }
+            break (got, verdict);
+        };
+        ctx.candidate_usage[candidate_idx].record(&got);
+
+        if ctx.show_outputs {
+            print_output(&ctx.candidate_names[candidate_idx], &got);
+        }
+        if !got.stopped_early {
+            record_output_hash(idx, candidate_idx, &got.stdout, ctx);
+        }
+        if config.problem.probe_trailing_newline {
+            probe_trailing_newline(idx, input, candidate_idx, &expected, verdict.is_none(), ctx);
+        }
+        let Some(rejection) = verdict else {
+            continue;
+        };
+
+        failures.push(candidate_failure(
+            idx,
+            input,
+            candidate_idx,
+            ctx,
+            rejection,
+            &origin,
+            Some(got),
+        ));
+        mark_candidate_failed(ctx.failed_candidates, candidate_idx);
+    }
+
+    Ok(with_origin_name(failures, origin_name))
+}
+
+/// The expected output of `example` as if an origin had printed it.
+pub(crate) fn example_output(example: &Example, config: &Config) -> RunOutput {
+    RunOutput {
+        status: config.clean_exit(),
+        stdout: example.output.clone(),
+        stderr: String::new(),
+        timed_out: false,
+        stopped_early: false,
+        duration: Duration::ZERO,
+        max_rss_kb: None,
+    }
+}
+
+/// With `engine.rotate_origins`, the one origin that judges case `idx`: a
+/// weighted pick keyed on the case itself, so a fixed seed picks the same
+/// origins every run.
+fn rotated_origin(config: &Config, idx: usize, input: &str) -> Option<usize> {
+    if !config.engine.rotate_origins || config.origin.len() < 2 {
+        return None;
+    }
+
+    let weights = config
+        .origin
+        .iter()
+        .map(|origin| u64::from(origin.weight.unwrap_or(1)))
+        .collect::<Vec<_>>();
+    let mut key = Fingerprint::default();
+    key.update(&(idx as u64).to_le_bytes());
+    key.update(input.as_bytes());
+    let mut ticket = key.value() % weights.iter().sum::<u64>();
+    for (origin_idx, weight) in weights.into_iter().enumerate() {
+        if ticket < weight {
+            return Some(origin_idx);
+        }
+        ticket -= weight;
+    }
+    None
+}
+
+/// Tags `failures` with what judged their case, when that was not the
+/// usual vote of every origin.
+fn with_origin_name(mut failures: Vec<Failure>, origin_name: Option<String>) -> Vec<Failure> {
+    if let Some(origin_name) = origin_name {
+        for failure in &mut failures {
+            failure.origin_name = Some(origin_name.clone());
+        }
+    }
+    failures
+}
+
+/// One program's result under `--only-case`.
+fn print_output(name: &str, output: &RunOutput) {
+    println!();
+    println!("== {name} ==");
+    println!("exit: {}", describe_status(&output.status));
+    if output.timed_out {
+        println!("wall timeout");
+    }
+    print_stream("stdout", &output.stdout);
+    print_stream("stderr", &output.stderr);
+}
+
+/// How long one candidate's runs took, timed by the runner from spawn to
+/// exit, and the most memory any of them used. Timeouts and wrong answers
+/// count; runner errors have no usage. Only the last run of a retried case
+/// is measured.
+#[derive(Default)]
+struct CandidateUsage {
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+    runs: AtomicU64,
+    /// Peak RSS over the runs that reported one; `0` when none did.
+    max_rss_kb: AtomicU64,
+    /// Cases re-run under `engine.retries`, whatever their final verdict.
+    retried: AtomicU64,
+}
+
+impl CandidateUsage {
+    fn record(&self, output: &RunOutput) {
+        let micros = u64::try_from(output.duration.as_micros()).unwrap_or(u64::MAX);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        if let Some(rss) = output.max_rss_kb {
+            self.max_rss_kb.fetch_max(rss, Ordering::Relaxed);
+        }
+    }
+
+    fn record_retry(&self) {
+        self.retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn total(&self) -> Duration {
+        Duration::from_micros(self.total_micros.load(Ordering::Relaxed))
+    }
+
+    /// `avg 12ms, max 80ms over 1000 case(s), peak RSS 3.2 MiB`, followed by
+    /// `, 2 case(s) retried` when any were, or `None` before any run.
+    fn summary(&self) -> Option<String> {
+        let runs = self.runs.load(Ordering::Relaxed);
+        let average = self
+            .total_micros
+            .load(Ordering::Relaxed)
+            .checked_div(runs)?;
+        let max = self.max_micros.load(Ordering::Relaxed);
+        let peak = match self.max_rss_kb.load(Ordering::Relaxed) {
+            0 => "unknown".to_string(),
+            kb => format!("{:.1} MiB", kb as f64 / 1024.0),
+        };
+        let retried = match self.retried.load(Ordering::Relaxed) {
+            0 => String::new(),
+            cases => format!(", {cases} case(s) retried"),
+        };
+        Some(format!(
+            "avg {}ms, max {}ms over {runs} case(s), peak RSS {peak}{retried}",
+            average / 1000,
+            max / 1000
+        ))
+    }
+}
+
+fn record_output_hash(idx: usize, candidate_idx: usize, stdout: &str, ctx: &CaseContext) {
+    // Outputs that fail to normalize are hashed raw; they still tell
+    // candidates apart.
+    let output =
+        normalize_output(stdout, &ctx.config.normalize).unwrap_or_else(|_| stdout.to_string());
+    let mut hash = Fingerprint::default();
+    hash.update(output.as_bytes());
+    let mut hashes = ctx.output_hashes.lock().unwrap_or_else(|e| e.into_inner());
+    hashes[candidate_idx][idx] = Some(hash.hex());
+}
+
+/// [`candidate_verdict`], bounded by `engine.compare_timeout_ms` when set.
+/// An overrunning comparison is abandoned to finish on its own thread.
+fn bounded_verdict(
+    got: &RunOutput,
+    expected: &str,
+    input: &str,
+    ctx: &CaseContext,
+) -> Result<Option<Rejection>> {
+    let (Some(config), Some(timeout_ms)) =
+        (&ctx.compare_config, ctx.config.engine.compare_timeout_ms)
+    else {
+        return Ok(candidate_verdict(
+            got,
+            expected,
+            input,
+            ctx.config,
+            ctx.config_dir,
+        ));
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let (config, config_dir, got, expected, input) = (
+        Arc::clone(config),
+        ctx.config_dir.to_path_buf(),
+        got.clone(),
+        expected.to_string(),
+        input.to_string(),
+    );
+    thread::spawn(move || {
+        let verdict = candidate_verdict(&got, &expected, &input, &config, &config_dir);
+        let _ = sender.send(verdict);
+    });
+    receiver
+        .recv_timeout(Duration::from_millis(timeout_ms))
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "comparing candidate output took over engine.compare_timeout_ms={timeout_ms}ms"
+            )
+        })
+}
+
+/// Re-runs a candidate with the input's trailing newline toggled and records a
+/// warning when that alone flips its verdict, which points at fragile parsing.
+fn probe_trailing_newline(
+    idx: usize,
+    input: &str,
+    candidate_idx: usize,
+    expected: &str,
+    passed: bool,
+    ctx: &CaseContext,
+) {
+    let config = ctx.config;
+    let candidate = &config.candidate[candidate_idx];
+    let flipped = match input.strip_suffix('\n') {
+        Some(stripped) => stripped.to_string(),
+        None => format!("{input}\n"),
+    };
 
-    let raw = std::fs::read_to_string(config_path)
-        .with_context(|| format!("failed to read config: {}", config_path.display()))?;
-    let config: Config = toml::from_str(&raw)
-        .with_context(|| format!("failed to parse TOML: {}", config_path.display()))?;
+    let timeout_ms = config.timeout_ms(candidate);
+    let Ok(got) = case_program(config, candidate, &flipped).and_then(|(candidate, stdin)| {
+        run_program(
+            &candidate,
+            stdin,
+            ctx.config_dir,
+            timeout_ms,
+            &RunSettings::new(config),
+        )
+    }) else {
+        return;
+    };
 
-    if config.candidate.is_empty() {
-        bail!("at least one candidate is required");
+    let flipped_passed =
+        candidate_verdict(&got, expected, &flipped, config, ctx.config_dir).is_none();
+    if flipped_passed == passed {
+        return;
     }
 
-    let parsed_inputs = parse_problem_inputs(&config.problem)
-        .context("failed to parse [problem.inputs] constraints")?;
-    let generated_inputs = generate_inputs(
-        &parsed_inputs,
-        config.engine.cases,
-        config.engine.seed,
-        &config.pbt,
-    )
-    .context("failed to generate test inputs")?;
+    let (with, without) = if input.ends_with('\n') {
+        (passed, flipped_passed)
+    } else {
+        (flipped_passed, passed)
+    };
+    let describe = |ok: bool| if ok { "passes" } else { "fails" };
+    let message = format!(
+        "verdict depends on the input's trailing newline: {} with it, {} without it (case #{})",
+        describe(with),
+        describe(without),
+        idx + 1
+    );
 
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(config.engine.workers)
-        .build()
-        .context("failed to build worker pool")?;
-    let candidate_names = config
-        .candidate
-        .iter()
-        .enumerate()
-        .map(|(idx, candidate)| {
-            candidate
-                .name
-                .clone()
-                .unwrap_or_else(|| format!("candidate-{}", idx + 1))
-        })
-        .collect::<Vec<_>>();
+    if let Ok(mut warnings) = ctx.warnings.lock() {
+        warnings.push(Warning {
+            case_index: idx,
+            candidate_index: candidate_idx,
+            message,
+        });
+    }
+}
 
-    println!(
-        "nado: cases={}, candidates={}, workers={}, timeout={}ms",
-        generated_inputs.len(),
-        config.candidate.len(),
-        config.engine.workers,
-        config.engine.timeout_ms
-    );
-    let progress = build_progress_bar(generated_inputs.len());
-    let failed_candidates = config.engine.stop_on_first_fail.then(|| {
-        Arc::new(
-            (0..config.candidate.len())
-                .map(|_| AtomicBool::new(false))
-                .collect::<Vec<_>>(),
+/// Interactive counterpart of [`run_case`]: origin and every candidate converse
+/// with the judge, whose exit status decides the verdict. A judge rejecting the
+/// origin is reported as an origin failure since the setup itself is suspect.
+fn run_interactive_case(
+    idx: usize,
+    input: &str,
+    judge: &Program,
+    ctx: &CaseContext,
+) -> Result<Vec<Failure>> {
+    let config = ctx.config;
+    let rotated = rotated_origin(config, idx, input);
+    let mut origin = None;
+    for (origin_idx, program) in config.origin.iter().enumerate() {
+        if rotated.is_some_and(|pick| pick != origin_idx) {
+            continue;
+        }
+        let name = &ctx.origin_names[origin_idx];
+        let origin_timeout_ms = config.timeout_ms(program);
+        let output = run_interactive(
+            program,
+            judge,
+            input,
+            ctx.config_dir,
+            origin_timeout_ms,
+            judge.timeout_ms.unwrap_or(origin_timeout_ms),
+            &RunSettings::new(config),
         )
-    });
+        .with_context(|| format!("{name} execution failed"))?;
 
-    let progress = progress.clone();
-    let mut failures = pool.install(|| {
-        generated_inputs
-            .par_iter()
-            .enumerate()
-            .flat_map_iter(|(idx, input)| {
-                let result = run_case_or_failure(
-                    idx,
-                    input,
-                    &config,
-                    &config_dir,
-                    &candidate_names,
-                    &config.normalize,
-                    &config.limits,
-                    config.engine.timeout_ms,
-                    failed_candidates.as_ref().map(|flags| flags.as_slice()),
-                );
-                progress.inc(1);
-                result
-            })
-            .collect::<Vec<_>>()
-    });
+        let origin_reason = if output.program.timed_out {
+            Some(format!("{name} timed out"))
+        } else if !config.exited_ok(&output.program.status) {
+            Some(format!("{name} exited with {}", output.program.status))
+        } else {
+            judge_rejection(&output.judge, name)
+        };
+        if let Some(reason) = origin_reason {
+            let failure = origin_failure(idx, input, reason, &output.program);
+            let origin_name = rotated.map(|pick| ctx.origin_names[pick].clone());
+            return Ok(with_origin_name(vec![failure], origin_name));
+        }
+        origin.get_or_insert(output);
+    }
+    let origin = origin.context("no origin configured")?;
 
-    progress.finish_and_clear();
-    failures.sort_by_key(|f| (f.case_index, f.candidate_index.unwrap_or(usize::MAX)));
+    let mut failures = Vec::new();
 
-    let mut infra_failures = Vec::new();
-    let mut candidate_failures = vec![Vec::new(); config.candidate.len()];
-    for failure in failures {
-        if let Some(candidate_idx) = failure.candidate_index {
-            if config.engine.stop_on_first_fail && !candidate_failures[candidate_idx].is_empty() {
+    for (candidate_idx, candidate) in config.candidate.iter().enumerate() {
+        if should_skip_candidate(ctx.failed_candidates, candidate_idx) {
+            continue;
+        }
+
+        let candidate_timeout_ms = config.timeout_ms(candidate);
+        let mut retries = 0;
+        let run = loop {
+            let run = run_interactive(
+                candidate,
+                judge,
+                input,
+                ctx.config_dir,
+                candidate_timeout_ms,
+                judge.timeout_ms.unwrap_or(candidate_timeout_ms),
+                &RunSettings::new(config),
+            );
+            match &run {
+                Ok(output)
+                    if retries < config.engine.retries
+                        && interactive_verdict(output, config)
+                            .is_some_and(|rejection| rejection.kind.retryable()) =>
+                {
+                    if retries == 0 {
+                        ctx.candidate_usage[candidate_idx].record_retry();
+                    }
+                    retries += 1;
+                }
+                _ => break run,
+            }
+        };
+        if let Ok(output) = &run {
+            ctx.candidate_usage[candidate_idx].record(&output.program);
+        }
+        let (rejection, got) = match run {
+            Ok(output) => match interactive_verdict(&output, config) {
+                Some(rejection) => (rejection, output.program),
+                None => continue,
+            },
+            Err(error) => {
+                let rejection = Rejection::new(
+                    FailureKind::Error,
+                    format!("candidate runner error: {error:#}"),
+                );
+                failures.push(candidate_failure(
+                    idx,
+                    input,
+                    candidate_idx,
+                    ctx,
+                    rejection,
+                    &origin.program,
+                    None,
+                ));
+                mark_candidate_failed(ctx.failed_candidates, candidate_idx);
                 continue;
             }
-            candidate_failures[candidate_idx].push(failure);
+        };
+
+        failures.push(candidate_failure(
+            idx,
+            input,
+            candidate_idx,
+            ctx,
+            rejection,
+            &origin.program,
+            Some(got),
+        ));
+        mark_candidate_failed(ctx.failed_candidates, candidate_idx);
+    }
+
+    let origin_name = rotated.map(|pick| ctx.origin_names[pick].clone());
+    Ok(with_origin_name(failures, origin_name))
+}
+
+/// Settles the expected output from the origin runs by strict majority vote,
+/// returning it with the index of an origin that produced it. With a single
+/// origin this simply checks that it ran cleanly.
+pub(crate) fn settle_expected(
+    names: &[String],
+    outputs: &[RunOutput],
+    config: &Config,
+) -> std::result::Result<(String, usize), String> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    let mut rejected = Vec::new();
+
+    for (idx, output) in outputs.iter().enumerate() {
+        let name = &names[idx];
+        let normalized = if output.timed_out {
+            Err(format!("{name} timed out"))
+        } else if !config.exited_ok(&output.status) {
+            Err(format!("{name} exited with {}", output.status))
         } else {
-            infra_failures.push(failure);
+            normalize_output(&output.stdout, &config.normalize)
+                .map_err(|error| format!("malformed {name} output: {error:#}"))
+        };
+
+        match normalized {
+            Ok(normalized) => match groups.iter_mut().find(|(out, _)| *out == normalized) {
+                Some((_, members)) => members.push(idx),
+                None => groups.push((normalized, vec![idx])),
+            },
+            Err(reason) => rejected.push(reason),
         }
     }
 
-    let failed_count = candidate_failures
-        .iter()
-        .filter(|per_candidate| !per_candidate.is_empty())
-        .count();
-    let has_infra_failure = !infra_failures.is_empty();
+    if outputs.len() == 1 {
+        if let Some(reason) = rejected.pop() {
+            return Err(reason);
+        }
+    }
 
-    if failed_count == 0 && !has_infra_failure {
-        println!("PASS: all candidates matched origin");
-        print_candidate_summary(&candidate_names, &candidate_failures, false);
-        return Ok(0);
+    if let Some((expected, members)) = groups.iter().max_by_key(|(_, members)| members.len()) {
+        if members.len() * 2 > outputs.len() {
+            return Ok((expected.clone(), members[0]));
+        }
     }
 
-    println!(
-        "FAIL: {} / {} candidate(s) failed",
-        failed_count,
-        candidate_names.len()
-    );
-    print_candidate_summary(&candidate_names, &candidate_failures, has_infra_failure);
+    let mut camps = groups
+        .iter()
+        .map(|(_, members)| {
+            let members = members
+                .iter()
+                .map(|&idx| names[idx].as_str())
+                .collect::<Vec<_>>();
+            format!("[{}]", members.join(", "))
+        })
+        .collect::<Vec<_>>();
+    camps.extend(rejected);
+    Err(format!(
+        "origins disagree, no majority among {}: {}",
+        outputs.len(),
+        camps.join(" vs ")
+    ))
+}
 
-    if let Some(first_infra) = infra_failures.first() {
-        println!();
-        println!("origin/engine failure (candidate verdict may be incomplete):");
-        print_failure(first_infra);
+/// Returns why a batch candidate run fails against `expected`, or `None` when it passes.
+pub(crate) fn candidate_verdict(
+    got: &RunOutput,
+    expected: &str,
+    input: &str,
+    config: &Config,
+    config_dir: &Path,
+) -> Option<Rejection> {
+    if let Some(rejection) = timeout_rejection(got) {
+        return Some(rejection);
+    }
+    if !config.exited_ok(&got.status) && !got.stopped_early {
+        return Some(exit_rejection(&got.status));
     }
 
-    for per_candidate in candidate_failures.iter().filter(|f| !f.is_empty()) {
-        println!();
-        print_failure(&per_candidate[0]);
+    // A run stopped early holds a prefix that already diverges, so the
+    // regular comparison reports the mismatch; its shape is not checked.
+    if let Some(count) = config.normalize.expect_lines.as_ref() {
+        if !got.stopped_early {
+            let want = match expected_line_count(count, config, input) {
+                Ok(want) => want,
+                Err(error) => {
+                    return Some(Rejection::new(
+                        FailureKind::Error,
+                        format!("expect_lines: {error:#}"),
+                    ))
+                }
+            };
+            let lines = got.stdout.trim_end_matches(['\r', '\n']).lines().count();
+            if lines != want {
+                return Some(Rejection::wrong_answer(format!(
+                    "expected {want} line(s), got {lines}"
+                )));
+            }
+        }
+    }
+    if let Some(format) = config.problem.output_format.filter(|_| !got.stopped_early) {
+        if let Err(error) = check_output_format(&got.stdout, format) {
+            return Some(Rejection::wrong_answer(format!(
+                "malformed output: {error:#}"
+            )));
+        }
+    }
+
+    if let Some(checker) = &config.checker {
+        return match &checker.output_pattern {
+            Some(pattern) => pattern_verdict(pattern, got, &config.normalize),
+            None => checker_verdict(checker, got, expected, input, config, config_dir),
+        };
     }
 
-    Ok(1)
+    let normalize = &config.normalize;
+    let reason = match normalize_output(&got.stdout, normalize) {
+        Ok(actual) => match &config.problem.output_schema {
+            Some(schema) => compare_with_schema(expected, &actual, schema),
+            None => compare_outputs(expected, &actual, normalize),
+        },
+        Err(error) => Some(format!("malformed candidate output: {error:#}")),
+    };
+    reason.map(Rejection::wrong_answer)
 }
 
-fn run_case_or_failure(
-    idx: usize,
+/// Matches the candidate's normalized output against the checker's
+/// `output_regex`, quoting its first line when it does not match.
+fn pattern_verdict(pattern: &Regex, got: &RunOutput, normalize: &Normalize) -> Option<Rejection> {
+    let actual = match normalize_output(&got.stdout, normalize) {
+        Ok(actual) => actual,
+        Err(error) => {
+            return Some(Rejection::wrong_answer(format!(
+                "malformed candidate output: {error:#}"
+            )))
+        }
+    };
+    if pattern.is_match(&actual) {
+        return None;
+    }
+    // The pattern covers the whole output; a line that fails it on its own
+    // is only a hint at where things went wrong.
+    let hint = actual
+        .lines()
+        .enumerate()
+        .find(|(_, line)| !pattern.is_match(line))
+        .map(|(idx, line)| format!(" (line {}: {line:?} does not match on its own)", idx + 1))
+        .unwrap_or_default();
+    Some(Rejection::wrong_answer(format!(
+        "output does not match `{pattern}`{hint}"
+    )))
+}
+
+/// Runs the `[checker]` on the candidate's raw output; its stderr becomes
+/// the reason when it rejects the answer.
+fn checker_verdict(
+    checker: &Program,
+    got: &RunOutput,
+    expected: &str,
     input: &str,
     config: &Config,
     config_dir: &Path,
-    candidate_names: &[String],
-    normalize: &Normalize,
-    limits: &Limits,
-    timeout_ms: u64,
-    failed_candidates: Option<&[AtomicBool]>,
-) -> Vec<Failure> {
-    run_case(
-        idx,
+) -> Option<Rejection> {
+    let timeout_ms = config.timeout_ms(checker);
+    let output = match run_checker(
+        checker,
         input,
-        config,
+        &got.stdout,
+        expected,
         config_dir,
-        candidate_names,
-        normalize,
-        limits,
         timeout_ms,
-        failed_candidates,
-    )
-    .unwrap_or_else(|e| vec![engine_failure(idx, input, &e)])
+        &RunSettings::new(config),
+    ) {
+        Ok(output) => output,
+        Err(error) => {
+            return Some(Rejection::new(
+                FailureKind::Error,
+                format!("checker failed to run: {error:#}"),
+            ))
+        }
+    };
+    if output.timed_out {
+        return Some(Rejection::new(
+            FailureKind::Error,
+            format!("checker timed out after {timeout_ms}ms"),
+        ));
+    }
+    if output.status.success() {
+        return None;
+    }
+
+    let message = output.stderr.trim();
+    let reason = if message.is_empty() {
+        format!("checker rejected the output ({})", output.status)
+    } else {
+        format!("checker rejected the output ({}): {message}", output.status)
+    };
+    Some(Rejection::new(FailureKind::Rejected, reason))
 }
 
-fn engine_failure(idx: usize, input: &str, error: &anyhow::Error) -> Failure {
-    Failure {
-        case_index: idx,
-        input: input.to_string(),
-        candidate_index: None,
-        candidate_name: "engine".to_string(),
-        reason: format!("runner error: {error:#}"),
-        origin_stdout: String::new(),
-        candidate_stdout: String::new(),
-        origin_stderr: String::new(),
-        candidate_stderr: String::new(),
+fn expected_line_count(count: &LineCount, config: &Config, input: &str) -> Result<usize> {
+    let name = match count {
+        LineCount::Fixed(lines) => return Ok(*lines),
+        LineCount::Input(name) => name,
+    };
+    match expr::bind_inputs(&config.problem, input)?.get(name) {
+        Some(expr::Number::Int(value)) => usize::try_from(*value)
+            .with_context(|| format!("input `{name}` is {value}, not a line count")),
+        _ => bail!("input `{name}` is not an integer"),
     }
 }
 
-fn run_case(
-    idx: usize,
-    input: &str,
-    config: &Config,
-    config_dir: &Path,
-    candidate_names: &[String],
-    normalize: &Normalize,
-    limits: &Limits,
-    timeout_ms: u64,
-    failed_candidates: Option<&[AtomicBool]>,
-) -> Result<Vec<Failure>> {
-    let origin_timeout_ms = config.origin.timeout_ms.unwrap_or(timeout_ms);
-    let origin = run_program(&config.origin, input, config_dir, origin_timeout_ms, limits)
-        .context("origin execution failed")?;
+/// Tells a wall-clock kill by nado from a run the kernel stopped at
+/// `limits.cpu_seconds`; both count as timeouts.
+fn timeout_rejection(got: &RunOutput) -> Option<Rejection> {
+    let reason = if got.timed_out {
+        "candidate timed out (wall timeout)"
+    } else if cpu_limit_exceeded(&got.status) {
+        "candidate timed out (CPU limit exceeded)"
+    } else {
+        return None;
+    };
+    Some(Rejection::new(FailureKind::TimeLimit, reason))
+}
 
-    if origin.timed_out {
-        return Ok(vec![Failure {
-            case_index: idx,
-            input: input.to_string(),
-            candidate_index: None,
-            candidate_name: "origin".to_string(),
-            reason: "origin timed out".to_string(),
-            origin_stdout: origin.stdout,
-            candidate_stdout: String::new(),
-            origin_stderr: origin.stderr,
-            candidate_stderr: String::new(),
-        }]);
-    }
+fn exit_rejection(status: &ExitStatus) -> Rejection {
+    Rejection::new(
+        FailureKind::RuntimeError,
+        format!("candidate exited with {status}"),
+    )
+}
 
-    if !origin.status.success() {
-        return Ok(vec![Failure {
-            case_index: idx,
-            input: input.to_string(),
-            candidate_index: None,
-            candidate_name: "origin".to_string(),
-            reason: format!("origin exited with {}", origin.status),
-            origin_stdout: origin.stdout,
-            candidate_stdout: String::new(),
-            origin_stderr: origin.stderr,
-            candidate_stderr: String::new(),
-        }]);
+/// Interactive counterpart of [`candidate_verdict`].
+pub(crate) fn interactive_verdict(got: &InteractiveOutput, config: &Config) -> Option<Rejection> {
+    if let Some(rejection) = timeout_rejection(&got.program) {
+        return Some(rejection);
+    }
+    if !config.exited_ok(&got.program.status) {
+        return Some(exit_rejection(&got.program.status));
     }
 
-    let expected = normalize_output(&origin.stdout, normalize);
-    let mut failures = Vec::new();
-
-    for (candidate_idx, candidate) in config.candidate.iter().enumerate() {
-        if should_skip_candidate(failed_candidates, candidate_idx) {
-            continue;
-        }
+    judge_rejection(&got.judge, "candidate")
+        .map(|reason| Rejection::new(FailureKind::Rejected, reason))
+}
 
-        let candidate_timeout_ms = candidate.timeout_ms.unwrap_or(timeout_ms);
-        let candidate_name = candidate_names[candidate_idx].clone();
-        let got = match run_program(candidate, input, config_dir, candidate_timeout_ms, limits) {
-            Ok(output) => output,
-            Err(error) => {
-                failures.push(Failure {
-                    case_index: idx,
-                    input: input.to_string(),
-                    candidate_index: Some(candidate_idx),
-                    candidate_name,
-                    reason: format!("candidate runner error: {error:#}"),
-                    origin_stdout: origin.stdout.clone(),
-                    candidate_stdout: String::new(),
-                    origin_stderr: origin.stderr.clone(),
-                    candidate_stderr: String::new(),
-                });
-                mark_candidate_failed(failed_candidates, candidate_idx);
-                continue;
-            }
-        };
+pub(crate) fn judge_rejection(judge: &RunOutput, who: &str) -> Option<String> {
+    if judge.timed_out {
+        return Some(format!("judge timed out while talking to {who}"));
+    }
+    if judge.status.success() {
+        return None;
+    }
 
-        if got.timed_out {
-            failures.push(Failure {
-                case_index: idx,
-                input: input.to_string(),
-                candidate_index: Some(candidate_idx),
-                candidate_name,
-                reason: "candidate timed out".to_string(),
-                origin_stdout: origin.stdout.clone(),
-                candidate_stdout: got.stdout,
-                origin_stderr: origin.stderr.clone(),
-                candidate_stderr: got.stderr,
-            });
-            mark_candidate_failed(failed_candidates, candidate_idx);
-            continue;
-        }
+    let mut reason = format!("judge rejected {who} ({})", judge.status);
+    let stderr = judge.stderr.trim();
+    if !stderr.is_empty() {
+        reason.push_str(": ");
+        reason.push_str(stderr);
+    }
+    Some(reason)
+}
 
-        if !got.status.success() {
-            failures.push(Failure {
-                case_index: idx,
-                input: input.to_string(),
-                candidate_index: Some(candidate_idx),
-                candidate_name,
-                reason: format!("candidate exited with {}", got.status),
-                origin_stdout: origin.stdout.clone(),
-                candidate_stdout: got.stdout,
-                origin_stderr: origin.stderr.clone(),
-                candidate_stderr: got.stderr,
-            });
-            mark_candidate_failed(failed_candidates, candidate_idx);
-            continue;
-        }
+/// Groups failures by [`failure_signature`], largest cluster first.
+fn cluster_failures(failures: &[Failure]) -> Vec<(String, Vec<&Failure>)> {
+    let mut clusters: Vec<(String, Vec<&Failure>)> = Vec::new();
 
-        let actual = normalize_output(&got.stdout, normalize);
-        if expected != actual {
-            failures.push(Failure {
-                case_index: idx,
-                input: input.to_string(),
-                candidate_index: Some(candidate_idx),
-                candidate_name,
-                reason: "output mismatch".to_string(),
-                origin_stdout: origin.stdout.clone(),
-                candidate_stdout: got.stdout,
-                origin_stderr: origin.stderr.clone(),
-                candidate_stderr: got.stderr,
-            });
-            mark_candidate_failed(failed_candidates, candidate_idx);
+    for failure in failures {
+        let signature = failure_signature(failure);
+        match clusters.iter_mut().find(|(s, _)| *s == signature) {
+            Some((_, members)) => members.push(failure),
+            None => clusters.push((signature, vec![failure])),
         }
     }
 
-    Ok(failures)
+    clusters.sort_by_key(|(_, members)| std::cmp::Reverse(members.len()));
+    clusters
 }
 
-fn normalize_output(output: &str, normalize: &Normalize) -> String {
-    let mut normalized = output.replace("\r\n", "\n");
+/// Up to `limit` failures to print in full, taking one from each cluster in
+/// turn, largest first, so every signature gets an example before any gets
+/// a second.
+fn reported_failures<'a>(
+    clusters: &[(String, Vec<&'a Failure>)],
+    limit: usize,
+) -> Vec<&'a Failure> {
+    let deepest = clusters.first().map_or(0, |(_, members)| members.len());
+    (0..deepest)
+        .flat_map(|rank| {
+            clusters
+                .iter()
+                .filter_map(move |(_, members)| members.get(rank))
+        })
+        .copied()
+        .take(limit)
+        .collect()
+}
 
-    if normalize.trim_trailing_ws {
-        normalized = normalized
-            .split('\n')
-            .map(str::trim_end)
-            .collect::<Vec<_>>()
-            .join("\n");
+/// A coarse root-cause key: the reason, plus the shape of the first differing
+/// line for mismatches, with digit runs masked so values don't split clusters.
+fn failure_signature(failure: &Failure) -> String {
+    let reason = mask_digits(&failure.reason);
+    if !failure.reason.starts_with("output mismatch") {
+        return reason;
     }
 
-    if normalize.ignore_final_newline {
-        normalized = normalized.trim_end_matches('\n').to_string();
+    let mut expected = failure.origin_stdout.lines();
+    let mut actual = failure.candidate_stdout.lines();
+    loop {
+        match (expected.next(), actual.next()) {
+            (Some(e), Some(a)) if e == a => continue,
+            (None, None) => return reason,
+            (e, a) => {
+                let shape = |line: Option<&str>| match line {
+                    Some(line) => format!("`{}`", mask_digits(line.trim_end())),
+                    None => "<missing>".to_string(),
+                };
+                return format!("{reason}: {} vs {}", shape(e), shape(a));
+            }
+        }
+    }
+}
+
+fn mask_digits(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut in_digits = false;
+
+    for ch in text.chars() {
+        if ch.is_ascii_digit() {
+            if !in_digits {
+                masked.push('#');
+            }
+            in_digits = true;
+        } else {
+            masked.push(ch);
+            in_digits = false;
+        }
     }
 
-    normalized
+    masked
 }
 
-fn print_failure(failure: &Failure) {
+fn print_failure(failure: &Failure, config: &Config) {
     println!("FAIL at case #{}", failure.case_index + 1);
     println!("candidate: {}", failure.candidate_name);
+    if let Some(origin_name) = &failure.origin_name {
+        println!("origin: {origin_name}");
+    }
     println!("reason: {}", failure.reason);
     println!("input:\n{}", failure.input.trim_end());
+    // Under `output_regex` the origin's answer plays no part in a candidate's
+    // verdict, so showing it or a diff against it would mislead.
+    let pattern_judged = failure.candidate_index.is_some()
+        && config
+            .checker
+            .as_ref()
+            .is_some_and(|checker| checker.output_pattern.is_some());
+    if pattern_judged {
+        println!("candidate stdout:\n{}", failure.candidate_stdout.trim_end());
+        if !failure.candidate_stderr.trim().is_empty() {
+            println!("candidate stderr:\n{}", failure.candidate_stderr.trim_end());
+        }
+        return;
+    }
     println!("origin stdout:\n{}", failure.origin_stdout.trim_end());
     println!("candidate stdout:\n{}", failure.candidate_stdout.trim_end());
+    // A candidate stopped at its first wrong line printed only a prefix;
+    // compare it with as many lines of the origin's answer.
+    let expected = if failure.candidate_stopped_early {
+        let lines = failure.candidate_stdout.lines().count();
+        let end = failure
+            .origin_stdout
+            .match_indices('\n')
+            .nth(lines.saturating_sub(1))
+            .map_or(failure.origin_stdout.len(), |(at, _)| at + 1);
+        &failure.origin_stdout[..end]
+    } else {
+        &failure.origin_stdout
+    };
+    let actual = &failure.candidate_stdout;
+    // One-line answers are easy to compare by eye; longer ones get a diff.
+    if expected.lines().count() > 1 || actual.lines().count() > 1 {
+        let diff = diff::unified(expected, actual, std::io::stdout().is_terminal());
+        if !diff.is_empty() {
+            println!("diff (origin -> candidate):\n{}", diff.trim_end());
+        }
+    }
+    if diff::only_trailing_whitespace(expected, actual) {
+        println!("note: the outputs differ only in trailing whitespace");
+    }
 
     if !failure.origin_stderr.trim().is_empty() {
         println!("origin stderr:\n{}", failure.origin_stderr.trim_end());
@@ -364,28 +2238,193 @@ fn mark_candidate_failed(failed_candidates: Option<&[AtomicBool]>, candidate_idx
     }
 }
 
+/// A candidate's verdict for the whole run, honoring `expect_fail`.
+fn overall_verdict(
+    failure_count: usize,
+    expect_fail: bool,
+    has_infra_failure: bool,
+    fail_under: Option<(f64, usize)>,
+) -> Verdict {
+    // `--fail-under` tolerates a few mismatches; `expect_fail` still needs one.
+    let failed = match fail_under {
+        Some((threshold, cases)) if !expect_fail => pass_ratio(failure_count, cases) < threshold,
+        _ => failure_count > 0,
+    };
+    match (failed, expect_fail) {
+        (true, false) => Verdict::Fail,
+        (true, true) => Verdict::Pass,
+        (false, _) if has_infra_failure => Verdict::Unknown,
+        (false, false) => Verdict::Pass,
+        (false, true) => Verdict::Fail,
+    }
+}
+
+/// `6 passed, 3 WA, 1 TLE`: how a candidate's `cases` split across verdict
+/// classes. Classes with no cases are left out.
+fn verdict_histogram(failures: &[Failure], cases: usize) -> String {
+    let mut parts = vec![format!("{} passed", cases.saturating_sub(failures.len()))];
+    for label in FailureKind::LABELS {
+        let count = failures
+            .iter()
+            .filter(|failure| failure.kind.label() == label)
+            .count();
+        if count > 0 {
+            parts.push(format!("{count} {label}"));
+        }
+    }
+    parts.join(", ")
+}
+
+fn pass_ratio(failure_count: usize, cases: usize) -> f64 {
+    if cases == 0 {
+        return 1.0;
+    }
+    cases.saturating_sub(failure_count) as f64 / cases as f64
+}
+
+/// Prints the opt-in reports that follow the candidate summary.
+fn print_run_extras(
+    overrides: &Overrides,
+    coverage: &[EdgeCoverage],
+    baseline_changes: Option<&[String]>,
+) {
+    if overrides.coverage {
+        print_coverage(coverage);
+    }
+    if let Some(changes) = baseline_changes {
+        if changes.is_empty() {
+            println!("baseline: no verdict changes");
+        } else {
+            println!("baseline changes:");
+            for change in changes {
+                println!("- {change}");
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn print_candidate_summary(
     candidate_names: &[String],
     candidate_failures: &[Vec<Failure>],
+    expect_fail: &[bool],
     has_infra_failure: bool,
+    cached: &[String],
+    fail_under: Option<(f64, usize)>,
+    histogram_cases: Option<usize>,
+    candidate_usage: &[CandidateUsage],
 ) {
     println!("candidate summary:");
     for (idx, candidate_name) in candidate_names.iter().enumerate() {
         let failure_count = candidate_failures[idx].len();
-        if failure_count > 0 {
+        let (verdict, detail) =
+            if let (Some((threshold, cases)), false) = (fail_under, expect_fail[idx]) {
+                let ratio = pass_ratio(failure_count, cases);
+                let matched = format!(
+                    "{}/{cases} cases matched, {:.2}%",
+                    cases.saturating_sub(failure_count),
+                    ratio * 100.0
+                );
+                if ratio < threshold {
+                    (
+                        "FAIL",
+                        Some(format!("{matched}, below {:.2}%", threshold * 100.0)),
+                    )
+                } else if has_infra_failure {
+                    ("UNKNOWN", Some("origin/engine failure".to_string()))
+                } else {
+                    ("PASS", Some(matched))
+                }
+            } else if expect_fail[idx] {
+                if failure_count > 0 {
+                    (
+                        "PASS",
+                        Some(format!("failed as expected, {failure_count} mismatch(es)")),
+                    )
+                } else if has_infra_failure {
+                    ("UNKNOWN", Some("origin/engine failure".to_string()))
+                } else {
+                    (
+                        "FAIL",
+                        Some("expect_fail is set, but it matched origin on every case".to_string()),
+                    )
+                }
+            } else if failure_count > 0 {
+                ("FAIL", Some(format!("{failure_count} mismatch(es)")))
+            } else if has_infra_failure {
+                ("UNKNOWN", Some("origin/engine failure".to_string()))
+            } else {
+                ("PASS", None)
+            };
+        let details = detail
+            .into_iter()
+            .chain(candidate_usage.get(idx).and_then(CandidateUsage::summary))
+            .collect::<Vec<_>>();
+        if details.is_empty() {
+            println!("- {candidate_name}: {verdict}");
+        } else {
+            println!("- {candidate_name}: {verdict} ({})", details.join(", "));
+        }
+        if let (Some(cases), false) = (histogram_cases, candidate_failures[idx].is_empty()) {
             println!(
-                "- {}: FAIL ({} mismatch(es))",
-                candidate_name, failure_count
+                "  verdicts: {}",
+                verdict_histogram(&candidate_failures[idx], cases)
             );
-        } else if has_infra_failure {
-            println!("- {}: UNKNOWN (origin/engine failure)", candidate_name);
-        } else {
-            println!("- {}: PASS", candidate_name);
         }
     }
+    for candidate_name in cached {
+        println!(
+            "- {}: PASS (cached, unchanged since it last passed)",
+            candidate_name
+        );
+    }
+}
+
+fn print_warnings(candidate_names: &[String], warnings: Mutex<Vec<Warning>>) {
+    let mut warnings = warnings.into_inner().unwrap_or_else(|e| e.into_inner());
+    warnings.sort_by_key(|w| (w.candidate_index, w.case_index));
+    warnings.dedup_by_key(|w| w.candidate_index);
+
+    for warning in warnings {
+        println!(
+            "warning: {}: {}",
+            candidate_names[warning.candidate_index], warning.message
+        );
+    }
+}
+
+/// Notes candidates whose normalized output matched each other on every case,
+/// which usually means a duplicated or copy-pasted solution. Candidates with a
+/// case that was skipped or stopped early have no full output and are left out.
+fn print_identical_candidates(
+    candidate_names: &[String],
+    output_hashes: Mutex<Vec<Vec<Option<String>>>>,
+) {
+    let hashes = output_hashes
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner());
+    let mut classes: BTreeMap<Vec<String>, Vec<&str>> = BTreeMap::new();
+    for (name, per_case) in candidate_names.iter().zip(hashes) {
+        if let Some(per_case) = per_case.into_iter().collect::<Option<Vec<_>>>() {
+            classes.entry(per_case).or_default().push(name);
+        }
+    }
+
+    let mut classes = classes
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .collect::<Vec<_>>();
+    classes.sort_by_key(|names| candidate_names.iter().position(|name| name == names[0]));
+    for names in classes {
+        let (last, rest) = names.split_last().expect("classes have two or more names");
+        println!(
+            "note: {} and {last} produced identical output on all cases",
+            rest.join(", ")
+        );
+    }
 }
 
-fn build_progress_bar(total: usize) -> ProgressBar {
+fn build_progress_bar(total: usize, visible: bool) -> ProgressBar {
     let progress = ProgressBar::new(total as u64);
 
     let style = ProgressStyle::with_template(
@@ -396,9 +2435,51 @@ fn build_progress_bar(total: usize) -> ProgressBar {
     progress.set_style(style);
     progress.enable_steady_tick(Duration::from_millis(100));
 
-    if !std::io::stdout().is_terminal() {
+    if !visible || !std::io::stdout().is_terminal() {
         progress.set_draw_target(ProgressDrawTarget::hidden());
     }
 
     progress
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{verdict_histogram, Failure, FailureKind};
+
+    fn failure(kind: FailureKind, reason: &str) -> Failure {
+        Failure {
+            case_index: 0,
+            input: String::new(),
+            candidate_index: Some(0),
+            candidate_name: "greedy".to_string(),
+            kind,
+            reason: reason.to_string(),
+            origin_name: None,
+            origin_stdout: String::new(),
+            candidate_stdout: String::new(),
+            origin_stderr: String::new(),
+            candidate_stderr: String::new(),
+            candidate_stopped_early: false,
+        }
+    }
+
+    #[test]
+    fn histogram_counts_failures_by_kind_not_reason() {
+        let failures = [
+            failure(FailureKind::WrongAnswer, "line 1 differs"),
+            failure(
+                FailureKind::Rejected,
+                "judge rejected candidate (exit status: 1)",
+            ),
+            failure(FailureKind::TimeLimit, "candidate timed out (wall timeout)"),
+            failure(FailureKind::Error, "runner error: judging greedy"),
+            failure(FailureKind::Error, "checker timed out after 1000ms"),
+        ];
+
+        assert_eq!(
+            verdict_histogram(&failures, 12),
+            "7 passed, 2 WA, 1 TLE, 2 ERR"
+        );
+        assert_eq!(verdict_histogram(&failures[..1], 1), "0 passed, 1 WA");
+    }
+}
@@ -0,0 +1,284 @@
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+
+use crate::config::{self, Config, Program};
+use crate::engine::{
+    candidate_verdict, case_program, example_output, interactive_verdict, judge_rejection,
+    literal_input, run_origin, settle_expected,
+};
+use crate::runner::{
+    describe_status, run_interactive, run_program, InteractiveOutput, RunOutput, RunSettings,
+};
+use crate::state;
+
+/// Re-runs the failure recorded by the previous run with full, untruncated
+/// output, timing and decoded exit statuses. Returns 1 while the failure
+/// still reproduces.
+pub fn run(config_path: &Path) -> Result<i32> {
+    let (config, config_dir) = config::load(config_path)?;
+    let record = state::load_last_failure(&config_dir)?;
+    let candidate_names = config.candidate_names();
+
+    println!(
+        "explain: case #{} (recorded for {})",
+        record.case_index + 1,
+        record.candidate_name
+    );
+    println!("recorded reason: {}", record.reason);
+    println!("input:\n{}", record.input.trim_end());
+
+    let selected = match candidate_names
+        .iter()
+        .position(|name| name == &record.candidate_name)
+    {
+        Some(idx) => vec![idx],
+        None => (0..config.candidate.len()).collect(),
+    };
+
+    let origin_names = config.origin_names();
+    let example = config
+        .problem
+        .examples
+        .iter()
+        .position(|example| literal_input(&config, example.input.clone()) == record.input);
+    let expected = match example {
+        // An example's answer is written down; no origin needs to run.
+        Some(idx) => {
+            println!();
+            println!("expected output: problem.examples #{}", idx + 1);
+            settle_expected(
+                &[format!("example #{}", idx + 1)],
+                &[example_output(&config.problem.examples[idx], &config)],
+                &config,
+            )
+            .map(|(expected, _)| expected)
+        }
+        None => {
+            let mut origins = Vec::with_capacity(config.origin.len());
+            for (idx, program) in config.origin.iter().enumerate() {
+                origins.push(trace(
+                    &config,
+                    &config_dir,
+                    &origin_names[idx],
+                    program,
+                    &record.input,
+                )?);
+            }
+            origin_expected(&config, &origin_names, origins)
+        }
+    };
+    if let Err(reason) = &expected {
+        println!("origin verdict: {reason}");
+    }
+
+    let mut reproduced = expected.is_err();
+    for idx in selected {
+        let traced = trace(
+            &config,
+            &config_dir,
+            &candidate_names[idx],
+            &config.candidate[idx],
+            &record.input,
+        )?;
+
+        let verdict = match (&traced, &expected) {
+            (Traced::Interactive(output), _) => interactive_verdict(output, &config),
+            (Traced::Batch(output), Ok(expected)) => {
+                candidate_verdict(output, expected, &record.input, &config, &config_dir)
+            }
+            (Traced::Batch(_), Err(_)) => {
+                println!("verdict: UNKNOWN (origin failed)");
+                continue;
+            }
+        };
+
+        match verdict {
+            Some(reason) => {
+                reproduced = true;
+                println!("verdict: FAIL ({reason})");
+            }
+            None => println!("verdict: PASS"),
+        }
+
+        if let (Traced::Batch(output), Ok(expected)) = (&traced, &expected) {
+            if output.timed_out {
+                probe_termination(
+                    &config,
+                    &config_dir,
+                    &config.candidate[idx],
+                    &record.input,
+                    expected,
+                )?;
+            }
+        }
+    }
+
+    println!();
+    if reproduced {
+        println!("failure reproduced");
+        Ok(1)
+    } else {
+        println!("failure did not reproduce (flaky or fixed)");
+        Ok(0)
+    }
+}
+
+enum Traced {
+    Batch(RunOutput),
+    Interactive(InteractiveOutput),
+}
+
+impl Traced {
+    fn program(&self) -> &RunOutput {
+        match self {
+            Traced::Batch(output) => output,
+            Traced::Interactive(output) => &output.program,
+        }
+    }
+}
+
+fn trace(
+    config: &Config,
+    config_dir: &Path,
+    label: &str,
+    program: &Program,
+    input: &str,
+) -> Result<Traced> {
+    let timeout_ms = config.timeout_ms(program);
+
+    println!();
+    println!("== {label} ==");
+    match &program.expr {
+        Some(source) => println!("expr: {source}"),
+        None => println!("cmd: {}", program.cmd.join(" ")),
+    }
+    if let Some(image) = &program.image {
+        println!("image: {image}");
+    }
+    println!("timeout: {timeout_ms}ms");
+
+    let started = Instant::now();
+    let traced = match &config.judge {
+        Some(judge) => Traced::Interactive(
+            run_interactive(
+                program,
+                judge,
+                input,
+                config_dir,
+                timeout_ms,
+                judge.timeout_ms.unwrap_or(timeout_ms),
+                &RunSettings::new(config),
+            )
+            .with_context(|| format!("{label} execution failed"))?,
+        ),
+        None => Traced::Batch(
+            run_origin(config, config_dir, program, input)
+                .with_context(|| format!("{label} execution failed"))?,
+        ),
+    };
+    let elapsed = started.elapsed();
+
+    let output = traced.program();
+    println!("elapsed: {}ms", elapsed.as_millis());
+    println!("exit: {}", describe_status(&output.status));
+    if output.timed_out {
+        println!("wall timeout: killed after {timeout_ms}ms");
+    }
+    print_stream("stdout", &output.stdout);
+    print_stream("stderr", &output.stderr);
+
+    if let Traced::Interactive(output) = &traced {
+        println!("judge exit: {}", describe_status(&output.judge.status));
+        if output.judge.timed_out {
+            println!("judge timed out");
+        }
+        print_stream("judge stdout", &output.judge.stdout);
+        print_stream("judge stderr", &output.judge.stderr);
+    }
+
+    Ok(traced)
+}
+
+/// Re-runs a timed-out candidate with a much larger timeout to tell a
+/// correct-but-slow solution from one that likely never halts.
+fn probe_termination(
+    config: &Config,
+    config_dir: &Path,
+    program: &Program,
+    input: &str,
+    expected: &str,
+) -> Result<()> {
+    let timeout_ms = config.timeout_ms(program);
+    let probe_ms = config
+        .engine
+        .termination_probe_ms
+        .unwrap_or(timeout_ms.saturating_mul(10));
+    println!("termination probe: re-running with a {probe_ms}ms timeout");
+
+    let started = Instant::now();
+    let output = case_program(config, program, input)
+        .and_then(|(program, stdin)| {
+            run_program(
+                &program,
+                stdin,
+                config_dir,
+                probe_ms,
+                &RunSettings::new(config),
+            )
+        })
+        .context("termination probe failed")?;
+    let elapsed = started.elapsed().as_millis();
+    if output.timed_out {
+        println!("termination probe: still running after {probe_ms}ms (likely an infinite loop)");
+    } else if let Some(reason) = candidate_verdict(&output, expected, input, config, config_dir) {
+        println!("termination probe: halted after {elapsed}ms with a wrong answer ({reason})");
+    } else {
+        println!("termination probe: halted after {elapsed}ms with the right answer (too slow)");
+    }
+    Ok(())
+}
+
+fn origin_expected(
+    config: &Config,
+    names: &[String],
+    origins: Vec<Traced>,
+) -> std::result::Result<String, String> {
+    let mut outputs = Vec::with_capacity(origins.len());
+
+    for (idx, origin) in origins.into_iter().enumerate() {
+        let name = &names[idx];
+        match origin {
+            Traced::Batch(output) => outputs.push(output),
+            Traced::Interactive(output) => {
+                if output.program.timed_out {
+                    return Err(format!("{name} timed out"));
+                }
+                if !config.exited_ok(&output.program.status) {
+                    return Err(format!("{name} exited with {}", output.program.status));
+                }
+                if let Some(reason) = judge_rejection(&output.judge, name) {
+                    return Err(reason);
+                }
+            }
+        }
+    }
+
+    if outputs.is_empty() {
+        return Ok(String::new());
+    }
+    settle_expected(names, &outputs, config).map(|(expected, _)| expected)
+}
+
+pub(crate) fn print_stream(name: &str, content: &str) {
+    if content.is_empty() {
+        println!("{name}: (empty)");
+    } else {
+        println!("{name} ({} bytes):", content.len());
+        print!("{content}");
+        if !content.ends_with('\n') {
+            println!();
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+
+use crate::config::{self, Overrides};
+use crate::engine::{
+    apply_memory_budget, build_pool, case_inputs, prepare_images, print_commands, print_coverage,
+    run_origins, settle_expected,
+};
+
+/// Runs the origin(s) over every generated case and writes the pairs as
+/// `NN.in`/`NN.out` files into `out_dir`, numbered from 1.
+pub fn run(config_path: &Path, overrides: &Overrides, out_dir: &Path) -> Result<i32> {
+    let (mut config, config_dir) = config::load(config_path)?;
+    overrides.apply(&mut config);
+
+    if config.judge.is_some() {
+        bail!("export is not supported for interactive problems");
+    }
+    if config.origin.is_empty() && !config.examples_only() {
+        bail!("at least one origin is required");
+    }
+
+    let (inputs, coverage) = case_inputs(&config, overrides)?;
+    apply_memory_budget(&mut config)?;
+    let pool = build_pool(&config)?;
+    let origin_names = config.origin_names();
+    if overrides.show_commands {
+        print_commands(&config, &config_dir, &origin_names, &[])?;
+    }
+    prepare_images(&config)?;
+
+    let outputs = pool.install(|| {
+        inputs
+            .par_iter()
+            .enumerate()
+            .map(|(idx, input)| {
+                if let Some(example) = config.problem.examples.get(idx) {
+                    return Ok(example.output.clone());
+                }
+                let origins = run_origins(&config, &config_dir, &origin_names, input)?;
+                match settle_expected(&origin_names, &origins, &config) {
+                    Ok((_, representative)) => Ok(origins[representative].stdout.clone()),
+                    Err(reason) => bail!("case #{}: {reason}", idx + 1),
+                }
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create export dir: {}", out_dir.display()))?;
+
+    let width = inputs.len().to_string().len().max(2);
+    for (idx, (input, output)) in inputs.iter().zip(&outputs).enumerate() {
+        let stem = format!("{:0width$}", idx + 1);
+        for (ext, content) in [("in", input), ("out", output)] {
+            let path = out_dir.join(format!("{stem}.{ext}"));
+            std::fs::write(&path, content)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+    }
+
+    println!("exported {} case(s) to {}", inputs.len(), out_dir.display());
+    if overrides.coverage {
+        print_coverage(&coverage);
+    }
+    Ok(0)
+}
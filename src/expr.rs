@@ -0,0 +1,583 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+use crate::config::Problem;
+
+/// A parsed `origin.expr` formula over the problem's input names.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(Number),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Func {
+    Min,
+    Max,
+}
+
+/// Integers stay exact; any float operand makes the result a float.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(value) => value as f64,
+            Number::Float(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(value) => write!(f, "{value}"),
+            Number::Float(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Result<Expr> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.expr()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        bail!("unexpected `{token}` in expr `{source}`");
+    }
+    Ok(expr)
+}
+
+/// Binds every numeric input to its value in the rendered `input`, which
+/// lists inputs ordered by name. Graph and array inputs are skipped and bind
+/// nothing, as do enum values that are not numbers.
+pub fn bind_inputs(problem: &Problem, input: &str) -> Result<BTreeMap<String, Number>> {
+    Ok(input_tokens(problem, input)?
+        .into_iter()
+        .filter_map(|(name, token)| Some((name, parse_number(token).ok()?)))
+        .collect())
+}
+
+/// The token every scalar input rendered as in `input`, by name.
+fn input_tokens<'a>(problem: &Problem, input: &'a str) -> Result<BTreeMap<String, &'a str>> {
+    let mut tokens = input.split_whitespace();
+    let mut next = |name: &str| {
+        tokens
+            .next()
+            .with_context(|| format!("input ended inside `{name}`"))
+    };
+
+    let mut scalars = BTreeMap::new();
+    for (name, spec) in &problem.inputs {
+        if spec.kind == "array" {
+            // The length input sorts, and so renders, before the array.
+            let len = spec.len.as_ref().and_then(|len| scalars.get(len));
+            let count = len
+                .and_then(|token: &&str| token.parse::<usize>().ok())
+                .with_context(|| format!("input `{name}` has no length"))?;
+            for _ in 0..count {
+                next(name)?;
+            }
+            continue;
+        }
+        if spec.kind == "grid" {
+            // Like an array, sized by inputs that render before it.
+            let size = |field: &Option<String>| {
+                field
+                    .as_ref()
+                    .and_then(|size| scalars.get(size))
+                    .and_then(|token: &&str| token.parse::<usize>().ok())
+                    .with_context(|| format!("input `{name}` has no size"))
+            };
+            let (rows, cols) = (size(&spec.rows)?, size(&spec.cols)?);
+            let chars = spec
+                .element
+                .as_ref()
+                .is_some_and(|cell| cell.kind == "char");
+            // A row of characters is one word, and an empty row none.
+            let count = match chars {
+                true if cols == 0 => 0,
+                true => rows,
+                false => rows * cols,
+            };
+            for _ in 0..count {
+                next(name)?;
+            }
+            continue;
+        }
+        let token = next(name)?;
+        if spec.kind == "graph" {
+            let edges = next(name)?
+                .parse::<usize>()
+                .with_context(|| format!("input `{name}` has no edge count"))?;
+            let per_edge = if spec.edge_weights.is_some() { 3 } else { 2 };
+            for _ in 0..edges * per_edge {
+                next(name)?;
+            }
+            continue;
+        }
+        scalars.insert(name.clone(), token);
+    }
+    Ok(scalars)
+}
+
+/// `{0}`, `{1}`, ... in a program's `cmd`: the case's inputs by position.
+fn arg_placeholder() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{(\d+)\}").expect("valid regex"))
+}
+
+/// Positions of the inputs `cmd` takes as arguments through `{N}`.
+pub fn arg_positions(cmd: &[String]) -> BTreeSet<usize> {
+    cmd.iter()
+        .flat_map(|arg| arg_placeholder().captures_iter(arg))
+        .map(|caps| caps[1].parse().unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// `cmd` with every `{N}` replaced by the token input `N` rendered as in
+/// `input`. Inputs are numbered in name order, which is also the order they
+/// render in.
+pub fn fill_args(problem: &Problem, cmd: &[String], input: &str) -> Result<Vec<String>> {
+    let tokens = input_tokens(problem, input)?;
+    let names = problem.inputs.keys().collect::<Vec<_>>();
+    cmd.iter()
+        .map(|arg| {
+            let mut filled = String::new();
+            let mut last = 0;
+            for caps in arg_placeholder().captures_iter(arg) {
+                let whole = caps.get(0).expect("whole match");
+                let token = caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|pos| names.get(pos))
+                    .and_then(|name| tokens.get(name.as_str()))
+                    .with_context(|| format!("`{}` names no scalar input", whole.as_str()))?;
+                filled.push_str(&arg[last..whole.start()]);
+                filled.push_str(token);
+                last = whole.end();
+            }
+            filled.push_str(&arg[last..]);
+            Ok(filled)
+        })
+        .collect()
+}
+
+/// A `problem.origin_input_template`: literal text with `{input}` for the
+/// generated case, `{name}` for an input's token and `{expr}` for any
+/// formula over the integer inputs. `{{` and `}}` are literal braces.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Input,
+    Token(String),
+    Expr(Expr),
+}
+
+impl Template {
+    pub fn parse(source: &str, problem: &Problem) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => bail!("unclosed `{{` in template"),
+                        }
+                    }
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                    let field = field.trim();
+                    parts.push(match problem.inputs.get(field) {
+                        _ if field == "input" => Part::Input,
+                        Some(spec) if spec.kind != "graph" => Part::Token(field.to_string()),
+                        _ => Part::Expr(
+                            parse(field).with_context(|| format!("invalid field `{{{field}}}`"))?,
+                        ),
+                    });
+                }
+                '}' => bail!("unmatched `}}` in template; write `}}}}` for a literal brace"),
+                c => text.push(c),
+            }
+        }
+        parts.push(Part::Text(text));
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, problem: &Problem, input: &str) -> Result<String> {
+        let tokens = input_tokens(problem, input)?;
+        let vars = bind_inputs(problem, input)?;
+        let mut out = String::with_capacity(input.len());
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Input => out.push_str(input),
+                Part::Token(name) => out.push_str(tokens[name]),
+                Part::Expr(expr) => out.push_str(&expr.eval(&vars)?.to_string()),
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Expr {
+    /// Names the expression refers to.
+    pub fn vars(&self) -> BTreeSet<String> {
+        let mut vars = BTreeSet::new();
+        let mut pending = vec![self];
+        while let Some(expr) = pending.pop() {
+            match expr {
+                Expr::Number(_) => {}
+                Expr::Var(name) => {
+                    vars.insert(name.clone());
+                }
+                Expr::Neg(inner) => pending.push(inner),
+                Expr::Binary(_, lhs, rhs) => pending.extend([&**lhs, &**rhs]),
+                Expr::Call(_, args) => pending.extend(args),
+            }
+        }
+        vars
+    }
+
+    pub fn eval(&self, vars: &BTreeMap<String, Number>) -> Result<Number> {
+        match self {
+            Expr::Number(value) => Ok(*value),
+            Expr::Var(name) => match vars.get(name) {
+                Some(value) => Ok(*value),
+                None => bail!("unknown name `{name}` in expr"),
+            },
+            Expr::Neg(inner) => match inner.eval(vars)? {
+                Number::Int(value) => value
+                    .checked_neg()
+                    .map(Number::Int)
+                    .context("integer overflow in expr"),
+                Number::Float(value) => Ok(Number::Float(-value)),
+            },
+            Expr::Binary(op, lhs, rhs) => apply(*op, lhs.eval(vars)?, rhs.eval(vars)?),
+            Expr::Call(func, args) => {
+                let mut best: Option<Number> = None;
+                for arg in args {
+                    let value = arg.eval(vars)?;
+                    let better = match (best, func) {
+                        (None, _) => true,
+                        (Some(current), Func::Min) => less(value, current),
+                        (Some(current), Func::Max) => less(current, value),
+                    };
+                    if better {
+                        best = Some(value);
+                    }
+                }
+                best.context("min/max need at least one argument")
+            }
+        }
+    }
+}
+
+fn less(a: Number, b: Number) -> bool {
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => a < b,
+        _ => a.as_f64() < b.as_f64(),
+    }
+}
+
+fn apply(op: Op, lhs: Number, rhs: Number) -> Result<Number> {
+    let (Number::Int(a), Number::Int(b)) = (lhs, rhs) else {
+        let (a, b) = (lhs.as_f64(), rhs.as_f64());
+        if matches!(op, Op::Div | Op::Rem) && b == 0.0 {
+            bail!("division by zero in expr");
+        }
+        return Ok(Number::Float(match op {
+            Op::Add => a + b,
+            Op::Sub => a - b,
+            Op::Mul => a * b,
+            Op::Div => a / b,
+            Op::Rem => a % b,
+            Op::Pow => a.powf(b),
+        }));
+    };
+
+    let value = match op {
+        Op::Add => a.checked_add(b),
+        Op::Sub => a.checked_sub(b),
+        Op::Mul => a.checked_mul(b),
+        Op::Div | Op::Rem if b == 0 => bail!("division by zero in expr"),
+        // Truncating, like C++ and Rust integer division.
+        Op::Div => a.checked_div(b),
+        Op::Rem => a.checked_rem(b),
+        Op::Pow if b < 0 => return Ok(Number::Float((a as f64).powf(b as f64))),
+        Op::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+    };
+    value.map(Number::Int).context("integer overflow in expr")
+}
+
+fn parse_number(token: &str) -> Result<Number> {
+    if let Ok(value) = token.parse::<i128>() {
+        return Ok(Number::Int(value));
+    }
+    let value = token.parse::<f64>()?;
+    if !value.is_finite() {
+        bail!("not a finite number");
+    }
+    Ok(Number::Float(value))
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                // Exponent sign, as in `1e-9`.
+                let exponent =
+                    matches!(chars[i], 'e' | 'E') && matches!(chars.get(i + 1), Some('+' | '-'));
+                i += if exponent { 2 } else { 1 };
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if c == '*' && chars.get(i + 1) == Some(&'*') {
+            tokens.push("**".to_string());
+            i += 2;
+        } else if "+-*/%(),".contains(c) {
+            tokens.push(c.to_string());
+            i += 1;
+        } else {
+            bail!("unexpected character `{c}` in expr `{source}`");
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent, loosest first: `+ -`, `* / %`, unary `-`, `**` (right
+/// associative, so `-2 ** 2` is `-4` and `2 ** 3 ** 2` is `2 ** 9`).
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, wanted: &str) -> Result<()> {
+        match self.next() {
+            Some(token) if token == wanted => Ok(()),
+            Some(token) => bail!("expected `{wanted}` in expr, got `{token}`"),
+            None => bail!("expected `{wanted}` in expr, got end of input"),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(op @ ("+" | "-")) = self.peek() {
+            let op = if op == "+" { Op::Add } else { Op::Sub };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some("*") => Op::Mul,
+                Some("/") => Op::Div,
+                Some("%") => Op::Rem,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.peek() == Some("-") {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr> {
+        let base = self.atom()?;
+        if self.peek() == Some("**") {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary(Op::Pow, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        let Some(token) = self.next() else {
+            bail!("expr ended unexpectedly");
+        };
+        if token == "(" {
+            let inner = self.expr()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            let value = parse_number(&token)
+                .with_context(|| format!("invalid number `{token}` in expr"))?;
+            return Ok(Expr::Number(value));
+        }
+        if !token.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            bail!("unexpected `{token}` in expr");
+        }
+
+        let func = match token.as_str() {
+            "min" => Func::Min,
+            "max" => Func::Max,
+            _ => return Ok(Expr::Var(token)),
+        };
+        self.expect("(")?;
+        let mut args = vec![self.expr()?];
+        while self.peek() == Some(",") {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        self.expect(")")?;
+        Ok(Expr::Call(func, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{parse, Number};
+
+    fn eval(source: &str) -> anyhow::Result<Number> {
+        let vars = BTreeMap::from([
+            ("a".to_string(), Number::Int(7)),
+            ("b".to_string(), Number::Int(-2)),
+        ]);
+        parse(source)?.eval(&vars)
+    }
+
+    #[test]
+    fn evaluates_with_precedence_and_types() {
+        assert_eq!(eval("a + b * 3").unwrap(), Number::Int(1));
+        assert_eq!(eval("a / b").unwrap(), Number::Int(-3));
+        assert_eq!(eval("a % 4 ** 2").unwrap(), Number::Int(7));
+        assert_eq!(eval("-2 ** 2").unwrap(), Number::Int(-4));
+        assert_eq!(eval("max(a, b, 3) - min(a, b)").unwrap(), Number::Int(9));
+        assert_eq!(eval("a / 2.0").unwrap(), Number::Float(3.5));
+        assert_eq!(eval("2 ** b").unwrap(), Number::Float(0.25));
+        assert_eq!(eval("1e3 + a").unwrap(), Number::Float(1007.0));
+    }
+
+    #[test]
+    fn reports_bad_input_and_division_by_zero() {
+        assert!(eval("a / (b + 2)")
+            .unwrap_err()
+            .to_string()
+            .contains("division by zero"));
+        assert!(eval("a +").is_err());
+        assert!(eval("c").is_err());
+        assert!(eval("10 ** 100").is_err());
+    }
+
+    #[test]
+    fn template_renders_tokens_exprs_and_the_case() {
+        let mut problem = crate::config::Problem::default();
+        for name in ["n", "op"] {
+            problem.inputs.insert(
+                name.to_string(),
+                crate::config::InputSpec {
+                    kind: if name == "n" { "integer" } else { "enum" }.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        let template = super::Template::parse("{input}{op} {n * (n + 1) / 2} {{x}}\n", &problem)
+            .expect("parse");
+
+        assert_eq!(
+            template.render(&problem, "4 add\n").unwrap(),
+            "4 add\nadd 10 {x}\n"
+        );
+        assert!(super::Template::parse("{n", &problem).is_err());
+        assert!(super::Template::parse("{n +}", &problem).is_err());
+    }
+
+    #[test]
+    fn fills_positional_args_from_the_case() {
+        let mut problem = crate::config::Problem::default();
+        for name in ["n", "op"] {
+            problem.inputs.insert(
+                name.to_string(),
+                crate::config::InputSpec {
+                    kind: if name == "n" { "integer" } else { "enum" }.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        let cmd = ["./solve", "{0}", "--op={1}"].map(String::from);
+
+        assert_eq!(
+            super::arg_positions(&cmd).into_iter().collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(
+            super::fill_args(&problem, &cmd, "4 add\n").unwrap(),
+            ["./solve", "4", "--op=add"]
+        );
+        assert!(super::fill_args(&problem, &["{2}".to_string()], "4 add\n").is_err());
+    }
+}
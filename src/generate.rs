@@ -0,0 +1,37 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::{self, Overrides};
+use crate::engine::{case_inputs, print_coverage};
+
+/// Generates every case the run would use and prints them, or writes them to
+/// `out`, without spawning any program.
+pub fn run(config_path: &Path, overrides: &Overrides, out: Option<&Path>) -> Result<i32> {
+    let (mut config, _) = config::load(config_path)?;
+    overrides.apply(&mut config);
+
+    let (inputs, coverage) = case_inputs(&config, overrides)?;
+    let mut listing = String::new();
+    for (idx, input) in inputs.iter().enumerate() {
+        if idx > 0 {
+            listing.push('\n');
+        }
+        let _ = writeln!(listing, "case #{}:", idx + 1);
+        listing.push_str(input);
+    }
+
+    match out {
+        Some(path) => {
+            std::fs::write(path, &listing)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!("wrote {} case(s) to {}", inputs.len(), path.display());
+        }
+        None => print!("{listing}"),
+    }
+    if overrides.coverage {
+        print_coverage(&coverage);
+    }
+    Ok(0)
+}
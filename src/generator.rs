@@ -1,4 +1,5 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::sync::OnceLock;
 
 use anyhow::{bail, Context, Result};
@@ -6,282 +7,1790 @@ use proptest::strategy::{Strategy, ValueTree};
 use proptest::test_runner::{Config as RunnerConfig, RngAlgorithm, TestRng, TestRunner};
 use regex::Regex;
 
-use crate::config::{InputSpec, Pbt, Problem};
+use crate::config::{Bound, Count, InputSpec, Pbt, Problem, StringShape};
+use crate::expr::{self, Expr, Func, Number, Op};
+use crate::graph::{parse_graph, Graph, GraphSpec};
 
 #[derive(Debug, Clone)]
 pub struct ParsedInput {
-    pub min: i64,
-    pub max: i64,
+    pub name: String,
+    pub kind: InputKind,
 }
 
-pub fn parse_problem_inputs(problem: &Problem) -> Result<Vec<ParsedInput>> {
-    if problem.inputs.is_empty() {
-        bail!("problem.inputs must not be empty");
-    }
+#[derive(Debug, Clone)]
+pub enum InputKind {
+    /// For inputs with [`DependentBounds`], `min`/`max` are the envelope of
+    /// every per-case range.
+    Integer {
+        min: i64,
+        max: i64,
+        distribution: Distribution,
+        dependent: Option<Box<DependentBounds>>,
+    },
+    /// A real number rendered with `decimals` digits after the point, held
+    /// as a count of `10^-decimals` units so rendering is exact.
+    Float {
+        min: i64,
+        max: i64,
+        decimals: u32,
+    },
+    /// A token of `min_len..=max_len` characters drawn from `chars`.
+    Str {
+        chars: Vec<char>,
+        min_len: usize,
+        max_len: usize,
+        /// Structures of the extra `max_len` strings among the edge values.
+        shapes: Vec<StringShape>,
+    },
+    /// Integers in `[min, max]`, as many as input `len` has in the same case.
+    Array {
+        len: usize,
+        min: i64,
+        max: i64,
+        distribution: Distribution,
+        /// No two elements equal; drawn uniformly without replacement.
+        distinct: bool,
+    },
+    /// `rows` lines of `cols` cells, both sizes taken from integer inputs
+    /// of the same case.
+    Grid {
+        rows: usize,
+        cols: usize,
+        cells: Cells,
+    },
+    /// One of a fixed set of tokens, optionally weighted.
+    Enum {
+        values: Vec<String>,
+        weights: Option<Vec<f64>>,
+    },
+    Graph(GraphSpec),
+}
 
-    let mut parsed = Vec::new();
-    for (name, spec) in &problem.inputs {
-        if spec.kind != "integer" {
-            bail!(
-                "only integer inputs are supported in MVP, got {} for {}",
-                spec.kind,
-                name
-            );
+/// What a grid cell holds.
+#[derive(Debug, Clone)]
+pub enum Cells {
+    /// Integers in `[min, max]`, space-separated.
+    Int {
+        min: i64,
+        max: i64,
+        distribution: Distribution,
+    },
+    /// Characters from a charset, each row written as one word.
+    Chars(Vec<char>),
+}
+
+impl Cells {
+    /// Range of the stored cell values; character cells store indices.
+    fn bounds(&self) -> (i64, i64) {
+        match self {
+            Cells::Int { min, max, .. } => (*min, *max),
+            Cells::Chars(chars) => (0, chars.len() as i64 - 1),
         }
+    }
 
-        let (min, max) = parse_bounds(spec).with_context(|| format!("input {}", name))?;
-        parsed.push(ParsedInput { min, max });
+    fn chars(&self) -> Option<Vec<char>> {
+        match self {
+            Cells::Int { .. } => None,
+            Cells::Chars(chars) => Some(chars.clone()),
+        }
     }
+}
 
-    Ok(parsed)
+/// A single generated value. Scalars render as one token; block values such
+/// as graphs span whole lines.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    /// `units` × 10^-`decimals`.
+    Float {
+        units: i64,
+        decimals: u32,
+    },
+    Token(String),
+    /// Space-separated on a line of its own. Seeded arrays hold a short
+    /// pattern until the case's length is known; see [`fill_array`].
+    Array(Vec<i64>),
+    /// One line per row. Character cells hold indices into `chars` and are
+    /// written without separators. Seeded grids hold a small pattern until
+    /// the case's size is known; see [`fill_grid`].
+    Grid {
+        cells: Vec<Vec<i64>>,
+        chars: Option<Vec<char>>,
+    },
+    Graph(Graph),
 }
 
-pub fn generate_inputs(
-    specs: &[ParsedInput],
-    cases: usize,
-    seed: u64,
-    pbt: &Pbt,
-) -> Result<Vec<String>> {
-    if cases == 0 {
-        return Ok(Vec::new());
+impl Value {
+    fn is_block(&self) -> bool {
+        matches!(self, Value::Array(_) | Value::Grid { .. } | Value::Graph(_))
     }
 
-    let mut seeded_cases = Vec::new();
-
-    if pbt.enabled {
-        validate_pbt_config(pbt)?;
-        let edge_budget = ((cases as f64) * pbt.edge_case_ratio).round() as usize;
-        let partition_budget = ((cases as f64) * pbt.partition_ratio).round() as usize;
+    /// One-line form for reports; block values are summarized.
+    pub fn label(&self) -> String {
+        match self {
+            Value::Array(items) if items.len() > 8 => format!("array of {}", items.len()),
+            Value::Array(items) => format!("[{}]", Value::Array(items.clone())),
+            Value::Grid { cells, .. } if cells.iter().map(Vec::len).sum::<usize>() > 8 => {
+                let cols = cells.first().map_or(0, Vec::len);
+                format!("{}x{cols} grid", cells.len())
+            }
+            Value::Grid { cells, chars } => {
+                let rows = cells
+                    .iter()
+                    .map(|row| render_row(row, chars.as_deref()))
+                    .collect::<Vec<_>>();
+                format!("[{}]", rows.join(" / "))
+            }
+            Value::Graph(graph) => graph.summary(),
+            scalar => scalar.to_string(),
+        }
+    }
+}
 
-        extend_edge_cases(
-            &mut seeded_cases,
-            specs,
-            edge_budget,
-            pbt.max_cartesian_cases,
-        );
-        extend_partition_cases(&mut seeded_cases, specs, partition_budget);
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Float { units, decimals } => {
+                if *decimals == 0 {
+                    return write!(f, "{units}");
+                }
+                let scale = 10u64.pow(*decimals);
+                let sign = if *units < 0 { "-" } else { "" };
+                let abs = units.unsigned_abs();
+                let width = *decimals as usize;
+                write!(f, "{sign}{}.{:0width$}", abs / scale, abs % scale)
+            }
+            Value::Token(token) => f.write_str(token),
+            Value::Array(items) => {
+                let items = items.iter().map(i64::to_string).collect::<Vec<_>>();
+                f.write_str(&items.join(" "))
+            }
+            Value::Grid { cells, chars } => {
+                let rows = cells
+                    .iter()
+                    .map(|row| render_row(row, chars.as_deref()))
+                    .collect::<Vec<_>>();
+                f.write_str(&rows.join("\n"))
+            }
+            Value::Graph(graph) => graph.fmt(f),
+        }
     }
+}
 
-    if seeded_cases.len() > cases {
-        seeded_cases.truncate(cases);
+fn render_row(row: &[i64], chars: Option<&[char]>) -> String {
+    match chars {
+        Some(chars) => row.iter().map(|&idx| chars[idx as usize]).collect(),
+        None => row.iter().map(i64::to_string).collect::<Vec<_>>().join(" "),
     }
+}
 
-    let random_needed = cases.saturating_sub(seeded_cases.len());
-    let random_cases = generate_random_cases(specs, random_needed, seed)?;
+/// Integer bounds given as expressions over other integer inputs; a bound
+/// without an expression keeps its numeric value.
+#[derive(Debug, Clone)]
+pub struct DependentBounds {
+    min: Limit,
+    max: Limit,
+    /// Indices of the inputs the expressions refer to.
+    refs: Vec<usize>,
+}
 
-    let mut all_cases = seeded_cases;
-    all_cases.extend(random_cases);
+#[derive(Debug, Clone)]
+enum Limit {
+    Fixed(i64),
+    Expr(Expr),
+}
 
-    Ok(all_cases
-        .into_iter()
-        .map(|values| format_case(&values))
-        .collect())
+/// How random (non-seeded) values are drawn from `[min, max]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    Uniform,
+    /// Rank `k` (value `min + k - 1`) is drawn with probability ∝ `1 / k^skew`,
+    /// so values near `min` recur far more often than under uniform sampling.
+    Zipf {
+        skew: f64,
+    },
 }
 
-fn validate_pbt_config(pbt: &Pbt) -> Result<()> {
-    if !(0.0..=1.0).contains(&pbt.edge_case_ratio) {
-        bail!("pbt.edge_case_ratio must be between 0.0 and 1.0");
-    }
-    if !(0.0..=1.0).contains(&pbt.partition_ratio) {
-        bail!("pbt.partition_ratio must be between 0.0 and 1.0");
-    }
-    if pbt.edge_case_ratio + pbt.partition_ratio > 1.0 {
-        bail!("pbt.edge_case_ratio + pbt.partition_ratio must be <= 1.0");
+pub fn parse_problem_inputs(problem: &Problem) -> Result<Vec<ParsedInput>> {
+    if problem.inputs.is_empty() {
+        bail!("problem.inputs must not be empty");
     }
-    if pbt.max_cartesian_cases == 0 {
-        bail!("pbt.max_cartesian_cases must be > 0");
+
+    let mut parsed = Vec::new();
+    for (name, spec) in &problem.inputs {
+        if spec.shapes.is_some() && spec.kind != "string" {
+            bail!("input {name}: `shapes` applies only to string inputs");
+        }
+        if spec.indexing.is_some() && !matches!(spec.kind.as_str(), "array" | "graph") {
+            bail!("input {name}: `indexing` applies only to array and graph inputs");
+        }
+        let kind = parse_kind(spec).with_context(|| format!("input {}", name))?;
+        parsed.push(ParsedInput {
+            name: name.clone(),
+            kind,
+        });
     }
 
+    link_array_lengths(problem, &mut parsed)?;
+    link_dependent_bounds(problem, &mut parsed)?;
+    link_graph_counts(problem, &mut parsed)?;
+    check_distinct_arrays(&parsed)?;
+    Ok(parsed)
+}
+
+/// Fails when a distinct array may be longer than its range has values.
+fn check_distinct_arrays(parsed: &[ParsedInput]) -> Result<()> {
+    for spec in parsed {
+        let InputKind::Array {
+            len,
+            min,
+            max,
+            distinct: true,
+            ..
+        } = spec.kind
+        else {
+            continue;
+        };
+        let InputKind::Integer { max: longest, .. } = parsed[len].kind else {
+            unreachable!("array lengths are integer inputs");
+        };
+        let size = i128::from(max) - i128::from(min) + 1;
+        if i128::from(longest) > size {
+            bail!(
+                "input {}: distinct array may hold up to {longest} elements (`{}`), but \
+                 [{min}, {max}] has only {size} values",
+                spec.name,
+                parsed[len].name
+            );
+        }
+    }
     Ok(())
 }
 
-fn extend_edge_cases(
-    out: &mut Vec<Vec<i64>>,
-    specs: &[ParsedInput],
-    budget: usize,
-    max_cartesian_cases: usize,
-) {
-    if budget == 0 || specs.is_empty() {
-        return;
+/// Points every array and grid input at the integer inputs holding its
+/// size, which must render before it.
+fn link_array_lengths(problem: &Problem, parsed: &mut [ParsedInput]) -> Result<()> {
+    for (idx, spec) in problem.inputs.values().enumerate() {
+        match parsed[idx].kind {
+            InputKind::Array { .. } => {
+                let target = size_input(parsed, idx, "array", "len", spec.len.as_ref())?;
+                if let InputKind::Array { len, .. } = &mut parsed[idx].kind {
+                    *len = target;
+                }
+            }
+            InputKind::Grid { .. } => {
+                let row_input = size_input(parsed, idx, "grid", "rows", spec.rows.as_ref())?;
+                let col_input = size_input(parsed, idx, "grid", "cols", spec.cols.as_ref())?;
+                if let InputKind::Grid { rows, cols, .. } = &mut parsed[idx].kind {
+                    (*rows, *cols) = (row_input, col_input);
+                }
+            }
+            _ => {}
+        }
     }
+    Ok(())
+}
 
-    let mids = specs
-        .iter()
-        .map(|s| midpoint(s.min, s.max))
-        .collect::<Vec<_>>();
+/// Index of the integer input that `field` of input `idx` names.
+fn size_input(
+    parsed: &[ParsedInput],
+    idx: usize,
+    kind: &str,
+    field: &str,
+    target_name: Option<&String>,
+) -> Result<usize> {
+    let name = &parsed[idx].name;
+    let Some(target_name) = target_name else {
+        bail!("input {name}: {kind} inputs need `{field}`, the name of an integer input");
+    };
+    let target = count_input(parsed, idx, kind, field, target_name)?;
+    // Inputs render in name order, and a reader needs the size first.
+    if target > idx {
+        bail!(
+            "input {name}: renders before its size `{target_name}` (inputs are emitted in \
+             name order); rename one of them"
+        );
+    }
+    Ok(target)
+}
 
-    push_unique(out, mids.clone(), budget);
-    push_unique(out, specs.iter().map(|s| s.min).collect::<Vec<_>>(), budget);
-    push_unique(out, specs.iter().map(|s| s.max).collect::<Vec<_>>(), budget);
+/// Index of `target_name`, a non-negative integer input that `field` of
+/// input `idx` takes a count from.
+fn count_input(
+    parsed: &[ParsedInput],
+    idx: usize,
+    kind: &str,
+    field: &str,
+    target_name: &str,
+) -> Result<usize> {
+    let name = &parsed[idx].name;
+    let Some(target) = parsed.iter().position(|other| other.name == target_name) else {
+        bail!("input {name}: `{field}` refers to `{target_name}`, which is not an input");
+    };
+    if target == idx {
+        bail!("input {name}: `{field}` refers to the {kind} itself");
+    }
+    let InputKind::Integer { min, .. } = parsed[target].kind else {
+        bail!("input {name}: `{field}` refers to `{target_name}`, which is not an integer input");
+    };
+    if min < 0 {
+        bail!("input {name}: size input `{target_name}` may be negative (min {min})");
+    }
+    Ok(target)
+}
 
-    let alt_min_max = specs
-        .iter()
-        .enumerate()
-        .map(|(i, s)| if i % 2 == 0 { s.min } else { s.max })
-        .collect::<Vec<_>>();
-    push_unique(out, alt_min_max, budget);
+/// Points every graph whose `nodes` or `edges` names an integer input at
+/// that input. Runs once dependent bounds are settled, as the graph is
+/// fitted to the input's range. The graph carries its own counts, so
+/// unlike an array it may render before them.
+fn link_graph_counts(problem: &Problem, parsed: &mut [ParsedInput]) -> Result<()> {
+    for (idx, spec) in problem.inputs.values().enumerate() {
+        if !matches!(parsed[idx].kind, InputKind::Graph(_)) {
+            continue;
+        }
+        let mut linked = [None, None];
+        for (slot, (field, count)) in linked
+            .iter_mut()
+            .zip([("nodes", &spec.nodes), ("edges", &spec.edges)])
+        {
+            let Some(Count::Input(target_name)) = count else {
+                continue;
+            };
+            let target = count_input(parsed, idx, "graph", field, target_name)?;
+            let InputKind::Integer { min, max, .. } = parsed[target].kind else {
+                unreachable!("graph counts are integer inputs");
+            };
+            *slot = Some((target, target_name.as_str(), (min as u64, max as u64)));
+        }
+        let ParsedInput { name, kind } = &mut parsed[idx];
+        if let InputKind::Graph(graph) = kind {
+            graph
+                .link_counts(linked[0], linked[1])
+                .with_context(|| format!("input {name}"))?;
+        }
+    }
+    Ok(())
+}
 
-    let alt_max_min = specs
+/// Resolves the inputs each expression bound refers to and narrows every
+/// dependent input's `min`/`max` to the envelope of its possible ranges.
+fn link_dependent_bounds(problem: &Problem, parsed: &mut [ParsedInput]) -> Result<()> {
+    let names = parsed
         .iter()
-        .enumerate()
-        .map(|(i, s)| if i % 2 == 0 { s.max } else { s.min })
+        .map(|spec| spec.name.clone())
         .collect::<Vec<_>>();
-    push_unique(out, alt_max_min, budget);
-
-    for (idx, spec) in specs.iter().enumerate() {
-        for edge in edge_values(spec) {
-            let mut candidate = mids.clone();
-            candidate[idx] = edge;
-            push_unique(out, candidate, budget);
-
-            if out.len() >= budget {
-                return;
+    for idx in 0..parsed.len() {
+        let InputKind::Integer {
+            dependent: Some(dependent),
+            ..
+        } = &mut parsed[idx].kind
+        else {
+            continue;
+        };
+        let mut vars = BTreeSet::new();
+        for limit in [&dependent.min, &dependent.max] {
+            if let Limit::Expr(expr) = limit {
+                vars.extend(expr.vars());
+            }
+        }
+        for var in vars {
+            let target = names.iter().position(|name| *name == var);
+            match target.map(|target| (target, &problem.inputs[&var])) {
+                Some((target, other)) if other.kind == "integer" && target != idx => {
+                    dependent.refs.push(target);
+                }
+                _ => bail!(
+                    "input {}: bound refers to `{var}`, which is not another integer input",
+                    names[idx]
+                ),
             }
         }
     }
 
-    let edge_sets = specs.iter().map(edge_values).collect::<Vec<_>>();
-    let total_cartesian = edge_sets
-        .iter()
-        .fold(1usize, |acc, set| acc.saturating_mul(set.len()));
+    for idx in dependency_order(parsed)? {
+        let InputKind::Integer {
+            dependent: Some(dependent),
+            ..
+        } = &parsed[idx].kind
+        else {
+            continue;
+        };
+        let (lo, hi) = envelope(parsed, idx, dependent)?;
+        if let InputKind::Integer { min, max, .. } = &mut parsed[idx].kind {
+            (*min, *max) = (lo, hi);
+        }
+    }
+    Ok(())
+}
 
-    if total_cartesian == 0 || total_cartesian > max_cartesian_cases {
-        return;
+/// Input indices ordered so every input comes after the inputs its bounds
+/// refer to.
+fn dependency_order(specs: &[ParsedInput]) -> Result<Vec<usize>> {
+    fn visit(
+        specs: &[ParsedInput],
+        idx: usize,
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<()> {
+        if order.contains(&idx) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&on_path| on_path == idx) {
+            let cycle = path[start..]
+                .iter()
+                .chain([&idx])
+                .map(|&i| specs[i].name.as_str())
+                .collect::<Vec<_>>();
+            bail!("input bounds refer to each other: {}", cycle.join(" -> "));
+        }
+        path.push(idx);
+        match &specs[idx].kind {
+            InputKind::Integer {
+                dependent: Some(dependent),
+                ..
+            } => {
+                for &target in &dependent.refs {
+                    visit(specs, target, path, order)?;
+                }
+            }
+            InputKind::Array { len, .. } => visit(specs, *len, path, order)?,
+            InputKind::Grid { rows, cols, .. } => {
+                visit(specs, *rows, path, order)?;
+                visit(specs, *cols, path, order)?;
+            }
+            InputKind::Graph(graph) => {
+                for target in graph.count_inputs() {
+                    visit(specs, target, path, order)?;
+                }
+            }
+            _ => {}
+        }
+        path.pop();
+        order.push(idx);
+        Ok(())
     }
 
-    let mut stack = Vec::with_capacity(specs.len());
-    cartesian_collect(&edge_sets, 0, &mut stack, out, budget);
+    let mut order = Vec::with_capacity(specs.len());
+    for idx in 0..specs.len() {
+        visit(specs, idx, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order)
 }
 
-fn cartesian_collect(
-    edge_sets: &[Vec<i64>],
-    depth: usize,
-    stack: &mut Vec<i64>,
-    out: &mut Vec<Vec<i64>>,
-    budget: usize,
-) {
-    if out.len() >= budget {
-        return;
+/// Smallest and largest value a dependent input can take, from its bounds
+/// evaluated at every combination of the referenced inputs' extremes.
+fn envelope(specs: &[ParsedInput], idx: usize, dependent: &DependentBounds) -> Result<(i64, i64)> {
+    let extremes = dependent
+        .refs
+        .iter()
+        .map(|&target| match specs[target].kind {
+            InputKind::Integer { min, max, .. } => [min, max],
+            _ => unreachable!("bounds only refer to integer inputs"),
+        })
+        .collect::<Vec<_>>();
+    if extremes.len() > 16 {
+        bail!("input {}: bounds refer to too many inputs", specs[idx].name);
     }
 
-    if depth == edge_sets.len() {
-        push_unique(out, stack.clone(), budget);
-        return;
+    let mut envelope: Option<(i64, i64)> = None;
+    for corner in 0..1usize << extremes.len() {
+        let mut case = vec![Value::Int(0); specs.len()];
+        for (bit, (&target, pair)) in dependent.refs.iter().zip(&extremes).enumerate() {
+            case[target] = Value::Int(pair[(corner >> bit) & 1]);
+        }
+        let (lo, hi) = case_bounds(specs, idx, dependent, &case)?;
+        envelope = Some(envelope.map_or((lo, hi), |(a, b)| (a.min(lo), b.max(hi))));
     }
+    Ok(envelope.expect("at least one combination"))
+}
 
-    for &value in &edge_sets[depth] {
-        if out.len() >= budget {
-            return;
+/// The range of dependent input `idx` in a case whose referenced inputs are
+/// already settled.
+fn case_bounds(
+    specs: &[ParsedInput],
+    idx: usize,
+    dependent: &DependentBounds,
+    case: &[Value],
+) -> Result<(i64, i64)> {
+    let vars = dependent
+        .refs
+        .iter()
+        .map(|&target| match &case[target] {
+            Value::Int(value) => (specs[target].name.clone(), Number::Int(i128::from(*value))),
+            _ => unreachable!("bounds only refer to integer inputs"),
+        })
+        .collect::<BTreeMap<_, _>>();
+    let name = &specs[idx].name;
+    let eval = |limit: &Limit| -> Result<i64> {
+        let expr = match limit {
+            Limit::Expr(expr) => expr,
+            Limit::Fixed(value) => return Ok(*value),
+        };
+        match expr.eval(&vars).with_context(|| format!("input {name}"))? {
+            Number::Int(value) => i64::try_from(value)
+                .with_context(|| format!("input {name}: bound {value} is out of range")),
+            Number::Float(value) => bail!("input {name}: bound evaluated to non-integer {value}"),
         }
+    };
+
+    let (lo, hi) = (eval(&dependent.min)?, eval(&dependent.max)?);
+    if lo > hi {
+        let given = vars
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>();
+        bail!(
+            "input {name}: infeasible bounds min({lo}) > max({hi}) when {}",
+            given.join(", ")
+        );
+    }
+    Ok((lo, hi))
+}
 
-        stack.push(value);
-        cartesian_collect(edge_sets, depth + 1, stack, out, budget);
-        stack.pop();
+/// Moves dependent inputs into the range their bounds give in this case:
+/// seeded values are clamped, so edges stay edges; random ones are redrawn.
+fn settle_dependent(
+    specs: &[ParsedInput],
+    order: &[usize],
+    case: &mut [Value],
+    mut runner: Option<&mut TestRunner>,
+) -> Result<()> {
+    for &idx in order {
+        if let InputKind::Array {
+            len,
+            min,
+            max,
+            distribution,
+            distinct,
+        } = &specs[idx].kind
+        {
+            let Value::Int(count) = case[*len] else {
+                unreachable!("array lengths are integer inputs");
+            };
+            let count = usize::try_from(count).unwrap_or(0);
+            if let Value::Array(items) = &case[idx] {
+                case[idx] = Value::Array(match runner.as_deref_mut() {
+                    Some(runner) if *distinct => sample_distinct(runner, *min, *max, count)?,
+                    Some(runner) => (0..count)
+                        .map(|_| sample_integer(runner, *min, *max, *distribution))
+                        .collect::<Result<_>>()?,
+                    None => fill_array(&specs[idx].kind, items, count),
+                });
+            }
+            continue;
+        }
+        if let InputKind::Grid { rows, cols, cells } = &specs[idx].kind {
+            let (Value::Int(row_count), Value::Int(col_count)) = (&case[*rows], &case[*cols])
+            else {
+                unreachable!("grid sizes are integer inputs");
+            };
+            let row_count = usize::try_from(*row_count).unwrap_or(0);
+            let col_count = usize::try_from(*col_count).unwrap_or(0);
+            if let Value::Grid { cells: pattern, .. } = &case[idx] {
+                let filled = match runner.as_deref_mut() {
+                    Some(runner) => (0..row_count)
+                        .map(|_| {
+                            (0..col_count)
+                                .map(|_| sample_cell(runner, cells))
+                                .collect::<Result<_>>()
+                        })
+                        .collect::<Result<_>>()?,
+                    None => fill_grid(pattern, row_count, col_count),
+                };
+                case[idx] = Value::Grid {
+                    cells: filled,
+                    chars: cells.chars(),
+                };
+            }
+            continue;
+        }
+        if let InputKind::Graph(graph) = &specs[idx].kind {
+            let Value::Graph(drawn) = &case[idx] else {
+                continue;
+            };
+            if graph.count_inputs().next().is_none() {
+                continue;
+            }
+            let count_of = |input: usize| match case[input] {
+                Value::Int(count) => count.max(0) as u64,
+                _ => unreachable!("graph counts are integer inputs"),
+            };
+            let built = match runner.as_deref_mut() {
+                Some(runner) => graph.case_graph(runner, count_of, None),
+                None => graph.case_graph(&mut structure_runner(), count_of, Some(drawn)),
+            }
+            .with_context(|| format!("input {}", specs[idx].name))?;
+            case[idx] = Value::Graph(built);
+            continue;
+        }
+        let InputKind::Integer {
+            dependent: Some(dependent),
+            distribution,
+            ..
+        } = &specs[idx].kind
+        else {
+            continue;
+        };
+        let (lo, hi) = case_bounds(specs, idx, dependent, case)?;
+        let Value::Int(value) = case[idx] else {
+            continue;
+        };
+        if (lo..=hi).contains(&value) {
+            continue;
+        }
+        case[idx] = Value::Int(match runner.as_deref_mut() {
+            Some(runner) => sample_integer(runner, lo, hi, *distribution)?,
+            None => value.clamp(lo, hi),
+        });
     }
+    Ok(())
 }
 
-fn extend_partition_cases(out: &mut Vec<Vec<i64>>, specs: &[ParsedInput], budget: usize) {
-    if budget == 0 || specs.is_empty() {
-        return;
+fn parse_kind(spec: &InputSpec) -> Result<InputKind> {
+    match spec.kind.as_str() {
+        "integer" => {
+            let (min, max) = parse_bounds(spec)?;
+            let dependent = match has_expr_bounds(spec)? {
+                true => Some(Box::new(dependent_bounds(spec)?)),
+                false => None,
+            };
+            Ok(InputKind::Integer {
+                min,
+                max,
+                distribution: parse_distribution(spec)?,
+                dependent,
+            })
+        }
+        "float" => parse_float(spec),
+        "string" => parse_string(spec),
+        "array" => parse_array(spec),
+        "grid" => parse_grid(spec),
+        "enum" => parse_enum(spec),
+        "graph" => Ok(InputKind::Graph(parse_graph(spec)?)),
+        other => bail!(
+            "unsupported input type: {other} (expected integer, float, string, array, grid, enum \
+             or graph)"
+        ),
     }
+}
 
-    let partition_values = specs.iter().map(partition_points).collect::<Vec<_>>();
+fn parse_array(spec: &InputSpec) -> Result<InputKind> {
+    let Some(element) = &spec.element else {
+        bail!("array inputs need an `element` table with the element bounds");
+    };
+    if element.kind != "integer" {
+        bail!("array elements must be integers, got {}", element.kind);
+    }
+    if has_expr_bounds(element)? {
+        bail!("array element bounds must be numbers");
+    }
+    let (min, max) = parse_bounds(element).context("array element")?;
+    // Index-valued arrays give 0-based bounds and render from the base.
+    let (min, max) = match spec.indexing {
+        None | Some(0) => (min, max),
+        Some(1) => match (min.checked_add(1), max.checked_add(1)) {
+            (Some(min), Some(max)) => (min, max),
+            _ => bail!("array element max {max} overflows once shifted by indexing = 1"),
+        },
+        Some(other) => bail!("indexing must be 0 or 1, got {other}"),
+    };
+    if spec.distinct && element.distribution.is_some() {
+        bail!("distinct arrays are drawn uniformly; remove element.distribution");
+    }
 
-    let mut cursor = 0usize;
-    while out.len() < budget {
-        let mut values = Vec::with_capacity(specs.len());
+    Ok(InputKind::Array {
+        // Linked to the length input once every input is parsed.
+        len: usize::MAX,
+        min,
+        max,
+        distribution: parse_distribution(element)?,
+        distinct: spec.distinct,
+    })
+}
 
-        for (idx, points) in partition_values.iter().enumerate() {
-            let point = points[(cursor + idx) % points.len()];
-            values.push(point);
+fn parse_grid(spec: &InputSpec) -> Result<InputKind> {
+    let Some(element) = &spec.element else {
+        bail!("grid inputs need an `element` table with the cell bounds or charset");
+    };
+    let cells = match element.kind.as_str() {
+        "integer" => {
+            if has_expr_bounds(element)? {
+                bail!("grid cell bounds must be numbers");
+            }
+            let (min, max) = parse_bounds(element).context("grid element")?;
+            Cells::Int {
+                min,
+                max,
+                distribution: parse_distribution(element)?,
+            }
         }
+        "char" => Cells::Chars(parse_charset(element.charset.as_deref().unwrap_or("a-z"))?),
+        other => bail!("grid cells must be integer or char, got {other}"),
+    };
+
+    Ok(InputKind::Grid {
+        // Linked to the size inputs once every input is parsed.
+        rows: usize::MAX,
+        cols: usize::MAX,
+        cells,
+    })
+}
 
-        push_unique(out, values, budget);
-        cursor += 1;
+/// A seeded grid pattern tiled over `rows` × `cols` cells.
+fn fill_grid(pattern: &[Vec<i64>], rows: usize, cols: usize) -> Vec<Vec<i64>> {
+    pattern
+        .iter()
+        .cycle()
+        .take(rows)
+        .map(|row| row.iter().copied().cycle().take(cols).collect())
+        .collect()
+}
 
-        if cursor > budget.saturating_mul(4) {
-            break;
-        }
+/// A seeded array pattern stretched to `count` elements. Plain arrays repeat
+/// or cut it. Distinct arrays become a run of consecutive values starting at
+/// its first element, shifted to fit the range, and descending when the
+/// pattern does.
+fn fill_array(kind: &InputKind, pattern: &[i64], count: usize) -> Vec<i64> {
+    let InputKind::Array {
+        min,
+        max,
+        distinct: true,
+        ..
+    } = *kind
+    else {
+        return pattern.iter().copied().cycle().take(count).collect();
+    };
+    let Some(&anchor) = pattern.first() else {
+        return Vec::new();
+    };
+
+    let span = count.saturating_sub(1) as i128;
+    let (min, max, anchor) = (i128::from(min), i128::from(max), i128::from(anchor));
+    if pattern
+        .get(1)
+        .is_some_and(|&next| i128::from(next) < anchor)
+    {
+        let top = anchor.clamp(min + span, max);
+        (0..count as i128).map(|step| (top - step) as i64).collect()
+    } else {
+        let bottom = anchor.clamp(min, max - span);
+        (0..count as i128)
+            .map(|step| (bottom + step) as i64)
+            .collect()
     }
 }
 
-fn generate_random_cases(specs: &[ParsedInput], count: usize, seed: u64) -> Result<Vec<Vec<i64>>> {
-    if count == 0 {
-        return Ok(Vec::new());
+/// Whether a filled array came from seeded pattern `edge`; other values
+/// compare as they are.
+fn covers(kind: &InputKind, value: &Value, edge: &Value) -> bool {
+    match (value, edge) {
+        (Value::Array(items), Value::Array(pattern)) => {
+            !items.is_empty() && *items == fill_array(kind, pattern, items.len())
+        }
+        (Value::Grid { cells, .. }, Value::Grid { cells: pattern, .. }) => {
+            let cols = cells.first().map_or(0, Vec::len);
+            cols > 0 && *cells == fill_grid(pattern, cells.len(), cols)
+        }
+        (Value::Graph(graph), Value::Graph(pattern)) => match kind {
+            InputKind::Graph(spec) => spec.follows(graph, pattern),
+            _ => unreachable!("graph values come from graph inputs"),
+        },
+        _ => value == edge,
     }
+}
 
-    let mut runner = build_proptest_runner(seed);
-    let mut out = Vec::with_capacity(count);
+fn parse_string(spec: &InputSpec) -> Result<InputKind> {
+    let chars = parse_charset(spec.charset.as_deref().unwrap_or("a-z"))?;
+    let min_len = spec.min_len.unwrap_or(1);
+    let max_len = spec.max_len.unwrap_or(10);
+    if min_len > max_len {
+        bail!("string min_len {min_len} is greater than max_len {max_len}");
+    }
 
-    for _ in 0..count {
-        let mut values = Vec::with_capacity(specs.len());
+    Ok(InputKind::Str {
+        chars,
+        min_len,
+        max_len,
+        shapes: spec.shapes.clone().unwrap_or_default(),
+    })
+}
 
-        for spec in specs {
-            let strategy = spec.min..=spec.max;
-            let tree = strategy.new_tree(&mut runner).map_err(|e| {
-                anyhow::anyhow!("failed to generate random case from strategy: {e}")
-            })?;
-            values.push(tree.current());
+/// Expands a charset such as `"A-Za-z0-9_"` into its distinct characters. A
+/// `-` at either end is taken literally.
+fn parse_charset(source: &str) -> Result<Vec<char>> {
+    let raw = source.chars().collect::<Vec<_>>();
+    let mut chars = BTreeSet::new();
+    let mut idx = 0;
+    while idx < raw.len() {
+        if idx + 2 < raw.len() && raw[idx + 1] == '-' {
+            let (lo, hi) = (raw[idx], raw[idx + 2]);
+            if lo > hi {
+                bail!("charset range {lo}-{hi} is reversed");
+            }
+            chars.extend(lo..=hi);
+            idx += 3;
+        } else {
+            chars.insert(raw[idx]);
+            idx += 1;
         }
-
-        out.push(values);
     }
 
-    Ok(out)
+    if chars.is_empty() {
+        bail!("string charset must not be empty");
+    }
+    if let Some(bad) = chars.iter().find(|c| c.is_whitespace() || c.is_control()) {
+        bail!("string charset must not contain whitespace or control characters, got {bad:?}");
+    }
+    Ok(chars.into_iter().collect())
 }
 
-fn build_proptest_runner(seed: u64) -> TestRunner {
-    let seed_bytes = seed_to_bytes(seed);
-    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &seed_bytes);
-    TestRunner::new_with_rng(RunnerConfig::default(), rng)
+/// `len` characters cycling through the charset, so seeded strings are fixed
+/// but not a single repeated character.
+fn cycled_string(chars: &[char], len: usize) -> Value {
+    Value::Token(chars.iter().cycle().take(len).collect())
 }
 
-fn seed_to_bytes(seed: u64) -> [u8; 32] {
-    let mut out = [0u8; 32];
-
-    for i in 0..4 {
-        let part = seed.wrapping_add(i as u64).to_le_bytes();
-        let start = i * 8;
-        out[start..start + 8].copy_from_slice(&part);
+/// A `len`-character string of `chars` with the structure `shape`, its
+/// characters drawn by `runner`.
+fn shaped_string(
+    runner: &mut TestRunner,
+    chars: &[char],
+    len: usize,
+    shape: StringShape,
+) -> Result<Value> {
+    // Mixes in a second character when the charset has one, so the shape
+    // does not collapse into `all_same`.
+    fn pick(runner: &mut TestRunner, chars: &[char], count: usize) -> Result<Vec<char>> {
+        let picks = sample(runner, proptest::collection::vec(0..chars.len(), count))?;
+        let mut picked = picks.into_iter().map(|idx| chars[idx]).collect::<Vec<_>>();
+        if picked.iter().all(|&c| c == picked[0]) {
+            if let Some(&other) = chars.iter().find(|&&c| c != picked[0]) {
+                picked[0] = other;
+            }
+        }
+        Ok(picked)
     }
 
-    out
+    let text = match shape {
+        StringShape::Palindrome => {
+            let half = pick(runner, chars, len.div_ceil(2))?;
+            half.iter().chain(half[..len / 2].iter().rev()).collect()
+        }
+        StringShape::Periodic => {
+            let period = sample(runner, (len / 2).min(2)..=len / 2)?;
+            let block = pick(runner, chars, period)?;
+            block.iter().cycle().take(len).collect()
+        }
+        StringShape::AllSame => {
+            let idx = sample(runner, 0..chars.len())?;
+            std::iter::repeat_n(chars[idx], len).collect()
+        }
+    };
+    Ok(Value::Token(text))
 }
 
-fn edge_values(spec: &ParsedInput) -> Vec<i64> {
-    let mut values = BTreeSet::new();
+/// Most digits a float input may render after the point; more would exceed
+/// what an `f64` bound can say precisely.
+const MAX_DECIMALS: u32 = 12;
+
+fn parse_float(spec: &InputSpec) -> Result<InputKind> {
+    let decimals = spec.decimals.unwrap_or(6);
+    if decimals > MAX_DECIMALS {
+        bail!("float decimals must be at most {MAX_DECIMALS}, got {decimals}");
+    }
+    if spec.range.is_some() {
+        bail!("float inputs take `min`/`max`, not `range`");
+    }
 
-    for candidate in [
-        spec.min,
-        spec.min.saturating_add(1),
-        spec.max.saturating_sub(1),
-        spec.max,
-        0,
-        1,
-        -1,
-    ] {
-        if (spec.min..=spec.max).contains(&candidate) {
-            values.insert(candidate);
+    let scale = 10f64.powi(decimals as i32);
+    let bound = |bound: &Option<Bound>, default: f64, round: fn(f64) -> f64| {
+        let value = match bound {
+            None => default,
+            Some(Bound::Value(value)) => *value as f64,
+            Some(Bound::Float(value)) if value.is_finite() => *value,
+            Some(other) => bail!("float bounds must be numbers, got {other:?}"),
+        };
+        // Unit counts must stay exact in an f64 and fit an i64.
+        let units = round(value * scale);
+        if units.abs() > 2f64.powi(53) {
+            bail!("float bound {value} is too large for {decimals} decimal(s)");
         }
+        Ok(units as i64)
+    };
+    // Only values that render within [min, max] are drawn.
+    let min = bound(&spec.min, -100.0, f64::ceil)?;
+    let max = bound(&spec.max, 100.0, f64::floor)?;
+    if min > max {
+        bail!("no value with {decimals} decimal(s) lies within the float bounds");
     }
 
-    values.into_iter().collect()
+    Ok(InputKind::Float { min, max, decimals })
 }
 
-fn partition_points(spec: &ParsedInput) -> Vec<i64> {
-    let mut values = BTreeSet::new();
-
-    values.insert(spec.min);
-    values.insert(interpolate(spec.min, spec.max, 1, 4));
-    values.insert(midpoint(spec.min, spec.max));
-    values.insert(interpolate(spec.min, spec.max, 3, 4));
-    values.insert(spec.max);
+fn parse_enum(spec: &InputSpec) -> Result<InputKind> {
+    let values = spec.values.clone().unwrap_or_default();
+    if values.is_empty() {
+        bail!("enum inputs need a non-empty `values` list");
+    }
+    if let Some(bad) = values
+        .iter()
+        .find(|value| value.is_empty() || value.chars().any(char::is_whitespace))
+    {
+        bail!("enum value {bad:?} must be a single non-empty token");
+    }
+
+    if let Some(weights) = &spec.weights {
+        if weights.len() != values.len() {
+            bail!(
+                "enum has {} value(s) but {} weight(s)",
+                values.len(),
+                weights.len()
+            );
+        }
+        if weights.iter().any(|w| !(w.is_finite() && *w >= 0.0)) {
+            bail!("enum weights must be non-negative numbers");
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            bail!("enum weights must not all be zero");
+        }
+    }
+
+    Ok(InputKind::Enum {
+        values,
+        weights: spec.weights.clone(),
+    })
+}
+
+fn parse_distribution(spec: &InputSpec) -> Result<Distribution> {
+    match spec.distribution.as_deref() {
+        None | Some("uniform") => {
+            if spec.skew.is_some() {
+                bail!("skew only applies to distribution = \"zipf\"");
+            }
+            Ok(Distribution::Uniform)
+        }
+        Some("zipf") => {
+            let skew = spec.skew.unwrap_or(1.0);
+            if !(skew.is_finite() && skew > 0.0) {
+                bail!("zipf skew must be a positive number, got {skew}");
+            }
+            Ok(Distribution::Zipf { skew })
+        }
+        Some(other) => bail!("unsupported distribution: {other} (expected uniform or zipf)"),
+    }
+}
+
+/// Bails when the worst-case rendered size of an input holding `per_input`
+/// cases exceeds `max_bytes`, naming the input that contributes the most.
+pub fn check_input_size(specs: &[ParsedInput], max_bytes: u64, per_input: usize) -> Result<()> {
+    let per_input = per_input as u128;
+    let sizes = specs
+        .iter()
+        .map(|spec| {
+            (
+                spec,
+                worst_case_bytes(specs, spec).saturating_mul(per_input),
+            )
+        })
+        .collect::<Vec<_>>();
+    let total = sizes
+        .iter()
+        .fold(0u128, |acc, (_, bytes)| acc.saturating_add(*bytes));
+
+    if total <= u128::from(max_bytes) {
+        return Ok(());
+    }
+
+    let (largest, bytes) = sizes
+        .iter()
+        .max_by_key(|(_, bytes)| *bytes)
+        .expect("non-empty specs");
+    bail!(
+        "worst-case input is {total} bytes, over engine.max_input_bytes ({max_bytes}); \
+         input {} alone may take {bytes} bytes",
+        largest.name
+    );
+}
+
+/// Upper bound of the bytes `spec` occupies in a formatted case, separator included.
+fn worst_case_bytes(specs: &[ParsedInput], spec: &ParsedInput) -> u128 {
+    let widest = match &spec.kind {
+        InputKind::Integer { min, max, .. } => min.to_string().len().max(max.to_string().len()),
+        InputKind::Float { min, max, decimals } => [*min, *max]
+            .map(|units| Value::Float {
+                units,
+                decimals: *decimals,
+            })
+            .iter()
+            .map(|value| value.to_string().len())
+            .max()
+            .unwrap_or(0),
+        InputKind::Str { chars, max_len, .. } => {
+            max_len.saturating_mul(chars.iter().map(|c| c.len_utf8()).max().unwrap_or(1))
+        }
+        InputKind::Array { len, min, max, .. } => {
+            let InputKind::Integer { max: count, .. } = specs[*len].kind else {
+                unreachable!("array lengths are integer inputs");
+            };
+            let item = min.to_string().len().max(max.to_string().len()) as u128 + 1;
+            return (count.max(0) as u128).saturating_mul(item).max(1);
+        }
+        InputKind::Grid { rows, cols, cells } => {
+            let (InputKind::Integer { max: rows, .. }, InputKind::Integer { max: cols, .. }) =
+                (&specs[*rows].kind, &specs[*cols].kind)
+            else {
+                unreachable!("grid sizes are integer inputs");
+            };
+            let cell = match cells {
+                Cells::Int { min, max, .. } => {
+                    min.to_string().len().max(max.to_string().len()) as u128 + 1
+                }
+                Cells::Chars(chars) => {
+                    chars.iter().map(|c| c.len_utf8()).max().unwrap_or(1) as u128
+                }
+            };
+            let row = (*cols).max(0) as u128 * cell + 1;
+            return ((*rows).max(0) as u128).saturating_mul(row).max(1);
+        }
+        InputKind::Enum { values, .. } => values.iter().map(String::len).max().unwrap_or(0),
+        InputKind::Graph(graph) => return graph.worst_case_bytes(),
+    };
+    widest as u128 + 1
+}
+
+/// Which of an input's [`edge_values`] made it into the final case set.
+#[derive(Debug)]
+pub struct EdgeCoverage {
+    pub name: String,
+    pub hit: Vec<Value>,
+    pub missed: Vec<Value>,
+}
+
+pub fn edge_coverage(specs: &[ParsedInput], cases: &[Vec<Value>]) -> Result<Vec<EdgeCoverage>> {
+    specs
+        .iter()
+        .enumerate()
+        .map(|(idx, spec)| {
+            let (hit, missed) = edge_values(spec)?.into_iter().partition(|edge| {
+                cases
+                    .iter()
+                    .any(|case| covers(&spec.kind, &case[idx], edge))
+            });
+            Ok(EdgeCoverage {
+                name: spec.name.clone(),
+                hit,
+                missed,
+            })
+        })
+        .collect()
+}
+
+/// Generates the cases as structured values, one per spec, in spec order.
+pub fn generate_values(
+    specs: &[ParsedInput],
+    cases: usize,
+    seed: u64,
+    pbt: &Pbt,
+) -> Result<Vec<Vec<Value>>> {
+    if cases == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut seeded_cases = Vec::new();
+
+    if pbt.enabled {
+        validate_pbt_config(pbt)?;
+        let edge_budget = ((cases as f64) * pbt.edge_case_ratio).round() as usize;
+        let partition_budget = ((cases as f64) * pbt.partition_ratio).round() as usize;
+
+        extend_edge_cases(
+            &mut seeded_cases,
+            specs,
+            edge_budget,
+            pbt.max_cartesian_cases,
+            pbt.off_by_one,
+        )?;
+        extend_partition_cases(&mut seeded_cases, specs, partition_budget)?;
+    }
+
+    if seeded_cases.len() > cases {
+        seeded_cases.truncate(cases);
+    }
+    let order = dependency_order(specs)?;
+    for case in &mut seeded_cases {
+        settle_dependent(specs, &order, case, None)?;
+    }
+
+    let random_needed = cases.saturating_sub(seeded_cases.len());
+    let adversarial_hash = pbt.enabled && pbt.adversarial_hash;
+    let random_cases = generate_random_cases(specs, random_needed, seed, adversarial_hash)?;
+
+    let mut all_cases = seeded_cases;
+    all_cases.extend(random_cases);
+
+    Ok(all_cases)
+}
+
+fn validate_pbt_config(pbt: &Pbt) -> Result<()> {
+    if !(0.0..=1.0).contains(&pbt.edge_case_ratio) {
+        bail!("pbt.edge_case_ratio must be between 0.0 and 1.0");
+    }
+    if !(0.0..=1.0).contains(&pbt.partition_ratio) {
+        bail!("pbt.partition_ratio must be between 0.0 and 1.0");
+    }
+    if pbt.edge_case_ratio + pbt.partition_ratio > 1.0 {
+        bail!("pbt.edge_case_ratio + pbt.partition_ratio must be <= 1.0");
+    }
+    if pbt.max_cartesian_cases == 0 {
+        bail!("pbt.max_cartesian_cases must be > 0");
+    }
+
+    Ok(())
+}
+
+fn extend_edge_cases(
+    out: &mut Vec<Vec<Value>>,
+    specs: &[ParsedInput],
+    budget: usize,
+    max_cartesian_cases: usize,
+    off_by_one: bool,
+) -> Result<()> {
+    if budget == 0 || specs.is_empty() {
+        return Ok(());
+    }
+
+    let edge_sets = specs.iter().map(edge_values).collect::<Result<Vec<_>>>()?;
+    let mins = edge_sets
+        .iter()
+        .map(|set| set[0].clone())
+        .collect::<Vec<_>>();
+    let maxs = edge_sets
+        .iter()
+        .map(|set| set[set.len() - 1].clone())
+        .collect::<Vec<_>>();
+    let mids = specs.iter().map(mid_value).collect::<Result<Vec<_>>>()?;
+
+    push_unique(out, mids.clone(), budget);
+    push_unique(out, mins.clone(), budget);
+    push_unique(out, maxs.clone(), budget);
+
+    let alternate = |first: &[Value], second: &[Value]| {
+        (0..specs.len())
+            .map(|i| {
+                if i % 2 == 0 {
+                    first[i].clone()
+                } else {
+                    second[i].clone()
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    push_unique(out, alternate(&mins, &maxs), budget);
+    push_unique(out, alternate(&maxs, &mins), budget);
+
+    if off_by_one {
+        for case in off_by_one_clusters(specs, &mids) {
+            push_unique(out, case, budget);
+        }
+    }
+
+    for (idx, edges) in edge_sets.iter().enumerate() {
+        for edge in edges {
+            let mut candidate = mids.clone();
+            candidate[idx] = edge.clone();
+            push_unique(out, candidate, budget);
+
+            if out.len() >= budget {
+                return Ok(());
+            }
+        }
+    }
+
+    let total_cartesian = edge_sets
+        .iter()
+        .fold(1usize, |acc, set| acc.saturating_mul(set.len()));
+
+    if total_cartesian == 0 || total_cartesian > max_cartesian_cases {
+        return Ok(());
+    }
+
+    let mut stack = Vec::with_capacity(specs.len());
+    cartesian_collect(&edge_sets, 0, &mut stack, out, budget);
+    Ok(())
+}
+
+/// For each integer input, consecutive cases with it at `k-1, k, k+1` around
+/// its min, midpoint and max (staying in range) and every other input at its
+/// midpoint, so neighbouring cases differ by exactly one.
+fn off_by_one_clusters(specs: &[ParsedInput], mids: &[Value]) -> Vec<Vec<Value>> {
+    let mut cases = Vec::new();
+    for (idx, spec) in specs.iter().enumerate() {
+        let InputKind::Integer { min, max, .. } = spec.kind else {
+            continue;
+        };
+        for point in [min, midpoint(min, max), max] {
+            for delta in [-1, 0, 1] {
+                let Some(value) = point.checked_add(delta).filter(|v| (min..=max).contains(v))
+                else {
+                    continue;
+                };
+                let mut case = mids.to_vec();
+                case[idx] = Value::Int(value);
+                cases.push(case);
+            }
+        }
+    }
+    cases
+}
+
+fn cartesian_collect(
+    edge_sets: &[Vec<Value>],
+    depth: usize,
+    stack: &mut Vec<Value>,
+    out: &mut Vec<Vec<Value>>,
+    budget: usize,
+) {
+    if out.len() >= budget {
+        return;
+    }
+
+    if depth == edge_sets.len() {
+        push_unique(out, stack.clone(), budget);
+        return;
+    }
+
+    for value in &edge_sets[depth] {
+        if out.len() >= budget {
+            return;
+        }
+
+        stack.push(value.clone());
+        cartesian_collect(edge_sets, depth + 1, stack, out, budget);
+        stack.pop();
+    }
+}
+
+fn extend_partition_cases(
+    out: &mut Vec<Vec<Value>>,
+    specs: &[ParsedInput],
+    budget: usize,
+) -> Result<()> {
+    if budget == 0 || specs.is_empty() {
+        return Ok(());
+    }
+
+    let partition_values = specs
+        .iter()
+        .map(partition_points)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut cursor = 0usize;
+    while out.len() < budget {
+        let mut values = Vec::with_capacity(specs.len());
+
+        for (idx, points) in partition_values.iter().enumerate() {
+            let point = &points[(cursor + idx) % points.len()];
+            values.push(point.clone());
+        }
+
+        push_unique(out, values, budget);
+        cursor += 1;
+
+        if cursor > budget.saturating_mul(4) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn generate_random_cases(
+    specs: &[ParsedInput],
+    count: usize,
+    seed: u64,
+    adversarial_hash: bool,
+) -> Result<Vec<Vec<Value>>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut runner = build_proptest_runner(seed);
+    let order = dependency_order(specs)?;
+    let hash_pools = specs
+        .iter()
+        .map(|spec| match &spec.kind {
+            InputKind::Integer { min, max, .. } if adversarial_hash => {
+                hash_collision_pool(*min, *max)
+            }
+            _ => Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    let mut out = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let mut values = Vec::with_capacity(specs.len());
+
+        for (spec, pool) in specs.iter().zip(&hash_pools) {
+            if !pool.is_empty() && sample(&mut runner, proptest::bool::ANY)? {
+                let idx = sample(&mut runner, 0..pool.len())?;
+                values.push(Value::Int(pool[idx]));
+                continue;
+            }
+            values.push(random_value(&mut runner, spec)?);
+        }
 
-    if (spec.min..=spec.max).contains(&0) {
-        values.insert(0);
+        settle_dependent(specs, &order, &mut values, Some(&mut runner))?;
+        out.push(values);
     }
 
-    values.into_iter().collect()
+    Ok(out)
+}
+
+fn random_value(runner: &mut TestRunner, spec: &ParsedInput) -> Result<Value> {
+    match &spec.kind {
+        InputKind::Integer {
+            min,
+            max,
+            distribution,
+            ..
+        } => Ok(Value::Int(sample_integer(
+            runner,
+            *min,
+            *max,
+            *distribution,
+        )?)),
+        InputKind::Float { min, max, decimals } => Ok(Value::Float {
+            units: sample(runner, *min..=*max)?,
+            decimals: *decimals,
+        }),
+        InputKind::Str {
+            chars,
+            min_len,
+            max_len,
+            ..
+        } => {
+            let picks = sample(
+                runner,
+                proptest::collection::vec(0..chars.len(), *min_len..=*max_len),
+            )?;
+            Ok(Value::Token(
+                picks.into_iter().map(|idx| chars[idx]).collect(),
+            ))
+        }
+        // Drawn in `settle_dependent`, once the length is known.
+        InputKind::Array { .. } => Ok(Value::Array(Vec::new())),
+        InputKind::Grid { cells, .. } => Ok(Value::Grid {
+            cells: Vec::new(),
+            chars: cells.chars(),
+        }),
+        InputKind::Enum { values, weights } => {
+            let idx = match weights {
+                None => sample(runner, 0..values.len())?,
+                Some(weights) => sample_weighted(runner, weights)?,
+            };
+            Ok(Value::Token(values[idx].clone()))
+        }
+        InputKind::Graph(graph) => Ok(Value::Graph(graph.random_graph(runner)?)),
+    }
+}
+
+/// `count` distinct integers from `[min, max]` in random order. Floyd's
+/// algorithm picks the set without materializing the range, then a shuffle
+/// orders it.
+fn sample_distinct(runner: &mut TestRunner, min: i64, max: i64, count: usize) -> Result<Vec<i64>> {
+    let size = (i128::from(max) - i128::from(min) + 1).min(i128::from(u64::MAX)) as u64;
+    let count = count as u64;
+    let mut chosen = BTreeSet::new();
+    let mut picks = Vec::new();
+    for top in size - count..size {
+        let offset = sample(runner, 0..=top)?;
+        let offset = if chosen.insert(offset) {
+            offset
+        } else {
+            chosen.insert(top);
+            top
+        };
+        picks.push((i128::from(min) + i128::from(offset)) as i64);
+    }
+    for idx in (1..picks.len()).rev() {
+        let other = sample(runner, 0..=idx)?;
+        picks.swap(idx, other);
+    }
+    Ok(picks)
+}
+
+fn sample_cell(runner: &mut TestRunner, cells: &Cells) -> Result<i64> {
+    match cells {
+        Cells::Int {
+            min,
+            max,
+            distribution,
+        } => sample_integer(runner, *min, *max, *distribution),
+        Cells::Chars(chars) => Ok(sample(runner, 0..chars.len())? as i64),
+    }
+}
+
+fn sample_integer(
+    runner: &mut TestRunner,
+    min: i64,
+    max: i64,
+    distribution: Distribution,
+) -> Result<i64> {
+    match distribution {
+        Distribution::Uniform => sample(runner, min..=max),
+        Distribution::Zipf { skew } => sample_zipf(runner, min, max, skew),
+    }
+}
+
+/// Bucket counts that common hash tables land on: powers of two for maps
+/// masking the low bits, and the bucket primes libstdc++'s `unordered_map`
+/// reaches around 10^5 elements (107897 before GCC 7, 126271 since).
+const HASH_MODULI: [i64; 4] = [1 << 16, 1 << 20, 107_897, 126_271];
+
+/// Values in `[min, max]` that share a bucket under [`HASH_MODULI`], i.e. small
+/// multiples of each modulus, so that solutions keyed by these values degrade
+/// to linear probing or long chains.
+fn hash_collision_pool(min: i64, max: i64) -> Vec<i64> {
+    let mut pool = BTreeSet::new();
+    for modulus in HASH_MODULI {
+        for k in -32i64..=32 {
+            let value = i128::from(modulus) * i128::from(k);
+            if (i128::from(min)..=i128::from(max)).contains(&value) {
+                pool.insert(value as i64);
+            }
+        }
+    }
+    pool.into_iter().collect()
+}
+
+pub(crate) fn sample<S: Strategy>(runner: &mut TestRunner, strategy: S) -> Result<S::Value> {
+    let tree = strategy
+        .new_tree(runner)
+        .map_err(|e| anyhow::anyhow!("failed to generate random case from strategy: {e}"))?;
+    Ok(tree.current())
+}
+
+/// Picks an index with probability proportional to its weight.
+fn sample_weighted(runner: &mut TestRunner, weights: &[f64]) -> Result<usize> {
+    let total = weights.iter().sum::<f64>();
+    let mut target = sample(runner, 0.0..total)?;
+    for (idx, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return Ok(idx);
+        }
+        target -= weight;
+    }
+    Ok(weights.iter().rposition(|w| *w > 0.0).unwrap_or(0))
+}
+
+/// Zipf sampling by rejection-inversion (Hörmann & Derflinger), which stays
+/// O(1) per draw even for ranges spanning the whole `i64` domain.
+fn sample_zipf(runner: &mut TestRunner, min: i64, max: i64, skew: f64) -> Result<i64> {
+    let n = (i128::from(max) - i128::from(min) + 1) as f64;
+    let q = if skew != 1.0 { 1.0 / (1.0 - skew) } else { 0.0 };
+    let t = if skew != 1.0 {
+        (n.powf(1.0 - skew) - skew) * q
+    } else {
+        1.0 + n.ln()
+    };
+    let inv_cdf = |p: f64| {
+        let pt = p * t;
+        if pt <= 1.0 {
+            pt
+        } else if skew != 1.0 {
+            (pt * (1.0 - skew) + skew).powf(q)
+        } else {
+            (pt - 1.0).exp()
+        }
+    };
+
+    let rank = loop {
+        let inv_b = inv_cdf(sample(runner, 0.0f64..1.0)?);
+        let x = (inv_b + 1.0).floor();
+        let mut ratio = x.powf(-skew);
+        if x > 1.0 {
+            ratio *= inv_b.powf(skew);
+        }
+        if sample(runner, 0.0f64..1.0)? < ratio {
+            break x.min(n);
+        }
+    };
+
+    Ok((i128::from(min) + rank as i128 - 1) as i64)
+}
+
+/// Case count of each of `batches` multi-test inputs, drawn from
+/// `t_min..=t_max`. The first two batches take the extremes so both are
+/// always exercised.
+pub fn batch_sizes(batches: usize, t_min: usize, t_max: usize, seed: u64) -> Result<Vec<usize>> {
+    let mut runner = build_proptest_runner(seed.rotate_left(32));
+    (0..batches)
+        .map(|batch| match batch {
+            0 => Ok(t_min),
+            1 => Ok(t_max),
+            _ => sample(&mut runner, t_min..=t_max),
+        })
+        .collect()
+}
+
+fn build_proptest_runner(seed: u64) -> TestRunner {
+    let seed_bytes = seed_to_bytes(seed);
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &seed_bytes);
+    TestRunner::new_with_rng(RunnerConfig::default(), rng)
+}
+
+fn seed_to_bytes(seed: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+
+    for i in 0..4 {
+        let part = seed.wrapping_add(i as u64).to_le_bytes();
+        let start = i * 8;
+        out[start..start + 8].copy_from_slice(&part);
+    }
+
+    out
+}
+
+/// Boundary values of `spec`, smallest first and largest last.
+fn edge_values(spec: &ParsedInput) -> Result<Vec<Value>> {
+    match &spec.kind {
+        InputKind::Integer { min, max, .. } => {
+            let mut values = BTreeSet::new();
+
+            for candidate in [
+                *min,
+                min.saturating_add(1),
+                max.saturating_sub(1),
+                *max,
+                0,
+                1,
+                -1,
+            ] {
+                if (*min..=*max).contains(&candidate) {
+                    values.insert(candidate);
+                }
+            }
+
+            Ok(values.into_iter().map(Value::Int).collect())
+        }
+        // The bounds, the values one unit inside them, and zero.
+        InputKind::Float { min, max, decimals } => {
+            let values = [*min, min.saturating_add(1), max.saturating_sub(1), *max, 0]
+                .into_iter()
+                .filter(|units| (*min..=*max).contains(units))
+                .collect::<BTreeSet<_>>();
+            Ok(values
+                .into_iter()
+                .map(|units| Value::Float {
+                    units,
+                    decimals: *decimals,
+                })
+                .collect())
+        }
+        // The shortest and longest strings, plus the empty and single-character
+        // ones when the lengths allow them, and a longest string of each of
+        // `shapes` just before the last.
+        InputKind::Str {
+            chars,
+            min_len,
+            max_len,
+            shapes,
+        } => {
+            let lengths = [0, 1, *min_len, *max_len]
+                .into_iter()
+                .filter(|len| (*min_len..=*max_len).contains(len))
+                .collect::<BTreeSet<_>>();
+            let mut values = lengths
+                .into_iter()
+                .map(|len| cycled_string(chars, len))
+                .collect::<Vec<_>>();
+            let longest = values.pop();
+            if *max_len >= 2 {
+                let mut runner = structure_runner();
+                for &shape in shapes {
+                    let value = shaped_string(&mut runner, chars, *max_len, shape)?;
+                    if !values.contains(&value) && longest.as_ref() != Some(&value) {
+                        values.push(value);
+                    }
+                }
+            }
+            values.extend(longest);
+            Ok(values)
+        }
+        // Ascending from min, ending at max and descending from max, at
+        // whatever length the case has.
+        InputKind::Array {
+            min,
+            max,
+            distinct: true,
+            ..
+        } => {
+            let patterns = BTreeSet::from([vec![*min], vec![*max], vec![*max, *min]]);
+            Ok(patterns.into_iter().map(Value::Array).collect())
+        }
+        // All-min, all-max and alternating arrays, at whatever length the case has.
+        InputKind::Array { min, max, .. } => {
+            let patterns = BTreeSet::from([vec![*min], vec![*min, *max], vec![*max]]);
+            Ok(patterns.into_iter().map(Value::Array).collect())
+        }
+        // All-min, all-max and checkerboard grids, at whatever size the case has.
+        InputKind::Grid { cells, .. } => {
+            let (min, max) = cells.bounds();
+            let patterns = [
+                vec![vec![min]],
+                vec![vec![max]],
+                vec![vec![min, max], vec![max, min]],
+            ];
+            Ok(grid_patterns(cells, patterns))
+        }
+        // Every enum value is an edge, so seeded cases cover each one.
+        InputKind::Enum { values, .. } => Ok(values.iter().cloned().map(Value::Token).collect()),
+        InputKind::Graph(graph) => Ok(graph
+            .edge_graphs(&mut structure_runner())?
+            .into_iter()
+            .map(Value::Graph)
+            .collect()),
+    }
+}
+
+fn partition_points(spec: &ParsedInput) -> Result<Vec<Value>> {
+    match &spec.kind {
+        InputKind::Integer { min, max, .. } => {
+            let (min, max) = (*min, *max);
+            let mut values = BTreeSet::new();
+
+            values.insert(min);
+            values.insert(interpolate(min, max, 1, 4));
+            values.insert(midpoint(min, max));
+            values.insert(interpolate(min, max, 3, 4));
+            values.insert(max);
+
+            if (min..=max).contains(&0) {
+                values.insert(0);
+            }
+
+            Ok(values.into_iter().map(Value::Int).collect())
+        }
+        InputKind::Float { min, max, decimals } => {
+            let (min, max) = (*min, *max);
+            let mut values = BTreeSet::from([
+                min,
+                interpolate(min, max, 1, 4),
+                midpoint(min, max),
+                interpolate(min, max, 3, 4),
+                max,
+            ]);
+            if (min..=max).contains(&0) {
+                values.insert(0);
+            }
+
+            Ok(values
+                .into_iter()
+                .map(|units| Value::Float {
+                    units,
+                    decimals: *decimals,
+                })
+                .collect())
+        }
+        InputKind::Str {
+            chars,
+            min_len,
+            max_len,
+            ..
+        } => {
+            let lengths = BTreeSet::from([*min_len, min_len + (max_len - min_len) / 2, *max_len]);
+            Ok(lengths
+                .into_iter()
+                .map(|len| cycled_string(chars, len))
+                .collect())
+        }
+        InputKind::Array { min, max, .. } => {
+            let (min, max) = (*min, *max);
+            let quartiles = vec![
+                min,
+                interpolate(min, max, 1, 4),
+                midpoint(min, max),
+                interpolate(min, max, 3, 4),
+                max,
+            ];
+            let mut descending = quartiles.clone();
+            descending.reverse();
+            Ok(vec![
+                Value::Array(vec![midpoint(min, max)]),
+                Value::Array(quartiles),
+                Value::Array(descending),
+            ])
+        }
+        InputKind::Grid { cells, .. } => {
+            let (min, max) = cells.bounds();
+            let quartiles = vec![
+                min,
+                interpolate(min, max, 1, 4),
+                midpoint(min, max),
+                interpolate(min, max, 3, 4),
+                max,
+            ];
+            Ok(grid_patterns(
+                cells,
+                [vec![vec![midpoint(min, max)]], vec![quartiles]],
+            ))
+        }
+        InputKind::Enum { values, .. } => Ok(values.iter().cloned().map(Value::Token).collect()),
+        InputKind::Graph(graph) => Ok(graph
+            .partition_graphs(&mut structure_runner())?
+            .into_iter()
+            .map(Value::Graph)
+            .collect()),
+    }
+}
+
+fn mid_value(spec: &ParsedInput) -> Result<Value> {
+    match &spec.kind {
+        InputKind::Integer { min, max, .. } => Ok(Value::Int(midpoint(*min, *max))),
+        InputKind::Float { min, max, decimals } => Ok(Value::Float {
+            units: midpoint(*min, *max),
+            decimals: *decimals,
+        }),
+        InputKind::Str {
+            chars,
+            min_len,
+            max_len,
+            ..
+        } => Ok(cycled_string(chars, min_len + (max_len - min_len) / 2)),
+        InputKind::Array { min, max, .. } => Ok(Value::Array(vec![midpoint(*min, *max)])),
+        InputKind::Grid { cells, .. } => {
+            let (min, max) = cells.bounds();
+            Ok(Value::Grid {
+                cells: vec![vec![midpoint(min, max)]],
+                chars: cells.chars(),
+            })
+        }
+        InputKind::Enum { values, .. } => Ok(Value::Token(values[values.len() / 2].clone())),
+        InputKind::Graph(_) => {
+            let mut points = partition_points(spec)?;
+            Ok(points.swap_remove(points.len() / 2))
+        }
+    }
+}
+
+/// Seeded grid patterns as values, without duplicates from a narrow range.
+fn grid_patterns(cells: &Cells, patterns: impl IntoIterator<Item = Vec<Vec<i64>>>) -> Vec<Value> {
+    patterns
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|pattern| Value::Grid {
+            cells: pattern,
+            chars: cells.chars(),
+        })
+        .collect()
+}
+
+/// Fixed-seed runner for the random parts of seeded structured values, so
+/// edge and partition cases come out the same regardless of `engine.seed`.
+fn structure_runner() -> TestRunner {
+    build_proptest_runner(0)
 }
 
 fn midpoint(min: i64, max: i64) -> i64 {
@@ -296,7 +1805,7 @@ fn interpolate(min: i64, max: i64, num: i64, den: i64) -> i64 {
     value as i64
 }
 
-fn push_unique(out: &mut Vec<Vec<i64>>, values: Vec<i64>, budget: usize) {
+fn push_unique(out: &mut Vec<Vec<Value>>, values: Vec<Value>, budget: usize) {
     if out.len() >= budget {
         return;
     }
@@ -306,41 +1815,109 @@ fn push_unique(out: &mut Vec<Vec<i64>>, values: Vec<i64>, budget: usize) {
     }
 }
 
-fn format_case(values: &[i64]) -> String {
-    let body = values
-        .iter()
-        .map(i64::to_string)
-        .collect::<Vec<_>>()
-        .join(" ");
-    format!("{body}\n")
+/// Renders a case: consecutive scalars share a space-separated line, block
+/// values start on a line of their own.
+pub fn format_case(values: &[Value]) -> String {
+    let mut lines = Vec::new();
+    let mut scalars = Vec::new();
+
+    for value in values {
+        if value.is_block() {
+            if !scalars.is_empty() {
+                lines.push(scalars.join(" "));
+                scalars.clear();
+            }
+            lines.push(value.to_string());
+        } else {
+            scalars.push(value.to_string());
+        }
+    }
+    if !scalars.is_empty() {
+        lines.push(scalars.join(" "));
+    }
+
+    format!("{}\n", lines.join("\n"))
 }
 
-pub fn parse_bounds(spec: &InputSpec) -> Result<(i64, i64)> {
-    let mut min = spec.min.unwrap_or(-100);
-    let mut max = spec.max.unwrap_or(100);
+/// Whether `min`, `max` or a `range` token refers to other inputs.
+fn has_expr_bounds(spec: &InputSpec) -> Result<bool> {
+    let bound_expr = [&spec.min, &spec.max]
+        .iter()
+        .any(|bound| matches!(bound, Some(Bound::Expr(_))));
+    Ok(bound_expr || !range_constraints(spec)?.1.is_empty())
+}
 
-    if let Some(range) = &spec.range {
-        for token in range.split(&[',', '&'][..]) {
-            let token = token.trim();
-            if token.is_empty() {
-                continue;
+/// Per-case bounds of an integer input whose `min`, `max` or `range` refers
+/// to other inputs. Every lower bound applies, so `min` is the largest of
+/// them and `max` the smallest of the upper ones.
+fn dependent_bounds(spec: &InputSpec) -> Result<DependentBounds> {
+    let base = |bound: Option<&Bound>, default| match bound {
+        Some(Bound::Expr(source)) => {
+            expr::parse(source).with_context(|| format!("invalid bound `{source}`"))
+        }
+        Some(Bound::Value(value)) => Ok(Expr::Number(Number::Int(i128::from(*value)))),
+        _ => Ok(Expr::Number(Number::Int(default))),
+    };
+    let mut lower = vec![base(spec.min.as_ref(), -100)?];
+    let mut upper = vec![base(spec.max.as_ref(), 100)?];
+
+    let (numeric, exprs) = range_constraints(spec)?;
+    let numeric = numeric
+        .into_iter()
+        .map(|(op, value)| (op, Expr::Number(Number::Int(i128::from(value)))));
+    let one = || Box::new(Expr::Number(Number::Int(1)));
+    for (op, value) in numeric.chain(exprs) {
+        match op {
+            ">" => lower.push(Expr::Binary(Op::Add, Box::new(value), one())),
+            ">=" => lower.push(value),
+            "<" => upper.push(Expr::Binary(Op::Sub, Box::new(value), one())),
+            "<=" => upper.push(value),
+            _ => {
+                lower.push(value.clone());
+                upper.push(value);
             }
+        }
+    }
 
-            let Some((op, value)) = parse_constraint(token) else {
-                bail!("unsupported range expression token: {token}");
-            };
+    fn limit(mut terms: Vec<Expr>, func: Func) -> Limit {
+        if terms.len() > 1 {
+            return Limit::Expr(Expr::Call(func, terms));
+        }
+        match terms.remove(0) {
+            // Numbers here all started out as `i64`.
+            Expr::Number(Number::Int(value)) => Limit::Fixed(value as i64),
+            expr => Limit::Expr(expr),
+        }
+    }
+    Ok(DependentBounds {
+        min: limit(lower, Func::Max),
+        max: limit(upper, Func::Min),
+        refs: Vec::new(),
+    })
+}
 
-            match op {
-                ">" => min = min.max(value + 1),
-                ">=" => min = min.max(value),
-                "<" => max = max.min(value - 1),
-                "<=" => max = max.min(value),
-                "==" => {
-                    min = value;
-                    max = value;
-                }
-                _ => bail!("unsupported operator: {op}"),
+/// Numeric bounds of an integer input. Expression bounds are resolved per
+/// case, so here they count as the default.
+pub fn parse_bounds(spec: &InputSpec) -> Result<(i64, i64)> {
+    let numeric = |bound: &Option<Bound>, default| match bound {
+        Some(Bound::Value(value)) => Ok(*value),
+        Some(Bound::Float(value)) => bail!("integer bounds must be whole numbers, got {value}"),
+        _ => Ok(default),
+    };
+    let mut min = numeric(&spec.min, -100)?;
+    let mut max = numeric(&spec.max, 100)?;
+
+    for (op, value) in range_constraints(spec)?.0 {
+        match op {
+            ">" => min = min.max(value + 1),
+            ">=" => min = min.max(value),
+            "<" => max = max.min(value - 1),
+            "<=" => max = max.min(value),
+            "==" => {
+                min = value;
+                max = value;
             }
+            _ => bail!("unsupported operator: {op}"),
         }
     }
 
@@ -348,25 +1925,68 @@ pub fn parse_bounds(spec: &InputSpec) -> Result<(i64, i64)> {
         bail!("invalid bounds: min({min}) > max({max})");
     }
 
-    Ok((min, max))
+    Ok((min, max))
+}
+
+/// The `range` tokens comparing against a number, then those comparing
+/// against an expression over other inputs, such as `< n`.
+type Constraints<'a> = (Vec<(&'a str, i64)>, Vec<(&'a str, Expr)>);
+
+fn range_constraints(spec: &InputSpec) -> Result<Constraints<'_>> {
+    let mut numeric = Vec::new();
+    let mut exprs = Vec::new();
+    let Some(range) = &spec.range else {
+        return Ok((numeric, exprs));
+    };
+
+    for token in range.split(&[',', '&'][..]) {
+        let token = token.trim();
+        if token.is_empty() {
+            continue;
+        }
+
+        let Some((op, operand)) = parse_constraint(token) else {
+            bail!("unsupported range expression token: {token}");
+        };
+        match operand.parse::<i64>() {
+            Ok(value) => numeric.push((op, value)),
+            Err(_) => {
+                let expr = expr::parse(operand)
+                    .with_context(|| format!("unsupported range expression token: {token}"))?;
+                exprs.push((op, expr));
+            }
+        }
+    }
+    Ok((numeric, exprs))
 }
 
-fn parse_constraint(token: &str) -> Option<(&str, i64)> {
+fn parse_constraint(token: &str) -> Option<(&str, &str)> {
     static RE: OnceLock<Regex> = OnceLock::new();
-    let re = RE.get_or_init(|| Regex::new(r"^(<=|>=|<|>|==)\s*(-?\d+)$").expect("valid regex"));
+    let re = RE.get_or_init(|| Regex::new(r"^(<=|>=|<|>|==)\s*(.+)$").expect("valid regex"));
 
     let caps = re.captures(token)?;
-    let op = caps.get(1)?.as_str();
-    let value = caps.get(2)?.as_str().parse().ok()?;
-    Some((op, value))
+    Some((caps.get(1)?.as_str(), caps.get(2)?.as_str()))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{InputSpec, Pbt, Problem};
-    use std::collections::BTreeMap;
-
-    use super::{generate_inputs, parse_bounds, parse_problem_inputs};
+    use crate::config::{Bound, Count, InputSpec, Pbt, Problem, RangeSpec, StringShape};
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::{
+        batch_sizes, format_case, generate_values, parse_bounds, parse_problem_inputs, ParsedInput,
+        Value, HASH_MODULI,
+    };
+
+    fn generate_inputs(
+        specs: &[ParsedInput],
+        cases: usize,
+        seed: u64,
+        pbt: &Pbt,
+    ) -> anyhow::Result<Vec<String>> {
+        let values = generate_values(specs, cases, seed, pbt)?;
+        Ok(values.iter().map(|case| format_case(case)).collect())
+    }
 
     #[test]
     fn parse_range_tokens() {
@@ -375,6 +1995,7 @@ mod tests {
             range: Some(">= 1, <= 9".to_string()),
             min: None,
             max: None,
+            ..InputSpec::default()
         };
 
         let (min, max) = parse_bounds(&spec).expect("must parse");
@@ -387,8 +2008,9 @@ mod tests {
         let spec = InputSpec {
             kind: "integer".to_string(),
             range: Some("> 0".to_string()),
-            min: Some(5),
-            max: Some(10),
+            min: Some(5.into()),
+            max: Some(10.into()),
+            ..InputSpec::default()
         };
 
         let (min, max) = parse_bounds(&spec).expect("must parse");
@@ -396,6 +2018,653 @@ mod tests {
         assert_eq!(max, 10);
     }
 
+    #[test]
+    fn zipf_distribution_favors_low_ranks() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "a".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                min: Some(1.into()),
+                max: Some(1000.into()),
+                distribution: Some("zipf".to_string()),
+                skew: Some(1.5),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+        let pbt = Pbt {
+            enabled: false,
+            ..Pbt::default()
+        };
+
+        let samples = generate_inputs(&specs, 500, 7, &pbt).expect("generate");
+        let ones = samples.iter().filter(|line| line.trim() == "1").count();
+        assert!(ones > 100, "expected rank 1 to dominate, got {ones}/500");
+        assert!(samples
+            .iter()
+            .all(|line| (1..=1000).contains(&line.trim().parse::<i64>().unwrap())));
+    }
+
+    #[test]
+    fn enum_edges_cover_every_value() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "op".to_string(),
+            InputSpec {
+                kind: "enum".to_string(),
+                values: Some(vec!["ADD".into(), "REMOVE".into(), "QUERY".into()]),
+                weights: Some(vec![1.0, 0.0, 3.0]),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let samples = generate_inputs(&specs, 40, 3, &Pbt::default()).expect("generate");
+        for value in ["ADD", "REMOVE", "QUERY"] {
+            assert!(samples.iter().any(|line| line.trim() == value));
+        }
+
+        let random = &samples[samples.len() - 20..];
+        assert!(random.iter().all(|line| line.trim() != "REMOVE"));
+    }
+
+    #[test]
+    fn float_inputs_render_exact_decimals_and_seed_bounds() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "x".to_string(),
+            InputSpec {
+                kind: "float".to_string(),
+                min: Some(Bound::Float(-1.5)),
+                max: Some(Bound::Float(2.25)),
+                decimals: Some(2),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let samples = generate_inputs(&specs, 60, 5, &Pbt::default()).expect("generate");
+        for edge in ["-1.50", "-1.49", "0.00", "2.24", "2.25"] {
+            assert!(
+                samples.iter().any(|line| line.trim() == edge),
+                "missing {edge}"
+            );
+        }
+        for line in &samples {
+            let value = line.trim();
+            assert_eq!(value.split('.').nth(1).map(str::len), Some(2), "{value}");
+            assert!((-1.5..=2.25).contains(&value.parse::<f64>().unwrap()));
+        }
+    }
+
+    #[test]
+    fn string_inputs_respect_charset_and_lengths() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "s".to_string(),
+            InputSpec {
+                kind: "string".to_string(),
+                charset: Some("ab0-2".to_string()),
+                min_len: Some(0),
+                max_len: Some(6),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let samples = generate_inputs(&specs, 60, 9, &Pbt::default()).expect("generate");
+        for edge in ["", "0", "012ab0"] {
+            assert!(
+                samples
+                    .iter()
+                    .any(|line| line.trim_end_matches('\n') == edge),
+                "missing {edge:?}"
+            );
+        }
+        for line in &samples {
+            let value = line.trim_end_matches('\n');
+            assert!(value.len() <= 6, "{value}");
+            assert!(value.chars().all(|c| "ab012".contains(c)), "{value}");
+        }
+    }
+
+    #[test]
+    fn array_length_follows_its_length_input() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "n".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                min: Some(0.into()),
+                max: Some(6.into()),
+                ..InputSpec::default()
+            },
+        );
+        inputs.insert(
+            "xs".to_string(),
+            InputSpec {
+                kind: "array".to_string(),
+                len: Some("n".to_string()),
+                element: Some(Box::new(InputSpec {
+                    kind: "integer".to_string(),
+                    min: Some((-5).into()),
+                    max: Some(5.into()),
+                    ..InputSpec::default()
+                })),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let samples = generate_inputs(&specs, 50, 11, &Pbt::default()).expect("generate");
+        assert!(samples.iter().any(|case| case == "6\n5 5 5 5 5 5\n"));
+        for case in &samples {
+            let lines = case.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 2, "{case:?}");
+            let n = lines[0].parse::<usize>().unwrap();
+            let xs = lines[1]
+                .split_whitespace()
+                .map(|x| x.parse::<i64>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(xs.len(), n, "{case:?}");
+            assert!(xs.iter().all(|x| (-5..=5).contains(x)));
+        }
+    }
+
+    #[test]
+    fn distinct_arrays_never_repeat_and_must_fit_their_range() {
+        let problem = |max_len: i64| {
+            let mut inputs = BTreeMap::new();
+            inputs.insert(
+                "n".to_string(),
+                InputSpec {
+                    kind: "integer".to_string(),
+                    min: Some(0.into()),
+                    max: Some(max_len.into()),
+                    ..InputSpec::default()
+                },
+            );
+            inputs.insert(
+                "xs".to_string(),
+                InputSpec {
+                    kind: "array".to_string(),
+                    len: Some("n".to_string()),
+                    distinct: true,
+                    element: Some(Box::new(InputSpec {
+                        kind: "integer".to_string(),
+                        min: Some(1.into()),
+                        max: Some(8.into()),
+                        ..InputSpec::default()
+                    })),
+                    ..InputSpec::default()
+                },
+            );
+            Problem {
+                inputs,
+                ..Problem::default()
+            }
+        };
+
+        let specs = parse_problem_inputs(&problem(8)).expect("parse");
+        let samples = generate_inputs(&specs, 60, 5, &Pbt::default()).expect("generate");
+        assert!(samples.iter().any(|case| case == "8\n1 2 3 4 5 6 7 8\n"));
+        assert!(samples.iter().any(|case| case == "8\n8 7 6 5 4 3 2 1\n"));
+        for case in &samples {
+            let lines = case.lines().collect::<Vec<_>>();
+            let xs = lines[1]
+                .split_whitespace()
+                .map(|x| x.parse::<i64>().unwrap())
+                .collect::<BTreeSet<_>>();
+            assert_eq!(xs.len(), lines[0].parse::<usize>().unwrap(), "{case:?}");
+            assert!(xs.iter().all(|x| (1..=8).contains(x)));
+        }
+
+        let error = parse_problem_inputs(&problem(9)).unwrap_err();
+        assert!(format!("{error:#}").contains("input xs: distinct array"));
+    }
+
+    #[test]
+    fn grid_size_follows_its_row_and_column_inputs() {
+        let mut inputs = BTreeMap::new();
+        for (name, max) in [("c", 3), ("r", 2)] {
+            inputs.insert(
+                name.to_string(),
+                InputSpec {
+                    kind: "integer".to_string(),
+                    min: Some(1.into()),
+                    max: Some(max.into()),
+                    ..InputSpec::default()
+                },
+            );
+        }
+        inputs.insert(
+            "s".to_string(),
+            InputSpec {
+                kind: "grid".to_string(),
+                rows: Some("r".to_string()),
+                cols: Some("c".to_string()),
+                element: Some(Box::new(InputSpec {
+                    kind: "char".to_string(),
+                    charset: Some(".#".to_string()),
+                    ..InputSpec::default()
+                })),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let samples = generate_inputs(&specs, 40, 3, &Pbt::default()).expect("generate");
+        for grid in ["3 2\n...\n...\n", "2 2\n##\n##\n", "2 2\n.#\n#.\n"] {
+            assert!(samples.iter().any(|case| case == grid), "{grid:?}");
+        }
+        for case in &samples {
+            let mut lines = case.lines();
+            let sizes = lines
+                .next()
+                .unwrap()
+                .split(' ')
+                .map(|size| size.parse::<usize>().unwrap())
+                .collect::<Vec<_>>();
+            let rows = lines.collect::<Vec<_>>();
+            assert_eq!(rows.len(), sizes[1], "{case:?}");
+            assert!(rows
+                .iter()
+                .all(|row| row.len() == sizes[0] && row.chars().all(|c| ".#".contains(c))));
+        }
+    }
+
+    #[test]
+    fn array_indexing_shifts_index_values_by_the_base() {
+        let problem = |kind: &str, indexing: Option<u8>| {
+            let mut inputs = BTreeMap::new();
+            inputs.insert(
+                "n".to_string(),
+                InputSpec {
+                    kind: "integer".to_string(),
+                    min: Some(1.into()),
+                    max: Some(5.into()),
+                    ..InputSpec::default()
+                },
+            );
+            inputs.insert(
+                "p".to_string(),
+                InputSpec {
+                    kind: kind.to_string(),
+                    len: Some("n".to_string()),
+                    indexing,
+                    element: Some(Box::new(InputSpec {
+                        kind: "integer".to_string(),
+                        min: Some(0.into()),
+                        max: Some(4.into()),
+                        ..InputSpec::default()
+                    })),
+                    ..InputSpec::default()
+                },
+            );
+            Problem {
+                inputs,
+                ..Problem::default()
+            }
+        };
+
+        for (indexing, base) in [(None, 0), (Some(0), 0), (Some(1), 1)] {
+            let specs = parse_problem_inputs(&problem("array", indexing)).expect("parse");
+            let mut seen = BTreeSet::new();
+            for case in generate_inputs(&specs, 60, 2, &Pbt::default()).expect("generate") {
+                let line = case.lines().nth(1).expect("array line");
+                seen.extend(line.split(' ').map(|x| x.parse::<i64>().unwrap()));
+            }
+            assert_eq!(seen, (base..=base + 4).collect(), "indexing {indexing:?}");
+        }
+
+        let error = |kind, indexing| {
+            format!(
+                "{:#}",
+                parse_problem_inputs(&problem(kind, Some(indexing))).unwrap_err()
+            )
+        };
+        assert!(error("array", 2).contains("indexing must be 0 or 1"));
+        assert!(error("grid", 1).contains("`indexing` applies only to array and graph inputs"));
+    }
+
+    #[test]
+    fn array_length_must_name_an_earlier_integer_input() {
+        let array = |len: &str| InputSpec {
+            kind: "array".to_string(),
+            len: Some(len.to_string()),
+            element: Some(Box::new(InputSpec {
+                kind: "integer".to_string(),
+                ..InputSpec::default()
+            })),
+            ..InputSpec::default()
+        };
+        let mut inputs = BTreeMap::new();
+        inputs.insert("a".to_string(), array("m"));
+        inputs.insert(
+            "b".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                min: Some(0.into()),
+                ..InputSpec::default()
+            },
+        );
+        let problem = |inputs| Problem {
+            inputs,
+            ..Problem::default()
+        };
+
+        let error = parse_problem_inputs(&problem(inputs.clone())).unwrap_err();
+        assert!(error.to_string().contains("not an input"), "{error:#}");
+
+        inputs.insert("a".to_string(), array("b"));
+        let error = parse_problem_inputs(&problem(inputs)).unwrap_err();
+        assert!(error.to_string().contains("name order"), "{error:#}");
+    }
+
+    #[test]
+    fn off_by_one_seeds_neighbours_of_the_midpoint() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "n".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                min: Some(1.into()),
+                max: Some(1000.into()),
+                ..InputSpec::default()
+            },
+        );
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+        let pbt = Pbt {
+            edge_case_ratio: 0.5,
+            partition_ratio: 0.0,
+            off_by_one: true,
+            ..Pbt::default()
+        };
+
+        let samples = generate_inputs(&specs, 40, 3, &pbt).expect("generate");
+        let seeded = &samples[..20];
+        for value in ["499", "500", "501", "1", "2", "999", "1000"] {
+            assert!(seeded.iter().any(|line| line.trim() == value), "{value}");
+        }
+    }
+
+    #[test]
+    fn adversarial_hash_biases_toward_bucket_multiples() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "key".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                min: Some(1.into()),
+                max: Some(1_000_000_000.into()),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+        let pbt = Pbt {
+            edge_case_ratio: 0.0,
+            partition_ratio: 0.0,
+            adversarial_hash: true,
+            ..Pbt::default()
+        };
+
+        let samples = generate_inputs(&specs, 200, 11, &pbt).expect("generate");
+        let colliding = samples
+            .iter()
+            .map(|line| line.trim().parse::<i64>().expect("integer"))
+            .filter(|value| HASH_MODULI.iter().any(|m| value % m == 0))
+            .count();
+        assert!(colliding > 50, "only {colliding}/200 colliding values");
+    }
+
+    #[test]
+    fn graph_cases_put_edge_list_on_own_lines() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert(
+            "g".to_string(),
+            InputSpec {
+                kind: "graph".to_string(),
+                nodes: Some(Count::Range(RangeSpec {
+                    min: Some(3),
+                    max: Some(3),
+                })),
+                connected: true,
+                acyclic: true,
+                ..InputSpec::default()
+            },
+        );
+        inputs.insert(
+            "q".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                min: Some(7.into()),
+                max: Some(7.into()),
+                ..InputSpec::default()
+            },
+        );
+
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        for case in generate_inputs(&specs, 10, 1, &Pbt::default()).expect("generate") {
+            let lines = case.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 4, "{case:?}");
+            assert_eq!(lines[0], "3 2");
+            assert_eq!(lines[3], "7");
+        }
+    }
+
+    #[test]
+    fn string_shapes_join_the_seeded_cases() {
+        let mut problem = Problem::default();
+        problem.inputs.insert(
+            "s".to_string(),
+            InputSpec {
+                kind: "string".to_string(),
+                max_len: Some(9),
+                shapes: Some(vec![
+                    StringShape::Palindrome,
+                    StringShape::Periodic,
+                    StringShape::AllSame,
+                ]),
+                ..InputSpec::default()
+            },
+        );
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let cases = generate_inputs(&specs, 40, 5, &Pbt::default()).expect("generate");
+        let longest = cases
+            .iter()
+            .map(|case| case.trim_end())
+            .filter(|text| text.len() == 9)
+            .collect::<Vec<_>>();
+        let all_same = |text: &str| text.bytes().all(|byte| byte == text.as_bytes()[0]);
+        let palindrome = |text: &str| text.bytes().eq(text.bytes().rev());
+        let periodic = |text: &str| {
+            (2..=4).any(|period| text.as_bytes()[period..] == text.as_bytes()[..9 - period])
+        };
+        assert!(longest.iter().any(|text| all_same(text)), "{longest:?}");
+        assert!(
+            longest
+                .iter()
+                .any(|text| palindrome(text) && !all_same(text)),
+            "{longest:?}"
+        );
+        assert!(
+            longest.iter().any(|text| periodic(text) && !all_same(text)),
+            "{longest:?}"
+        );
+
+        problem.inputs.insert(
+            "n".to_string(),
+            InputSpec {
+                kind: "integer".to_string(),
+                shapes: Some(vec![StringShape::Palindrome]),
+                ..InputSpec::default()
+            },
+        );
+        let error = parse_problem_inputs(&problem).unwrap_err().to_string();
+        assert!(
+            error.contains("`shapes` applies only to string inputs"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn graph_counts_follow_the_inputs_they_name() {
+        let integer = |min: Bound, max: Bound| InputSpec {
+            kind: "integer".to_string(),
+            min: Some(min),
+            max: Some(max),
+            ..InputSpec::default()
+        };
+        let graph = |nodes: Count, edges: Count| InputSpec {
+            kind: "graph".to_string(),
+            nodes: Some(nodes),
+            edges: Some(edges),
+            ..InputSpec::default()
+        };
+        let named = |name: &str| Count::Input(name.to_string());
+        let mut problem = Problem::default();
+        problem
+            .inputs
+            .insert("a".to_string(), graph(named("n"), named("m")));
+        problem.inputs.insert(
+            "b".to_string(),
+            graph(Count::Range(RangeSpec::default()), named("m")),
+        );
+        problem.inputs.insert(
+            "m".to_string(),
+            integer(0.into(), Bound::Expr("n * (n - 1) / 2".to_string())),
+        );
+        problem
+            .inputs
+            .insert("n".to_string(), integer(1.into(), 6.into()));
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        for case in generate_inputs(&specs, 60, 3, &Pbt::default()).expect("generate") {
+            let lines = case.lines().collect::<Vec<_>>();
+            let (m, n) = lines[lines.len() - 1].split_once(' ').expect("m n");
+            assert_eq!(lines[0], format!("{n} {m}"), "{case:?}");
+            let b_header = lines[1 + m.parse::<usize>().unwrap()];
+            let (b_nodes, b_edges) = b_header.split_once(' ').expect("header");
+            assert_eq!(b_edges, m, "{case:?}");
+            let b_nodes = b_nodes.parse::<u64>().unwrap();
+            assert!(
+                b_nodes * (b_nodes - 1) / 2 >= m.parse().unwrap(),
+                "{case:?}"
+            );
+        }
+
+        problem.inputs.remove("b");
+        problem.inputs.insert(
+            "a".to_string(),
+            graph(
+                named("n"),
+                Count::Range(RangeSpec {
+                    min: Some(3),
+                    max: Some(3),
+                }),
+            ),
+        );
+        let error = format!("{:#}", parse_problem_inputs(&problem).unwrap_err());
+        assert!(error.contains("`n` may be 1"), "{error}");
+        problem
+            .inputs
+            .insert("a".to_string(), graph(named("a"), named("m")));
+        let error = format!("{:#}", parse_problem_inputs(&problem).unwrap_err());
+        assert!(error.contains("the graph itself"), "{error}");
+    }
+
+    #[test]
+    fn graphs_can_allow_loops_and_repeated_edges() {
+        let graph = |self_loops, multi_edges, acyclic| InputSpec {
+            kind: "graph".to_string(),
+            nodes: Some(Count::Range(RangeSpec {
+                min: Some(1),
+                max: Some(3),
+            })),
+            edges: Some(Count::Range(RangeSpec {
+                min: Some(0),
+                max: Some(8),
+            })),
+            self_loops,
+            multi_edges,
+            acyclic,
+            ..InputSpec::default()
+        };
+        let mut problem = Problem::default();
+        problem
+            .inputs
+            .insert("g".to_string(), graph(true, true, false));
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let (mut loops, mut repeats) = (false, false);
+        for case in generate_inputs(&specs, 100, 4, &Pbt::default()).expect("generate") {
+            let mut lines = case.lines();
+            let header = lines.next().expect("header");
+            let n = header.split(' ').next().unwrap().parse::<u64>().unwrap();
+            let mut seen = BTreeSet::new();
+            for line in lines {
+                let (u, v) = line.split_once(' ').expect("edge");
+                let (u, v) = (u.parse::<u64>().unwrap(), v.parse::<u64>().unwrap());
+                assert!((1..=n).contains(&u) && (1..=n).contains(&v), "{case:?}");
+                loops |= u == v;
+                repeats |= !seen.insert((u.min(v), u.max(v)));
+            }
+        }
+        assert!(loops && repeats);
+
+        problem
+            .inputs
+            .insert("g".to_string(), graph(true, false, true));
+        let error = format!("{:#}", parse_problem_inputs(&problem).unwrap_err());
+        assert!(error.contains("self_loops"), "{error}");
+    }
+
     #[test]
     fn seeded_generation_includes_edges() {
         let mut inputs = BTreeMap::new();
@@ -404,8 +2673,9 @@ mod tests {
             InputSpec {
                 kind: "integer".to_string(),
                 range: None,
-                min: Some(1),
-                max: Some(9),
+                min: Some(1.into()),
+                max: Some(9.into()),
+                ..InputSpec::default()
             },
         );
         inputs.insert(
@@ -413,16 +2683,104 @@ mod tests {
             InputSpec {
                 kind: "integer".to_string(),
                 range: None,
-                min: Some(1),
-                max: Some(9),
+                min: Some(1.into()),
+                max: Some(9.into()),
+                ..InputSpec::default()
             },
         );
 
-        let problem = Problem { inputs };
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
         let specs = parse_problem_inputs(&problem).expect("parse");
 
         let samples = generate_inputs(&specs, 30, 42, &Pbt::default()).expect("generate");
         assert!(samples.iter().any(|line| line.trim() == "1 1"));
         assert!(samples.iter().any(|line| line.trim() == "9 9"));
     }
+
+    #[test]
+    fn bounds_can_refer_to_other_inputs() {
+        let integer = |min: Bound, max: Bound| InputSpec {
+            kind: "integer".to_string(),
+            min: Some(min),
+            max: Some(max),
+            ..InputSpec::default()
+        };
+        let mut inputs = BTreeMap::new();
+        inputs.insert("k".to_string(), integer(1.into(), Bound::Expr("n".into())));
+        inputs.insert("n".to_string(), integer(1.into(), 50.into()));
+        inputs.insert(
+            "x".to_string(),
+            integer(Bound::Expr("k - 1".into()), 100.into()),
+        );
+        let problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let cases = generate_values(&specs, 200, 7, &Pbt::default()).expect("generate");
+        for case in &cases {
+            let [Value::Int(k), Value::Int(n), Value::Int(x)] = case[..] else {
+                panic!("expected three integers");
+            };
+            assert!((1..=n).contains(&k), "k={k} n={n}");
+            assert!((k - 1..=100).contains(&x), "x={x} k={k}");
+        }
+        assert!(cases.iter().any(|case| case[0] == case[1]));
+
+        let mut problem = problem;
+        problem
+            .inputs
+            .insert("n".to_string(), integer(Bound::Expr("x".into()), 50.into()));
+        let error = parse_problem_inputs(&problem).unwrap_err().to_string();
+        assert!(error.contains("refer to each other"), "{error}");
+    }
+
+    #[test]
+    fn range_tokens_can_compare_with_other_inputs() {
+        let integer = |min: i64, max: i64, range: Option<&str>| InputSpec {
+            kind: "integer".to_string(),
+            min: Some(min.into()),
+            max: Some(max.into()),
+            range: range.map(str::to_string),
+            ..InputSpec::default()
+        };
+        let mut inputs = BTreeMap::new();
+        inputs.insert("a".to_string(), integer(0, 100, Some("< b, >= 3")));
+        inputs.insert("b".to_string(), integer(5, 20, None));
+        let mut problem = Problem {
+            inputs,
+            ..Problem::default()
+        };
+        let specs = parse_problem_inputs(&problem).expect("parse");
+
+        let cases = generate_values(&specs, 200, 11, &Pbt::default()).expect("generate");
+        for case in &cases {
+            let [Value::Int(a), Value::Int(b)] = case[..] else {
+                panic!("expected two integers");
+            };
+            assert!((3..b).contains(&a), "a={a} b={b}");
+        }
+        assert!(cases.iter().any(|case| case[0] == Value::Int(3)));
+        assert!(cases
+            .iter()
+            .any(|case| matches!(case[..], [Value::Int(a), Value::Int(b)] if a == b - 1)));
+
+        problem.inputs.insert("b".to_string(), integer(1, 20, None));
+        let error = format!("{:#}", parse_problem_inputs(&problem).unwrap_err());
+        assert!(error.contains("input a"), "{error}");
+    }
+
+    #[test]
+    fn batch_sizes_cover_both_extremes() {
+        let sizes = batch_sizes(50, 2, 6, 5).expect("sizes");
+        assert_eq!(sizes.len(), 50);
+        assert_eq!(sizes[..2], [2, 6]);
+        assert!(sizes.iter().all(|size| (2..=6).contains(size)));
+        assert_eq!(sizes, batch_sizes(50, 2, 6, 5).expect("sizes"));
+        assert_eq!(batch_sizes(4, 1, 1, 5).expect("sizes"), [1; 4]);
+    }
 }
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use tempfile::TempDir;
+
+use crate::config::{Config, Program};
+use crate::runner::program_dir;
+
+/// Rewires `config` into a regression check for `file`: the program that
+/// references it becomes the only candidate, and a copy of it running the
+/// `HEAD` version of the file becomes the origin. The returned dir holds the
+/// checked-out file and must outlive the run.
+pub fn compare_with_head(config: &mut Config, config_dir: &Path, file: &Path) -> Result<TempDir> {
+    let file = std::fs::canonicalize(file)
+        .with_context(|| format!("file not found: {}", file.display()))?;
+
+    let program = config
+        .candidate
+        .iter()
+        .chain(&config.origin)
+        .find(|program| references(program, config_dir, &file))
+        .cloned()
+        .with_context(|| {
+            format!(
+                "no origin or candidate references {} via cmd or mounts",
+                file.display()
+            )
+        })?;
+
+    let (head_dir, head_file) = checkout_head(&file)?;
+    let name = program
+        .name
+        .clone()
+        .unwrap_or_else(|| "working-tree".to_string());
+    let mut head_program = program.clone();
+    retarget(&mut head_program, config_dir, &file, &head_file);
+    head_program.name = Some(format!("{name}@HEAD"));
+
+    config.origin = vec![head_program];
+    config.candidate = vec![Program {
+        name: Some(name),
+        ..program
+    }];
+
+    Ok(head_dir)
+}
+
+fn checkout_head(file: &Path) -> Result<(TempDir, PathBuf)> {
+    let dir = file.parent().context("file has no parent directory")?;
+    let file_name = file.file_name().context("file has no name")?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("HEAD:./{}", file_name.to_string_lossy()))
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git show HEAD:{} failed: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let head_dir = tempfile::Builder::new()
+        .prefix("nado-head-")
+        .tempdir()
+        .context("failed to create temp dir")?;
+    let head_file = head_dir.path().join(file_name);
+    std::fs::write(&head_file, &output.stdout)
+        .with_context(|| format!("failed to write {}", head_file.display()))?;
+
+    Ok((head_dir, head_file))
+}
+
+fn references(program: &Program, config_dir: &Path, file: &Path) -> bool {
+    let dir = program_dir(program, config_dir);
+    program
+        .mounts
+        .iter()
+        .filter_map(|mount| mount.split(':').next())
+        .chain(program.cmd.iter().map(String::as_str))
+        .any(|candidate| same_file(&dir, candidate, file))
+}
+
+/// Points every mount host path and cmd argument that resolves to `from` at `to`.
+fn retarget(program: &mut Program, config_dir: &Path, from: &Path, to: &Path) {
+    let dir = program_dir(program, config_dir);
+    let to = to.to_string_lossy().to_string();
+
+    for mount in &mut program.mounts {
+        if let Some((host, rest)) = mount.split_once(':') {
+            if same_file(&dir, host, from) {
+                *mount = format!("{to}:{rest}");
+            }
+        }
+    }
+    for arg in &mut program.cmd {
+        if same_file(&dir, arg, from) {
+            arg.clone_from(&to);
+        }
+    }
+}
+
+fn same_file(dir: &Path, raw: &str, file: &Path) -> bool {
+    std::fs::canonicalize(dir.join(raw.trim())).is_ok_and(|path| path == file)
+}
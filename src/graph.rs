@@ -0,0 +1,759 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{bail, Result};
+use proptest::test_runner::TestRunner;
+
+use crate::config::{Count, InputSpec, RangeSpec};
+use crate::generator::sample;
+
+/// Shape constraints of a `type = "graph"` input.
+#[derive(Debug, Clone)]
+pub struct GraphSpec {
+    /// Smallest and largest node counts that admit a valid edge count.
+    nodes: (u64, u64),
+    edges: (u64, u64),
+    /// Integer inputs holding the node or edge count of each case, when
+    /// `nodes` or `edges` names one.
+    nodes_input: Option<usize>,
+    edges_input: Option<usize>,
+    directed: bool,
+    acyclic: bool,
+    connected: bool,
+    self_loops: bool,
+    multi_edges: bool,
+    weights: Option<(i64, i64)>,
+    base: u64,
+}
+
+/// A generated graph, rendered as `n m` followed by one `u v [w]` line per edge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    nodes: u64,
+    edges: Vec<(u64, u64, Option<i64>)>,
+    base: u64,
+}
+
+impl Graph {
+    /// One-line description for reports, e.g. `graph(n=4, m=6)`.
+    pub fn summary(&self) -> String {
+        format!("graph(n={}, m={})", self.nodes, self.edges.len())
+    }
+}
+
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.nodes, self.edges.len())?;
+        for (u, v, weight) in &self.edges {
+            write!(f, "\n{} {}", u + self.base, v + self.base)?;
+            if let Some(weight) = weight {
+                write!(f, " {weight}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Most nodes a graph may have. Generation keeps a couple of words per node
+/// even when few edges are rendered, which `engine.max_input_bytes` does not
+/// account for.
+const MAX_NODES: u64 = 10_000_000;
+
+pub fn parse_graph(spec: &InputSpec) -> Result<GraphSpec> {
+    let nodes = count_bounds(spec.nodes.as_ref(), (1, 10), "nodes")?;
+    let edges = count_bounds(spec.edges.as_ref(), (0, u64::MAX), "edges")?;
+    let weights = match spec.edge_weights {
+        None => None,
+        Some(range) => {
+            let min = range.min.unwrap_or(1);
+            let max = range.max.unwrap_or(100);
+            if min > max {
+                bail!("invalid edge_weights: min({min}) > max({max})");
+            }
+            Some((min, max))
+        }
+    };
+    if spec.acyclic && spec.self_loops {
+        bail!("self_loops cannot be combined with acyclic: a loop is a cycle");
+    }
+    if spec.acyclic && spec.multi_edges && !spec.directed {
+        bail!("multi_edges cannot be combined with an acyclic undirected graph: a repeated edge is a cycle");
+    }
+    let edges_max = match &spec.edges {
+        Some(Count::Range(range)) => range.max.is_some(),
+        Some(Count::Input(_)) => true,
+        None => false,
+    };
+    if spec.multi_edges && !edges_max {
+        bail!("multi_edges needs edges.max, as repeated edges have no natural limit");
+    }
+    let base = match spec.indexing.unwrap_or(1) {
+        base @ (0 | 1) => u64::from(base),
+        other => bail!("indexing must be 0 or 1, got {other}"),
+    };
+
+    let mut graph = GraphSpec {
+        nodes,
+        edges,
+        nodes_input: None,
+        edges_input: None,
+        directed: spec.directed,
+        acyclic: spec.acyclic,
+        connected: spec.connected,
+        self_loops: spec.self_loops,
+        multi_edges: spec.multi_edges,
+        weights,
+        base,
+    };
+    // Counts taken from inputs are fitted once those are linked.
+    let from_input = |count: &Option<Count>| matches!(count, Some(Count::Input(_)));
+    if !from_input(&spec.nodes) && !from_input(&spec.edges) {
+        graph.fit_nodes()?;
+    }
+    Ok(graph)
+}
+
+/// Bounds of a `nodes` or `edges` range. A count named after an input
+/// takes the default until [`GraphSpec::link_counts`] replaces it.
+fn count_bounds(count: Option<&Count>, default: (u64, u64), name: &str) -> Result<(u64, u64)> {
+    let range = match count {
+        None => RangeSpec::default(),
+        Some(Count::Range(range)) => *range,
+        Some(Count::Input(_)) => return Ok(default),
+    };
+    let to_count = |value: Option<i64>, default: u64| match value {
+        None => Ok(default),
+        Some(value) => u64::try_from(value)
+            .map_err(|_| anyhow::anyhow!("{name} must not be negative, got {value}")),
+    };
+    let min = to_count(range.min, default.0)?;
+    let max = to_count(range.max, default.1)?;
+    if min > max {
+        bail!("invalid {name}: min({min}) > max({max})");
+    }
+    Ok((min, max))
+}
+
+impl GraphSpec {
+    /// Takes the node and edge counts from integer inputs, given as their
+    /// index, name and `[min, max]`, and fits the node range to them.
+    pub fn link_counts(
+        &mut self,
+        nodes: Option<(usize, &str, (u64, u64))>,
+        edges: Option<(usize, &str, (u64, u64))>,
+    ) -> Result<()> {
+        if let Some((input, _, bounds)) = nodes {
+            (self.nodes_input, self.nodes) = (Some(input), bounds);
+        }
+        if let Some((input, _, bounds)) = edges {
+            (self.edges_input, self.edges) = (Some(input), bounds);
+        }
+        let wanted = self.nodes;
+        self.fit_nodes()?;
+        // With a fixed edge range every node count the input may hold must
+        // work; with the edges from an input too, each case is checked.
+        if let (Some((_, name, _)), None) = (nodes, edges) {
+            if self.nodes != wanted {
+                let n = if self.nodes.0 != wanted.0 {
+                    wanted.0
+                } else {
+                    wanted.1
+                };
+                bail!(
+                    "`{name}` may be {n}, but no {}graph with {n} nodes can have {}..={} edges",
+                    self.describe(),
+                    self.edges.0,
+                    self.edges.1
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Narrows the node range to the counts that admit a valid edge count,
+    /// failing when none does.
+    fn fit_nodes(&mut self) -> Result<()> {
+        let (nodes, edges) = (self.nodes, self.edges);
+        if nodes.1 > MAX_NODES {
+            bail!(
+                "graph nodes.max must be at most {MAX_NODES}, got {}",
+                nodes.1
+            );
+        }
+
+        // Node counts outside [lo, hi] cannot satisfy the edge bounds: too few
+        // nodes to hold `edges.min` edges, or (when connected) so many
+        // that a spanning tree alone exceeds `edges.max`. Every count in between
+        // admits at least one edge count.
+        let lo = self.fewest_nodes(edges.0);
+        let hi = if self.connected {
+            nodes.1.min(edges.1.saturating_add(1))
+        } else {
+            nodes.1
+        };
+        if lo > hi || self.edge_range(lo).is_none() {
+            bail!(
+                "no {}graph with {}..={} nodes can have {}..={} edges",
+                self.describe(),
+                nodes.0,
+                nodes.1,
+                edges.0,
+                edges.1
+            );
+        }
+        self.nodes = (lo, hi);
+        Ok(())
+    }
+
+    /// Smallest node count in range that can carry `m` edges, or the largest
+    /// one when none can.
+    fn fewest_nodes(&self, m: u64) -> u64 {
+        let (mut lo, mut hi) = self.nodes;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.max_edges(mid) >= m {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        lo
+    }
+
+    /// Integer inputs the node and edge counts come from.
+    pub fn count_inputs(&self) -> impl Iterator<Item = usize> {
+        self.nodes_input.into_iter().chain(self.edges_input)
+    }
+
+    /// Most edges `n` nodes can carry under the spec's flags.
+    fn max_edges(&self, n: u64) -> u64 {
+        let loops = if self.self_loops { n } else { 0 };
+        let pairs = n.saturating_mul(n.saturating_sub(1));
+        let distinct = match (self.directed, self.acyclic) {
+            (false, true) => n.saturating_sub(1),
+            (true, false) => pairs + loops,
+            _ => pairs / 2 + loops,
+        };
+        if self.multi_edges && distinct > 0 {
+            u64::MAX
+        } else {
+            distinct
+        }
+    }
+
+    /// Valid edge counts for `n` nodes, if any.
+    fn edge_range(&self, n: u64) -> Option<(u64, u64)> {
+        let mut lo = self.edges.0;
+        if self.connected {
+            lo = lo.max(n.saturating_sub(1));
+        }
+        let hi = self.edges.1.min(self.max_edges(n));
+        (lo <= hi).then_some((lo, hi))
+    }
+
+    fn describe(&self) -> String {
+        let mut flags = Vec::new();
+        if self.connected {
+            flags.push("connected");
+        }
+        if self.acyclic {
+            flags.push("acyclic");
+        }
+        if self.self_loops {
+            flags.push("self-looped");
+        }
+        if self.multi_edges {
+            flags.push("multi-edged");
+        }
+        flags.push(if self.directed {
+            "directed"
+        } else {
+            "undirected"
+        });
+        format!("{} ", flags.join(" "))
+    }
+
+    /// Upper bound of the rendered size in bytes, trailing newline included.
+    pub fn worst_case_bytes(&self) -> u128 {
+        let n = self.nodes.1;
+        let m = self.edge_range(n).map_or(0, |(_, hi)| hi);
+        let node_digits = (n + self.base).to_string().len() as u128;
+        let weight_digits = self.weights.map_or(0, |(min, max)| {
+            min.to_string().len().max(max.to_string().len()) as u128 + 1
+        });
+        let header = node_digits + m.to_string().len() as u128 + 2;
+        header + u128::from(m) * (2 * node_digits + 2 + weight_digits)
+    }
+
+    /// Boundary graphs: empty, single edge, a complete small graph, and the
+    /// largest allowed. Built from a fixed seed so they are the same every run.
+    pub fn edge_graphs(&self, runner: &mut TestRunner) -> Result<Vec<Graph>> {
+        let (lo, hi) = self.nodes;
+        let mut shapes = vec![(lo, self.edge_range(lo).map(|(m, _)| m))];
+
+        let single = lo.max(2).min(hi);
+        shapes.push((
+            single,
+            self.edge_range(single)
+                .filter(|r| r.0 <= 1 && 1 <= r.1)
+                .map(|_| 1),
+        ));
+
+        let small = lo.max(4.min(hi));
+        shapes.push((small, self.edge_range(small).map(|(_, m)| m)));
+        shapes.push((hi, self.edge_range(hi).map(|(_, m)| m)));
+
+        let mut graphs = Vec::new();
+        for (n, m) in shapes {
+            let Some(m) = m else {
+                continue;
+            };
+            let graph = self.build(runner, n, m)?;
+            if !graphs.contains(&graph) {
+                graphs.push(graph);
+            }
+        }
+        Ok(graphs)
+    }
+
+    /// Node counts at the quartiles of the valid range, each with a mid-range
+    /// edge count.
+    pub fn partition_graphs(&self, runner: &mut TestRunner) -> Result<Vec<Graph>> {
+        let (lo, hi) = self.nodes;
+        let mut graphs = Vec::new();
+        for quarter in 0..=4 {
+            let n = lo + (hi - lo) * quarter / 4;
+            let Some((m_lo, m_hi)) = self.edge_range(n) else {
+                continue;
+            };
+            let graph = self.build(runner, n, m_lo + (m_hi - m_lo) / 2)?;
+            if !graphs.contains(&graph) {
+                graphs.push(graph);
+            }
+        }
+        Ok(graphs)
+    }
+
+    pub fn random_graph(&self, runner: &mut TestRunner) -> Result<Graph> {
+        if self.count_inputs().next().is_some() {
+            // Drawn in `case_graph`, once the counts are known.
+            return Ok(Graph {
+                nodes: 0,
+                edges: Vec::new(),
+                base: self.base,
+            });
+        }
+        let n = sample(runner, self.nodes.0..=self.nodes.1)?;
+        let (m_lo, m_hi) = self.edge_range(n).expect("validated in parse_graph");
+        let m = sample(runner, m_lo..=m_hi)?;
+        self.build(runner, n, m)
+    }
+
+    /// The graph of one case whose counts come from inputs, read through
+    /// `count_of`. Counts the inputs leave open are drawn at random, or kept
+    /// as close to `pattern`'s as the case allows for seeded cases.
+    pub fn case_graph(
+        &self,
+        runner: &mut TestRunner,
+        count_of: impl Fn(usize) -> u64,
+        pattern: Option<&Graph>,
+    ) -> Result<Graph> {
+        let edges = self.edges_input.map(&count_of);
+        let n = match (self.nodes_input, edges) {
+            (Some(input), _) => count_of(input),
+            (None, Some(m)) => {
+                let lo = self.fewest_nodes(m);
+                let hi = if self.connected {
+                    self.nodes.1.min(m.saturating_add(1))
+                } else {
+                    self.nodes.1
+                };
+                if lo > hi || self.max_edges(lo) < m {
+                    bail!(
+                        "no {}graph with {}..={} nodes can have {m} edges",
+                        self.describe(),
+                        self.nodes.0,
+                        self.nodes.1
+                    );
+                }
+                match pattern {
+                    Some(pattern) => pattern.nodes.clamp(lo, hi),
+                    None => sample(runner, lo..=hi)?,
+                }
+            }
+            (None, None) => unreachable!("only graphs with counts from inputs are drawn per case"),
+        };
+
+        let range = self
+            .edge_range(n)
+            .filter(|&(lo, hi)| edges.is_none_or(|m| (lo..=hi).contains(&m)));
+        let Some((lo, hi)) = range else {
+            let wanted = match edges {
+                Some(m) => m.to_string(),
+                None => format!("{}..={}", self.edges.0, self.edges.1),
+            };
+            bail!(
+                "no {}graph with {n} nodes can have {wanted} edges",
+                self.describe()
+            );
+        };
+        let m = match (edges, pattern) {
+            (Some(m), _) => m,
+            (None, Some(pattern)) => (pattern.edges.len() as u64).clamp(lo, hi),
+            (None, None) => sample(runner, lo..=hi)?,
+        };
+        self.build(runner, n, m)
+    }
+
+    /// Whether `graph`, drawn for a case, follows the seeded `pattern`: the
+    /// pattern itself, or for counts from inputs its edge count as far as the
+    /// case allows.
+    pub fn follows(&self, graph: &Graph, pattern: &Graph) -> bool {
+        if self.count_inputs().next().is_none() {
+            return graph == pattern;
+        }
+        self.edge_range(graph.nodes).is_some_and(|(lo, hi)| {
+            graph.edges.len() as u64 == (pattern.edges.len() as u64).clamp(lo, hi)
+        })
+    }
+
+    /// Builds a random graph with exactly `n` nodes and `m` edges, which the
+    /// caller has checked against [`GraphSpec::edge_range`].
+    fn build(&self, runner: &mut TestRunner, n: u64, m: u64) -> Result<Graph> {
+        // A random relabeling doubles as the topological order of a DAG:
+        // every directed edge points from lower to higher rank.
+        let mut order = (0..n).collect::<Vec<_>>();
+        shuffle(runner, &mut order)?;
+        let mut rank = vec![0; n as usize];
+        for (position, &node) in order.iter().enumerate() {
+            rank[node as usize] = position;
+        }
+
+        let mut chosen = HashSet::new();
+        let mut edges = Vec::with_capacity(m as usize);
+
+        if self.connected || (!self.directed && self.acyclic) {
+            // A random spanning tree; forests keep a prefix of its edges.
+            let mut tree = Vec::with_capacity(n.saturating_sub(1) as usize);
+            for i in 1..n {
+                let parent = sample(runner, 0..i)?;
+                tree.push((order[parent as usize], order[i as usize]));
+            }
+            shuffle(runner, &mut tree)?;
+            let keep = if self.connected {
+                tree.len()
+            } else {
+                m as usize
+            };
+            for (u, v) in tree.into_iter().take(keep) {
+                self.add_edge(runner, &rank, &mut chosen, &mut edges, u, v)?;
+            }
+        }
+
+        let capacity = self.max_edges(n);
+        if !self.directed && self.acyclic {
+            // The forest above already has exactly `m` edges.
+        } else if m.saturating_mul(2) > capacity {
+            // Dense: walk every allowed pair in random order.
+            let mut pairs = Vec::new();
+            for u in 0..n {
+                for v in 0..n {
+                    let allowed = if u == v {
+                        self.self_loops
+                    } else {
+                        self.directed && !self.acyclic || u < v
+                    };
+                    if allowed {
+                        pairs.push((u, v));
+                    }
+                }
+            }
+            shuffle(runner, &mut pairs)?;
+            for (u, v) in pairs {
+                if edges.len() as u64 >= m {
+                    break;
+                }
+                self.add_edge(runner, &rank, &mut chosen, &mut edges, u, v)?;
+            }
+        } else {
+            while (edges.len() as u64) < m {
+                let u = sample(runner, 0..n)?;
+                let v = sample(runner, 0..n)?;
+                if u != v || self.self_loops {
+                    self.add_edge(runner, &rank, &mut chosen, &mut edges, u, v)?;
+                }
+            }
+        }
+
+        shuffle(runner, &mut edges)?;
+        Ok(Graph {
+            nodes: n,
+            edges,
+            base: self.base,
+        })
+    }
+
+    /// Orients and records `u`-`v` unless it duplicates an existing edge
+    /// and `multi_edges` is off.
+    fn add_edge(
+        &self,
+        runner: &mut TestRunner,
+        rank: &[usize],
+        chosen: &mut HashSet<(u64, u64)>,
+        edges: &mut Vec<(u64, u64, Option<i64>)>,
+        u: u64,
+        v: u64,
+    ) -> Result<()> {
+        let (u, v) = if !self.directed {
+            (u.min(v), u.max(v))
+        } else if self.acyclic && rank[u as usize] > rank[v as usize] {
+            (v, u)
+        } else {
+            (u, v)
+        };
+        if !chosen.insert((u, v)) && !self.multi_edges {
+            return Ok(());
+        }
+
+        let weight = match self.weights {
+            Some((min, max)) => Some(sample(runner, min..=max)?),
+            None => None,
+        };
+        edges.push((u, v, weight));
+        Ok(())
+    }
+}
+
+fn shuffle<T>(runner: &mut TestRunner, items: &mut [T]) -> Result<()> {
+    for i in (1..items.len()).rev() {
+        let j = sample(runner, 0..=i)?;
+        items.swap(i, j);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use proptest::test_runner::TestRunner;
+
+    use crate::config::{Count, InputSpec, RangeSpec};
+
+    use super::{parse_graph, Graph, GraphSpec};
+
+    fn range(min: i64, max: i64) -> Option<Count> {
+        Some(Count::Range(RangeSpec {
+            min: Some(min),
+            max: Some(max),
+        }))
+    }
+
+    fn graph_spec(nodes: (i64, i64), edges: (i64, i64), flags: &[&str]) -> InputSpec {
+        InputSpec {
+            kind: "graph".to_string(),
+            nodes: range(nodes.0, nodes.1),
+            edges: range(edges.0, edges.1),
+            directed: flags.contains(&"directed"),
+            acyclic: flags.contains(&"acyclic"),
+            connected: flags.contains(&"connected"),
+            self_loops: flags.contains(&"self_loops"),
+            multi_edges: flags.contains(&"multi_edges"),
+            ..InputSpec::default()
+        }
+    }
+
+    /// Seeded and random graphs of `spec`, checked for valid endpoints.
+    fn sample_graphs(spec: &GraphSpec) -> Vec<Graph> {
+        let mut runner = TestRunner::deterministic();
+        let mut graphs = spec.edge_graphs(&mut runner).expect("edge graphs");
+        graphs.extend(
+            spec.partition_graphs(&mut runner)
+                .expect("partition graphs"),
+        );
+        for _ in 0..50 {
+            graphs.push(spec.random_graph(&mut runner).expect("random graph"));
+        }
+        for graph in &graphs {
+            assert!((spec.nodes.0..=spec.nodes.1).contains(&graph.nodes));
+            let m = graph.edges.len() as u64;
+            assert!((spec.edges.0..=spec.edges.1).contains(&m), "{graph}");
+            for &(u, v, _) in &graph.edges {
+                assert!(u < graph.nodes && v < graph.nodes, "{graph}");
+            }
+        }
+        graphs
+    }
+
+    fn is_simple(graph: &Graph, directed: bool) -> bool {
+        let mut seen = HashSet::new();
+        graph.edges.iter().all(|&(u, v, _)| {
+            let key = if directed {
+                (u, v)
+            } else {
+                (u.min(v), u.max(v))
+            };
+            u != v && seen.insert(key)
+        })
+    }
+
+    /// Union-find over the edges, ignoring direction: whether they form a
+    /// forest, and how many components the nodes fall into.
+    fn components(graph: &Graph) -> (bool, u64) {
+        let mut parent = (0..graph.nodes).collect::<Vec<_>>();
+        fn root(parent: &mut [u64], mut node: u64) -> u64 {
+            while parent[node as usize] != node {
+                node = parent[node as usize];
+            }
+            node
+        }
+        let mut forest = true;
+        let mut count = graph.nodes;
+        for &(u, v, _) in &graph.edges {
+            let (a, b) = (root(&mut parent, u), root(&mut parent, v));
+            if a == b {
+                forest = false;
+            } else {
+                parent[a as usize] = b;
+                count -= 1;
+            }
+        }
+        (forest, count)
+    }
+
+    fn has_directed_cycle(graph: &Graph) -> bool {
+        let n = graph.nodes as usize;
+        let mut indegree = vec![0; n];
+        let mut out = vec![Vec::new(); n];
+        for &(u, v, _) in &graph.edges {
+            out[u as usize].push(v as usize);
+            indegree[v as usize] += 1;
+        }
+        let mut ready = (0..n)
+            .filter(|&node| indegree[node] == 0)
+            .collect::<Vec<_>>();
+        let mut visited = 0;
+        while let Some(node) = ready.pop() {
+            visited += 1;
+            for &next in &out[node] {
+                indegree[next] -= 1;
+                if indegree[next] == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+        visited < n
+    }
+
+    #[test]
+    fn simple_graphs_have_no_loops_or_repeated_edges() {
+        for flags in [&[][..], &["directed"]] {
+            let spec = parse_graph(&graph_spec((1, 8), (0, 30), flags)).expect("parse");
+            for graph in sample_graphs(&spec) {
+                assert!(is_simple(&graph, spec.directed), "{graph}");
+            }
+        }
+    }
+
+    #[test]
+    fn acyclic_graphs_are_dags_and_forests() {
+        let dag = parse_graph(&graph_spec((1, 8), (0, 30), &["directed", "acyclic"])).unwrap();
+        for graph in sample_graphs(&dag) {
+            assert!(
+                is_simple(&graph, true) && !has_directed_cycle(&graph),
+                "{graph}"
+            );
+        }
+
+        let forest = parse_graph(&graph_spec((1, 8), (0, 30), &["acyclic"])).unwrap();
+        for graph in sample_graphs(&forest) {
+            assert!(components(&graph).0, "{graph}");
+        }
+    }
+
+    #[test]
+    fn connected_graphs_have_one_component() {
+        for flags in [
+            &["connected"][..],
+            &["connected", "directed"],
+            &["connected", "acyclic"],
+        ] {
+            let spec = parse_graph(&graph_spec((1, 8), (0, 30), flags)).expect("parse");
+            for graph in sample_graphs(&spec) {
+                assert_eq!(components(&graph).1, 1, "{graph}");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_bounds_no_graph_can_meet() {
+        let error = |nodes, edges, flags: &[&str]| {
+            parse_graph(&graph_spec(nodes, edges, flags))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error((1, 3), (4, 10), &[]).contains("no undirected graph"));
+        assert!(error((2, 4), (7, 10), &["directed", "acyclic"]).contains("acyclic directed"));
+        assert!(error((5, 5), (0, 3), &["connected"]).contains("connected"));
+        assert!(error((1, 1_000_000_000_000), (0, 0), &[]).contains("at most"));
+        assert!(parse_graph(&graph_spec((3, 3), (0, 3), &["acyclic", "self_loops"])).is_err());
+    }
+
+    #[test]
+    fn loops_and_repeated_edges_appear_only_when_allowed() {
+        for flags in [
+            &["self_loops", "multi_edges"][..],
+            &["self_loops", "multi_edges", "directed"],
+            &["multi_edges", "directed", "acyclic"],
+        ] {
+            let spec = parse_graph(&graph_spec((1, 4), (0, 12), flags)).expect("parse");
+            let graphs = sample_graphs(&spec);
+            let looped = graphs
+                .iter()
+                .any(|graph| graph.edges.iter().any(|&(u, v, _)| u == v));
+            let repeated = graphs.iter().any(|graph| !is_simple(graph, spec.directed));
+            assert_eq!(looped, spec.self_loops, "{flags:?}");
+            assert!(repeated, "{flags:?}");
+            if spec.acyclic {
+                assert!(graphs.iter().all(|graph| !has_directed_cycle(graph)));
+            }
+        }
+
+        // Loops alone fill a small graph past its simple capacity.
+        let spec = parse_graph(&graph_spec((3, 3), (6, 6), &["self_loops"])).expect("parse");
+        for graph in sample_graphs(&spec) {
+            let distinct = graph
+                .edges
+                .iter()
+                .map(|&(u, v, _)| (u, v))
+                .collect::<HashSet<_>>();
+            assert_eq!(distinct.len(), 6, "{graph}");
+        }
+    }
+
+    #[test]
+    fn rejects_loops_and_repeated_edges_that_break_the_shape() {
+        let error = |nodes, edges, flags: &[&str]| {
+            parse_graph(&graph_spec(nodes, edges, flags))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error((3, 3), (0, 3), &["acyclic", "self_loops"]).contains("self_loops"));
+        assert!(error((3, 3), (0, 3), &["acyclic", "multi_edges"]).contains("multi_edges"));
+        let unbounded = InputSpec {
+            edges: Some(Count::Range(RangeSpec {
+                min: Some(0),
+                max: None,
+            })),
+            ..graph_spec((1, 3), (0, 0), &["multi_edges"])
+        };
+        let error = parse_graph(&unbounded).unwrap_err().to_string();
+        assert!(error.contains("edges.max"), "{error}");
+        assert!(parse_graph(&graph_spec(
+            (3, 3),
+            (0, 3),
+            &["directed", "acyclic", "multi_edges"]
+        ))
+        .is_ok());
+    }
+}
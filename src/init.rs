@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// Starting point for a new problem: one input per common kind, an origin,
+/// a candidate, and the engine and limit settings most runs adjust.
+const TEMPLATE: &str = r#"# nado config. Generated inputs are fed to every program on stdin; each
+# candidate's stdout is compared with the origin's.
+version = 1
+
+# Inputs render in name order, each on its own line.
+[problem.inputs.n]
+type = "integer"
+min = 1
+max = 100
+
+[problem.inputs.values]
+type = "array"
+len = "n" # as many values as `n` holds
+element = { type = "integer", min = -1000, max = 1000 }
+
+# Trusted reference solution.
+[[origin]]
+cmd = ["python3", "origin.py"]
+
+# Solution under test; add one [[candidate]] block per solution.
+[[candidate]]
+name = "solution"
+cmd = ["./solution"]
+
+[engine]
+cases = 1000
+seed = 42
+timeout_ms = 1000 # wall-clock limit per run
+stop_on_first_fail = true
+
+[limits]
+cpu_seconds = 2
+memory_mb = 256
+
+[normalize]
+trim_trailing_ws = true
+ignore_final_newline = true
+"#;
+
+/// Writes [`TEMPLATE`] to `nado.toml` in `dir`, keeping an existing file
+/// unless `force` is set.
+pub fn run(dir: &Path, force: bool) -> Result<i32> {
+    let path = dir.join("nado.toml");
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+
+    std::fs::write(&path, TEMPLATE)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!("wrote {}", path.display());
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, TEMPLATE};
+    use crate::config::{self, Config};
+    use crate::engine::generate_cases;
+
+    #[test]
+    fn template_parses_and_generates() {
+        let config: Config = toml::from_str(TEMPLATE).expect("template parses");
+        assert_eq!(config.origin.len(), 1);
+        assert_eq!(config.candidate.len(), 1);
+
+        let dir = tempfile::tempdir().expect("temp dir");
+        run(dir.path(), false).expect("writes the template");
+        assert!(run(dir.path(), false).is_err());
+        run(dir.path(), true).expect("--force overwrites");
+
+        let (config, _) = config::load(&dir.path().join("nado.toml")).expect("loads");
+        let (inputs, _) = generate_cases(&config).expect("generates");
+        assert_eq!(inputs.len(), 1000);
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+/// Longest excerpt of an input or output kept in a `<failure>` body.
+const EXCERPT_BYTES: usize = 2000;
+
+/// One candidate as a JUnit `<testcase>`.
+pub struct TestCase {
+    pub name: String,
+    pub time: Duration,
+    pub outcome: Outcome,
+}
+
+pub enum Outcome {
+    Passed,
+    /// Skipped by `--since-last`.
+    Cached,
+    /// An origin or engine failure left the verdict open.
+    Unknown,
+    Failed {
+        /// Every failed case, so the message can count them.
+        failures: usize,
+        case: usize,
+        reason: String,
+        input: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Writes a JUnit XML report with one `<testsuite>` named `suite` and one
+/// `<testcase>` per candidate.
+pub fn write(path: &Path, suite: &str, cases: usize, testcases: &[TestCase]) -> Result<()> {
+    let failed = testcases
+        .iter()
+        .filter(|case| matches!(case.outcome, Outcome::Failed { .. }))
+        .count();
+    let skipped = testcases
+        .iter()
+        .filter(|case| matches!(case.outcome, Outcome::Cached))
+        .count();
+    let errors = testcases
+        .iter()
+        .filter(|case| matches!(case.outcome, Outcome::Unknown))
+        .count();
+    let total = testcases.iter().map(|case| case.time).sum::<Duration>();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{failed}\" errors=\"{errors}\" skipped=\"{skipped}\" time=\"{:.3}\">",
+        escape(suite),
+        testcases.len(),
+        total.as_secs_f64()
+    );
+    let _ = writeln!(
+        xml,
+        "  <properties><property name=\"cases\" value=\"{cases}\"/></properties>"
+    );
+    for case in testcases {
+        let _ = write!(
+            xml,
+            "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            escape(suite),
+            escape(&case.name),
+            case.time.as_secs_f64()
+        );
+        match &case.outcome {
+            Outcome::Passed => xml.push_str("/>\n"),
+            Outcome::Cached => {
+                xml.push_str(">\n    <skipped message=\"unchanged since it last passed\"/>\n");
+                xml.push_str("  </testcase>\n");
+            }
+            Outcome::Unknown => {
+                xml.push_str(
+                    ">\n    <error message=\"origin or engine failure; verdict unknown\"/>\n",
+                );
+                xml.push_str("  </testcase>\n");
+            }
+            Outcome::Failed {
+                failures,
+                case,
+                reason,
+                input,
+                expected,
+                actual,
+            } => {
+                let body = format!(
+                    "case #{case}: {reason}\n\ninput:\n{}\n\nexpected:\n{}\n\nactual:\n{}\n",
+                    excerpt(input),
+                    excerpt(expected),
+                    excerpt(actual)
+                );
+                let _ = writeln!(
+                    xml,
+                    ">\n    <failure message=\"{}\" type=\"{failures} failing case(s)\">{}</failure>",
+                    escape(reason),
+                    escape(&body)
+                );
+                xml.push_str("  </testcase>\n");
+            }
+        }
+    }
+    xml.push_str("</testsuite>\n");
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    std::fs::write(path, xml)
+        .with_context(|| format!("failed to write JUnit report: {}", path.display()))
+}
+
+/// The start of `text`, cut on a character boundary and marked when cut.
+fn excerpt(text: &str) -> String {
+    let text = text.trim_end();
+    if text.len() <= EXCERPT_BYTES {
+        return text.to_string();
+    }
+    let mut end = EXCERPT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n... ({} more bytes)", &text[..end], text.len() - end)
+}
+
+/// Escapes text for an XML attribute or element, dropping the control
+/// characters XML 1.0 cannot hold at all.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' | '\t' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{write, Outcome, TestCase};
+
+    #[test]
+    fn writes_one_testcase_per_candidate_with_escaped_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reports/junit.xml");
+        let testcases = [
+            TestCase {
+                name: "fast".to_string(),
+                time: Duration::from_millis(1500),
+                outcome: Outcome::Passed,
+            },
+            TestCase {
+                name: "greedy".to_string(),
+                time: Duration::from_millis(250),
+                outcome: Outcome::Failed {
+                    failures: 2,
+                    case: 3,
+                    reason: "output mismatch".to_string(),
+                    input: "1 < 2\n".to_string(),
+                    expected: "a&b\n".to_string(),
+                    actual: "x".repeat(5000),
+                },
+            },
+        ];
+
+        write(&path, "sum", 10, &testcases).unwrap();
+        let xml = std::fs::read_to_string(&path).unwrap();
+        assert!(xml.contains(r#"tests="2" failures="1" errors="0" skipped="0" time="1.750""#));
+        assert!(xml.contains(r#"<testcase classname="sum" name="fast" time="1.500"/>"#));
+        assert!(xml.contains(r#"<failure message="output mismatch" type="2 failing case(s)">"#));
+        assert!(xml.contains("1 &lt; 2"));
+        assert!(xml.contains("a&amp;b"));
+        assert!(xml.contains("... (3000 more bytes)"));
+    }
+}
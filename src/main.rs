@@ -1,19 +1,55 @@
+mod batch;
 mod cli;
 mod config;
+mod diff;
+mod doctor;
 mod engine;
+mod explain;
+mod export;
+mod expr;
+mod generate;
 mod generator;
+mod git;
+mod graph;
+mod init;
+mod junit;
+mod normalize;
+mod report;
+mod repro;
 mod runner;
+mod sample;
+mod state;
+
+use std::path::Path;
 
 use anyhow::Result;
 use clap::Parser;
 
-use crate::cli::Cli;
+use crate::cli::{resolve_config_path, Cli, Command};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    let config_path = cli::resolve_config_path(cli.config)?;
 
-    let exit_code = engine::run(&config_path)?;
+    let exit_code = match &cli.command {
+        Some(Command::Export(args)) => export::run(
+            &args.config.resolve_path()?,
+            &args.config.overrides(),
+            &args.to,
+        )?,
+        Some(Command::Sample(args)) => {
+            sample::run(&resolve_config_path(args.config.clone())?, args.seed)?
+        }
+        Some(Command::Gen(args)) => generate::run(
+            &args.config.resolve_path()?,
+            &args.config.overrides(),
+            args.out.as_deref(),
+        )?,
+        Some(Command::Doctor(args)) => doctor::run(&resolve_config_path(args.config.clone())?)?,
+        Some(Command::Init(args)) => init::run(Path::new("."), args.force)?,
+        Some(Command::Run(args)) => batch::run(&args.paths, &args.overrides())?,
+        None if cli.explain_failure => explain::run(&cli.config.resolve_path()?)?,
+        None => engine::run(&cli.config.resolve_path()?, &cli.overrides())?,
+    };
     if exit_code != 0 {
         std::process::exit(exit_code);
     }
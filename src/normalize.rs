@@ -0,0 +1,749 @@
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+use crate::config::{ColumnCompare, Normalize, OutputFormat, OutputSchema};
+
+pub fn normalize_output(output: &str, normalize: &Normalize) -> Result<String> {
+    let mut normalized = output.replace("\r\n", "\n");
+
+    if normalize.tokenize {
+        normalized = normalized.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+
+    if normalize.trim_trailing_ws {
+        normalized = normalized
+            .split('\n')
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    if normalize.ignore_final_newline {
+        normalized = normalized.trim_end_matches('\n').to_string();
+    }
+
+    if let Some(indices) = &normalize.compare_tokens {
+        normalized = select_tokens(&normalized, indices);
+    }
+
+    if let Some(modulus) = normalize.modulus {
+        normalized = reduce_integers(&normalized, modulus);
+    }
+
+    if normalize.boolean {
+        normalized = canonicalize_booleans(&normalized);
+    }
+
+    if let Some(decimals) = normalize.round_decimals {
+        normalized = round_numbers(&normalized, decimals);
+    }
+
+    // Folded before sorting, so `B a` and `a b` sort into the same order.
+    if normalize.ignore_case {
+        normalized = normalized.to_lowercase();
+    }
+
+    if normalize.sort_tokens_in_line {
+        normalized = sort_tokens_in_lines(&normalized);
+    }
+
+    if normalize.sort_lines {
+        normalized = sort_lines(&normalized);
+    }
+
+    if normalize.int_multiset {
+        normalized = int_multiset(&normalized)?;
+    }
+
+    Ok(normalized)
+}
+
+/// Compares two normalized outputs and returns the mismatch reason, if any.
+///
+/// With `per_line`, both outputs must have the same number of lines and each
+/// line pair is compared on its own, so the reason names the first diverging
+/// line. With `float_tolerance` or `float_rel_tolerance`, numeric token pairs
+/// within either tolerance are equal; all other tokens must match exactly.
+pub fn compare_outputs(expected: &str, actual: &str, normalize: &Normalize) -> Option<String> {
+    if normalize.per_line {
+        let expected_lines = expected.lines().collect::<Vec<_>>();
+        let actual_lines = actual.lines().collect::<Vec<_>>();
+        if let Some(idx) = expected_lines
+            .iter()
+            .zip(&actual_lines)
+            .position(|(e, a)| !line_matches(e, a, normalize))
+        {
+            return Some(format!("output mismatch at line {}", idx + 1));
+        }
+        return (expected_lines.len() != actual_lines.len()).then(|| {
+            format!(
+                "output mismatch: expected {} line(s), got {}",
+                expected_lines.len(),
+                actual_lines.len()
+            )
+        });
+    }
+
+    if normalize.tolerant() {
+        return (!line_matches(expected, actual, normalize)).then(|| "output mismatch".to_string());
+    }
+
+    (expected != actual).then(|| "output mismatch".to_string())
+}
+
+/// Compares two normalized outputs record by record: every line holds one
+/// token per schema column, and each column is compared by its own rule.
+pub fn compare_with_schema(expected: &str, actual: &str, schema: &OutputSchema) -> Option<String> {
+    let expected_lines = expected.lines().collect::<Vec<_>>();
+    let actual_lines = actual.lines().collect::<Vec<_>>();
+
+    for (idx, (e, a)) in expected_lines.iter().zip(&actual_lines).enumerate() {
+        let line = idx + 1;
+        let e = e.split_whitespace().collect::<Vec<_>>();
+        let a = a.split_whitespace().collect::<Vec<_>>();
+        let columns = schema.columns.len();
+        if e.len() != columns {
+            return Some(format!(
+                "origin output at line {line} has {} field(s), but the schema has {columns}",
+                e.len()
+            ));
+        }
+        if a.len() != columns {
+            return Some(format!(
+                "output mismatch at line {line}: expected {columns} field(s), got {}",
+                a.len()
+            ));
+        }
+
+        for (column, (e, a)) in schema.columns.iter().zip(e.iter().zip(&a)) {
+            let matches = match column.compare {
+                ColumnCompare::Exact => e == a,
+                ColumnCompare::IgnoreCase => e.eq_ignore_ascii_case(a),
+                ColumnCompare::Ignore => true,
+                ColumnCompare::Float => matches!(
+                    (parse_number(e), parse_number(a), column.tolerance),
+                    (Some(x), Some(y), Some(tolerance)) if (x - y).abs() <= tolerance
+                ),
+            };
+            if !matches {
+                return Some(format!(
+                    "output mismatch at line {line}, field `{}`",
+                    column.name
+                ));
+            }
+        }
+    }
+
+    (expected_lines.len() != actual_lines.len()).then(|| {
+        format!(
+            "output mismatch: expected {} line(s), got {}",
+            expected_lines.len(),
+            actual_lines.len()
+        )
+    })
+}
+
+/// Checks raw output line by line against an already normalized expected
+/// output, so a run can be stopped at the first line that can no longer
+/// match. Only rejects lines that are certain to fail the full comparison.
+pub struct LineChecker {
+    expected: Vec<String>,
+    normalize: Normalize,
+    next_line: usize,
+}
+
+impl LineChecker {
+    /// Returns `None` when `normalize` needs the whole output before it can
+    /// decide anything (e.g. `int_multiset`, `sort_lines`, `tokenize`, or a
+    /// flat tolerance comparison that ignores line breaks).
+    pub fn new(expected: &str, normalize: &Normalize) -> Option<Self> {
+        if normalize.int_multiset
+            || normalize.sort_lines
+            || normalize.tokenize
+            || (normalize.tolerant() && !normalize.per_line)
+        {
+            return None;
+        }
+
+        Some(Self {
+            expected: expected.split('\n').map(str::to_string).collect(),
+            normalize: normalize.clone(),
+            next_line: 0,
+        })
+    }
+
+    /// Feeds the next complete line (without its `\n`); `false` means the
+    /// output is already a mismatch.
+    pub fn accept(&mut self, line: &str) -> bool {
+        let mut line = line.strip_suffix('\r').unwrap_or(line);
+        if self.normalize.trim_trailing_ws {
+            line = line.trim_end();
+        }
+        let mut line = line.to_string();
+        if let Some(indices) = &self.normalize.compare_tokens {
+            line = select_tokens(&line, indices);
+        }
+        if let Some(modulus) = self.normalize.modulus {
+            line = reduce_integers(&line, modulus);
+        }
+        if self.normalize.boolean {
+            line = canonicalize_booleans(&line);
+        }
+        if let Some(decimals) = self.normalize.round_decimals {
+            line = round_numbers(&line, decimals);
+        }
+        if self.normalize.ignore_case {
+            line = line.to_lowercase();
+        }
+        if self.normalize.sort_tokens_in_line {
+            line = sort_tokens_in_lines(&line);
+        }
+
+        let idx = self.next_line;
+        self.next_line += 1;
+        match self.expected.get(idx) {
+            Some(expected) => line_matches(expected, &line, &self.normalize),
+            // Trailing blank lines may still vanish under ignore_final_newline.
+            None => line.is_empty(),
+        }
+    }
+}
+
+fn line_matches(expected: &str, actual: &str, normalize: &Normalize) -> bool {
+    if !normalize.tolerant() {
+        return expected == actual;
+    }
+
+    let expected = expected.split_whitespace().collect::<Vec<_>>();
+    let actual = actual.split_whitespace().collect::<Vec<_>>();
+    expected.len() == actual.len()
+        && expected.iter().zip(&actual).all(|(e, a)| {
+            e == a
+                || matches!(
+                    (parse_number(e), parse_number(a)),
+                    (Some(x), Some(y)) if within_tolerance(x, y, normalize)
+                )
+        })
+}
+
+/// Whether `actual` is within the absolute or the relative tolerance of
+/// `expected`; either one is enough.
+fn within_tolerance(expected: f64, actual: f64, normalize: &Normalize) -> bool {
+    let diff = (expected - actual).abs();
+    normalize
+        .float_tolerance
+        .is_some_and(|tolerance| diff <= tolerance)
+        || normalize
+            .float_rel_tolerance
+            .is_some_and(|tolerance| diff <= tolerance * expected.abs())
+}
+
+/// Parses a decimal token such as `-12`, `3.`, `.5` or `1.5e-3`.
+pub fn parse_number(token: &str) -> Option<f64> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"^[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?$").expect("valid regex")
+    });
+
+    if !re.is_match(token) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Keeps the tokens at `indices` on every line, in the listed order, joined
+/// by single spaces. Positions past the end of a line are left out, so a line
+/// that is too short only matches one missing the same tokens.
+fn select_tokens(output: &str, indices: &[usize]) -> String {
+    output
+        .split('\n')
+        .map(|line| {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            indices
+                .iter()
+                .filter_map(|&idx| tokens.get(idx).copied())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rewrites every integer token as its remainder modulo `modulus`, in
+/// `[0, modulus)` even for negative values. Tokens of any length are reduced
+/// digit by digit, so an un-modded answer that overflows every integer type
+/// still compares equal to the modded one.
+fn reduce_integers(output: &str, modulus: i64) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\S+").expect("valid regex"));
+    let modulus = modulus.unsigned_abs() as u128;
+
+    re.replace_all(output, |caps: &regex::Captures| {
+        let token = &caps[0];
+        let (negative, digits) = match token.as_bytes().first() {
+            Some(b'-') => (true, &token[1..]),
+            Some(b'+') => (false, &token[1..]),
+            _ => (false, token),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return token.to_string();
+        }
+
+        let remainder = digits
+            .bytes()
+            .fold(0u128, |acc, b| (acc * 10 + u128::from(b - b'0')) % modulus);
+        if negative && remainder != 0 {
+            (modulus - remainder).to_string()
+        } else {
+            remainder.to_string()
+        }
+    })
+    .into_owned()
+}
+
+/// Rewrites every affirmative token (`yes`, `y`, `true`, `1`, any case) as
+/// `YES` and every negative one (`no`, `n`, `false`, `0`) as `NO`.
+fn canonicalize_booleans(output: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\S+").expect("valid regex"));
+
+    re.replace_all(output, |caps: &regex::Captures| {
+        let token = &caps[0];
+        match token.to_ascii_lowercase().as_str() {
+            "yes" | "y" | "true" | "1" => "YES".to_string(),
+            "no" | "n" | "false" | "0" => "NO".to_string(),
+            _ => token.to_string(),
+        }
+    })
+    .into_owned()
+}
+
+/// Rewrites every numeric token with exactly `decimals` fractional digits,
+/// leaving other tokens and all whitespace untouched. A value that rounds to
+/// zero is printed without a sign so `-0.0001` and `0.0001` agree.
+fn round_numbers(output: &str, decimals: u32) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\S+").expect("valid regex"));
+
+    re.replace_all(output, |caps: &regex::Captures| {
+        let token = &caps[0];
+        let Some(value) = parse_number(token) else {
+            return token.to_string();
+        };
+
+        let rounded = format!("{:.*}", decimals as usize, value);
+        match rounded.strip_prefix('-') {
+            Some(unsigned) if unsigned.chars().all(|c| c == '0' || c == '.') => {
+                unsigned.to_string()
+            }
+            _ => rounded,
+        }
+    })
+    .into_owned()
+}
+
+/// Sorts the tokens of each line and rejoins them with single spaces, so a
+/// line compares as a set while the lines keep their order.
+fn sort_tokens_in_lines(output: &str) -> String {
+    output
+        .split('\n')
+        .map(|line| {
+            let mut tokens = line.split_whitespace().collect::<Vec<_>>();
+            tokens.sort_unstable();
+            tokens.join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Sorts the lines as strings. Trailing empty lines stay at the end, so a
+/// final newline kept by `ignore_final_newline = false` does not move.
+fn sort_lines(output: &str) -> String {
+    let body = output.trim_end_matches('\n');
+    let mut lines = body.split('\n').collect::<Vec<_>>();
+    lines.sort_unstable();
+    format!("{}{}", lines.join("\n"), &output[body.len()..])
+}
+
+/// Parses every whitespace-separated token as an integer and renders the
+/// sorted bag, so outputs compare equal when they hold the same values with
+/// the same multiplicities.
+fn int_multiset(output: &str) -> Result<String> {
+    let mut values = output
+        .split_whitespace()
+        .map(|token| {
+            token
+                .parse::<i128>()
+                .with_context(|| format!("int_multiset expects integers, got `{token}`"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    values.sort_unstable();
+
+    Ok(values
+        .iter()
+        .map(i128::to_string)
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Checks the raw `output` against the declared `problem.output_format`.
+pub fn check_output_format(output: &str, format: OutputFormat) -> Result<()> {
+    let lines = output
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect::<Vec<_>>();
+    let is_int = |token: &str| {
+        let digits = token.strip_prefix(['+', '-']).unwrap_or(token);
+        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+    };
+
+    match format {
+        OutputFormat::SingleInt => {
+            let tokens = output.split_whitespace().collect::<Vec<_>>();
+            match tokens.as_slice() {
+                [token] if is_int(token) => {}
+                [token] => bail!("expected a single integer, got `{token}`"),
+                _ => bail!("expected a single integer, got {} tokens", tokens.len()),
+            }
+        }
+        OutputFormat::IntLines => {
+            for (idx, line) in lines.iter().enumerate() {
+                if !is_int(line.trim()) {
+                    bail!("line {}: expected one integer, got `{line}`", idx + 1);
+                }
+            }
+        }
+        OutputFormat::Grid => {
+            let Some(first) = lines.first() else {
+                bail!("expected a grid, got no output");
+            };
+            let width = first.split_whitespace().count();
+            let chars = first.trim().chars().count();
+            for (idx, line) in lines.iter().enumerate() {
+                let tokens = line.split_whitespace().count();
+                if tokens == 0 {
+                    bail!("line {}: empty row in grid", idx + 1);
+                }
+                if tokens != width {
+                    bail!(
+                        "line {}: expected {width} column(s) like line 1, got {tokens}",
+                        idx + 1
+                    );
+                }
+                if width == 1 && line.trim().chars().count() != chars {
+                    bail!(
+                        "line {}: expected width {chars} like line 1, got {}",
+                        idx + 1,
+                        line.trim().chars().count()
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Column, ColumnCompare, Normalize, OutputFormat, OutputSchema};
+
+    use super::{
+        check_output_format, compare_outputs, compare_with_schema, normalize_output,
+        reduce_integers, LineChecker,
+    };
+
+    #[test]
+    fn int_multiset_ignores_order_but_keeps_counts() {
+        let normalize = Normalize {
+            int_multiset: true,
+            ..Normalize::default()
+        };
+
+        let a = normalize_output("3 1\n2 1\n", &normalize).expect("integers");
+        let b = normalize_output("1 1 2 3", &normalize).expect("integers");
+        let c = normalize_output("1 2 3", &normalize).expect("integers");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn round_decimals_rewrites_only_numeric_tokens() {
+        let normalize = Normalize {
+            round_decimals: Some(2),
+            ..Normalize::default()
+        };
+
+        let rounded = normalize_output("x 3.14159 -0.001\n7 .5", &normalize).expect("rounds");
+        assert_eq!(rounded, "x 3.14 0.00\n7.00 0.50");
+    }
+
+    #[test]
+    fn float_tolerance_accepts_scientific_notation() {
+        let normalize = Normalize {
+            float_tolerance: Some(1e-9),
+            ..Normalize::default()
+        };
+
+        assert_eq!(
+            compare_outputs("1000000 0.0015", "1e6 1.5E-3", &normalize),
+            None
+        );
+        assert!(compare_outputs("1e6", "1e7", &normalize).is_some());
+        assert!(compare_outputs("e6", "1e6", &normalize).is_some());
+    }
+
+    #[test]
+    fn relative_tolerance_scales_with_the_expected_value() {
+        let normalize = Normalize {
+            float_rel_tolerance: Some(1e-6),
+            ..Normalize::default()
+        };
+
+        assert_eq!(
+            compare_outputs("1000000 x", "1000000.5 x", &normalize),
+            None
+        );
+        assert!(compare_outputs("1 x", "1.5 x", &normalize).is_some());
+        assert!(compare_outputs("1 x", "1 y", &normalize).is_some());
+        assert!(compare_outputs("1", "1 1", &normalize).is_some());
+
+        let either = Normalize {
+            float_tolerance: Some(1e-3),
+            ..normalize
+        };
+        assert_eq!(compare_outputs("3.1400001", "3.14", &either), None);
+    }
+
+    #[test]
+    fn sort_lines_ignores_line_order_but_not_content() {
+        let normalize = Normalize {
+            sort_lines: true,
+            ..Normalize::default()
+        };
+        let sorted = |output| normalize_output(output, &normalize).expect("normalizes");
+
+        assert_eq!(sorted("2 3\n1 4 \n1 2\n"), "1 2\n1 4\n2 3");
+        assert_eq!(sorted("1 4\n2 3\n1 2"), sorted("1 2\n2 3\n1 4\n"));
+        assert_ne!(sorted("1 2\n3 4"), sorted("1 2\n4 3"));
+
+        let keep_newline = Normalize {
+            ignore_final_newline: false,
+            ..normalize
+        };
+        let kept = normalize_output("b\na\n", &keep_newline).expect("normalizes");
+        assert_eq!(kept, "a\nb\n");
+    }
+
+    #[test]
+    fn ignore_case_folds_before_sorting() {
+        let normalize = Normalize {
+            ignore_case: true,
+            sort_tokens_in_line: true,
+            ..Normalize::default()
+        };
+        let folded = |output| normalize_output(output, &normalize).expect("normalizes");
+
+        assert_eq!(folded("YES\nNo \n"), "yes\nno");
+        assert_eq!(folded("B a"), folded("A b"));
+        assert_ne!(folded("yes"), folded("yes!"));
+
+        let mut checker = LineChecker::new(&folded("Yes\nNO"), &normalize).expect("streams");
+        assert!(checker.accept("yEs"));
+        assert!(!checker.accept("yes"));
+    }
+
+    #[test]
+    fn tokenize_ignores_how_tokens_are_separated() {
+        let normalize = Normalize {
+            tokenize: true,
+            ..Normalize::default()
+        };
+        let flat = |output| normalize_output(output, &normalize).expect("normalizes");
+
+        assert_eq!(flat("1 2\t 3\r\n4\n\n"), "1 2 3 4");
+        assert_eq!(flat("1\n2\n3\n"), flat("1 2 3"));
+        assert_ne!(flat("1 2 3"), flat("1 3 2"));
+
+        let unordered = Normalize {
+            sort_tokens_in_line: true,
+            compare_tokens: Some(vec![2, 0]),
+            ..normalize
+        };
+        let picked = normalize_output("9 x\n1\n", &unordered).expect("normalizes");
+        assert_eq!(picked, "1 9");
+        assert!(LineChecker::new("1 2", &unordered).is_none());
+    }
+
+    #[test]
+    fn boolean_unifies_affirmative_and_negative_spellings() {
+        let normalize = Normalize {
+            boolean: true,
+            ..Normalize::default()
+        };
+
+        let a = normalize_output("Yes\nfalse\nmaybe", &normalize).expect("normalizes");
+        let b = normalize_output("1\nNO\nmaybe", &normalize).expect("normalizes");
+        assert_eq!(a, "YES\nNO\nmaybe");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn int_multiset_rejects_non_integer_tokens() {
+        let normalize = Normalize {
+            int_multiset: true,
+            ..Normalize::default()
+        };
+
+        let error = normalize_output("1 two 3", &normalize).expect_err("must reject");
+        assert!(error.to_string().contains("`two`"));
+    }
+
+    #[test]
+    fn per_line_tolerance_reports_first_diverging_line() {
+        let normalize = Normalize {
+            per_line: true,
+            float_tolerance: Some(1e-6),
+            ..Normalize::default()
+        };
+
+        assert_eq!(
+            compare_outputs("0.5\n3.1400001", "0.5\n3.14", &normalize),
+            None
+        );
+        assert_eq!(
+            compare_outputs("1.0\n2.0\n3.0", "1.0\n2.5\n3.0", &normalize).as_deref(),
+            Some("output mismatch at line 2")
+        );
+        assert_eq!(
+            compare_outputs("1.0\n2.0", "1.0", &normalize).as_deref(),
+            Some("output mismatch: expected 2 line(s), got 1")
+        );
+    }
+
+    #[test]
+    fn line_checker_rejects_only_certain_mismatches() {
+        let normalize = Normalize::default();
+        let expected = normalize_output("1\n2\n3\n", &normalize).expect("normalizes");
+
+        let mut checker = LineChecker::new(&expected, &normalize).expect("streamable");
+        assert!(checker.accept("1  "));
+        assert!(checker.accept("2\r"));
+        assert!(!checker.accept("4"));
+
+        let mut checker = LineChecker::new(&expected, &normalize).expect("streamable");
+        for line in ["1", "2", "3", ""] {
+            assert!(checker.accept(line));
+        }
+        assert!(!checker.accept("extra"));
+
+        let multiset = Normalize {
+            int_multiset: true,
+            ..Normalize::default()
+        };
+        assert!(LineChecker::new(&expected, &multiset).is_none());
+    }
+
+    #[test]
+    fn output_format_checks_shape_not_values() {
+        assert!(check_output_format("-42\n", OutputFormat::SingleInt).is_ok());
+        assert!(check_output_format("4 2\n", OutputFormat::SingleInt).is_err());
+        assert!(check_output_format("1\r\n+2\n3\n", OutputFormat::IntLines).is_ok());
+        assert!(check_output_format("1\n2.5\n", OutputFormat::IntLines).is_err());
+        assert!(check_output_format("#..#\n.##.\n", OutputFormat::Grid).is_ok());
+        assert!(check_output_format("#..#\n.#\n", OutputFormat::Grid).is_err());
+        assert!(check_output_format("1 0 1\n0 10 0\n", OutputFormat::Grid).is_ok());
+        assert!(check_output_format("1 0\n\n", OutputFormat::Grid).is_err());
+    }
+
+    #[test]
+    fn mod_reduces_integers_into_range() {
+        assert_eq!(
+            reduce_integers("1000000008 -1 7 x 2.5", 1_000_000_007),
+            "1 1000000006 7 x 2.5"
+        );
+        // 10^40 does not fit in any integer type.
+        let huge = format!("1{}", "0".repeat(40));
+        assert_eq!(reduce_integers(&huge, 1_000_000_007), "24010000");
+    }
+
+    #[test]
+    fn schema_compares_each_column_by_its_rule() {
+        let column = |name: &str, compare, tolerance| Column {
+            name: name.to_string(),
+            compare,
+            tolerance,
+        };
+        let schema = OutputSchema {
+            columns: vec![
+                column("name", ColumnCompare::IgnoreCase, None),
+                column("age", ColumnCompare::Exact, None),
+                column("score", ColumnCompare::Float, Some(1e-3)),
+            ],
+        };
+        let expected = "alice 30 91.5\nbob 41 77.25";
+
+        assert_eq!(
+            compare_with_schema(expected, "Alice 30 91.5004\nBOB 41 77.25", &schema),
+            None
+        );
+        assert_eq!(
+            compare_with_schema(expected, "alice 30 91.5\nbob 41 77.3", &schema),
+            Some("output mismatch at line 2, field `score`".to_string())
+        );
+        assert_eq!(
+            compare_with_schema(expected, "alice 30\nbob 41 77.25", &schema),
+            Some("output mismatch at line 1: expected 3 field(s), got 2".to_string())
+        );
+        assert_eq!(
+            compare_with_schema("alice 30 91.5 x\nbob 41 77.25", "alice 30 91.5", &schema),
+            Some("origin output at line 1 has 4 field(s), but the schema has 3".to_string())
+        );
+    }
+
+    #[test]
+    fn compare_tokens_ignores_unlisted_columns() {
+        let normalize = Normalize {
+            compare_tokens: Some(vec![0, 2]),
+            ..Normalize::default()
+        };
+        let normalize_both = |expected: &str, actual: &str| {
+            compare_outputs(
+                &normalize_output(expected, &normalize).unwrap(),
+                &normalize_output(actual, &normalize).unwrap(),
+                &normalize,
+            )
+        };
+
+        assert_eq!(
+            normalize_both("1 12:00 ok\n2 12:01 ok\n", "1 09:30 ok\n2 09:31 ok\n"),
+            None
+        );
+        assert!(normalize_both("1 12:00 ok\n", "1 12:00 bad\n").is_some());
+        assert!(normalize_both("1 12:00 ok\n", "1 12:00\n").is_some());
+
+        let mut checker = LineChecker::new("1 ok", &normalize).unwrap();
+        assert!(checker.accept("1   07:15   ok  "));
+    }
+
+    #[test]
+    fn sort_tokens_in_line_keeps_line_order() {
+        let normalize = Normalize {
+            sort_tokens_in_line: true,
+            ..Normalize::default()
+        };
+
+        let expected = normalize_output("1 3 2\n5 4\n", &normalize).unwrap();
+        assert_eq!(expected, "1 2 3\n4 5");
+        assert_eq!(
+            normalize_output("2  1 3\n4 5", &normalize).unwrap(),
+            expected
+        );
+        assert_ne!(
+            normalize_output("4 5\n1 2 3", &normalize).unwrap(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable outcome of a run, saved with `--report` and compared
+/// against with `--baseline`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunReport {
+    pub cases: usize,
+    pub candidates: Vec<CandidateReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CandidateReport {
+    pub name: String,
+    pub verdict: Verdict,
+    pub failures: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Verdict {
+    Pass,
+    Fail,
+    Unknown,
+}
+
+impl Verdict {
+    pub fn label(self) -> &'static str {
+        match self {
+            Verdict::Pass => "PASS",
+            Verdict::Fail => "FAIL",
+            Verdict::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+/// How a run prints its results: for people, or as one JSON object on stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(raw: &str) -> std::result::Result<Self, Self::Err> {
+        match raw {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("format must be \"text\" or \"json\", got {raw:?}")),
+        }
+    }
+}
+
+/// Everything a run prints under `--format json`.
+#[derive(Debug, Serialize)]
+pub struct JsonReport {
+    pub summary: JsonSummary,
+    pub candidates: Vec<JsonCandidate>,
+    /// First origin or engine failure, which leaves candidate verdicts unknown.
+    pub engine_failure: Option<FailureReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonSummary {
+    pub verdict: Verdict,
+    pub cases: usize,
+    /// Cases that finished; fewer than `cases` when the deadline cut the run short.
+    pub completed: usize,
+    pub candidates: usize,
+    pub workers: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonCandidate {
+    pub name: String,
+    pub verdict: Verdict,
+    pub mismatches: usize,
+    /// Skipped by `--since-last`; the verdict is the one it last passed with.
+    pub cached: bool,
+    pub first_failure: Option<FailureReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailureReport {
+    /// One-based, as in `FAIL at case #N`.
+    pub case: usize,
+    pub candidate: String,
+    pub origin: Option<String>,
+    pub reason: String,
+    pub input: String,
+    pub origin_stdout: String,
+    pub candidate_stdout: String,
+    pub origin_stderr: String,
+    pub candidate_stderr: String,
+}
+
+pub fn print_json(report: &JsonReport) -> Result<()> {
+    let raw = serde_json::to_string_pretty(report).context("failed to serialize JSON report")?;
+    println!("{raw}");
+    Ok(())
+}
+
+pub fn save(path: &Path, report: &RunReport) -> Result<()> {
+    let raw = serde_json::to_string_pretty(report).context("failed to serialize run report")?;
+    std::fs::write(path, raw + "\n")
+        .with_context(|| format!("failed to write run report: {}", path.display()))
+}
+
+pub fn load(path: &Path) -> Result<RunReport> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read baseline report: {}", path.display()))?;
+    serde_json::from_str(&raw)
+        .with_context(|| format!("failed to parse baseline report: {}", path.display()))
+}
+
+/// Describes every candidate whose verdict changed since `baseline`, plus
+/// candidates that were added or removed. Empty when nothing changed.
+pub fn diff(baseline: &RunReport, current: &RunReport) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for candidate in &current.candidates {
+        let before = baseline
+            .candidates
+            .iter()
+            .find(|old| old.name == candidate.name);
+        match before {
+            None => changes.push(format!(
+                "{}: new candidate, {}",
+                candidate.name,
+                candidate.verdict.label()
+            )),
+            Some(before) if before.verdict != candidate.verdict => {
+                let trend = match (before.verdict, candidate.verdict) {
+                    (_, Verdict::Pass) => "newly passes",
+                    (_, Verdict::Fail) => "newly fails",
+                    _ => "now unknown",
+                };
+                changes.push(format!(
+                    "{}: {trend} (was {}, now {})",
+                    candidate.name,
+                    before.verdict.label(),
+                    candidate.verdict.label()
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for before in &baseline.candidates {
+        if !current.candidates.iter().any(|c| c.name == before.name) {
+            changes.push(format!(
+                "{}: no longer configured (was {})",
+                before.name,
+                before.verdict.label()
+            ));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, CandidateReport, Format, JsonCandidate, RunReport, Verdict};
+
+    fn report(verdicts: &[(&str, Verdict)]) -> RunReport {
+        RunReport {
+            cases: 10,
+            candidates: verdicts
+                .iter()
+                .map(|(name, verdict)| CandidateReport {
+                    name: name.to_string(),
+                    verdict: *verdict,
+                    failures: usize::from(*verdict == Verdict::Fail),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn json_candidates_use_report_verdict_labels() {
+        let candidate = JsonCandidate {
+            name: "a".to_string(),
+            verdict: Verdict::Fail,
+            mismatches: 2,
+            cached: false,
+            first_failure: None,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&candidate).unwrap(),
+            r#"{"name":"a","verdict":"FAIL","mismatches":2,"cached":false,"first_failure":null}"#
+        );
+        assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+        assert!("yaml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn diff_flags_verdict_changes_only() {
+        let baseline = report(&[
+            ("a", Verdict::Pass),
+            ("b", Verdict::Fail),
+            ("c", Verdict::Pass),
+        ]);
+        let current = report(&[
+            ("a", Verdict::Fail),
+            ("b", Verdict::Pass),
+            ("c", Verdict::Pass),
+        ]);
+
+        assert_eq!(
+            diff(&baseline, &current),
+            vec![
+                "a: newly fails (was PASS, now FAIL)",
+                "b: newly passes (was FAIL, now PASS)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::{Config, Program, Transport};
+use crate::engine::{case_program, origin_input, run_origin};
+use crate::runner::{describe_command, program_dir, shell_quote, RunSettings};
+
+const INPUT_DELIMITER: &str = "NADO_INPUT";
+
+/// Writes a standalone shell script that feeds `input` to the first origin
+/// and, when given, the failing candidate, so the failure can be reproduced
+/// without nado.
+pub fn write(
+    path: &Path,
+    config: &Config,
+    config_dir: &Path,
+    case_index: usize,
+    candidate: Option<&Program>,
+    reason: &str,
+    input: &str,
+) -> Result<()> {
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(&format!(
+        "# nado reproducer for case #{}: {}\n",
+        case_index + 1,
+        comment(reason)
+    ));
+    script.push_str("# Resource limits from [limits] are not applied here.\n");
+    script.push_str("set -u\n");
+    script.push_str("input=$(mktemp)\n");
+    script.push_str("trap 'rm -f \"$input\"' EXIT\n");
+    script.push_str(&write_input("input", input));
+    let templated = config.problem.origin_template.is_some();
+    if templated {
+        script.push_str("origin_input=$(mktemp)\n");
+        script.push_str("trap 'rm -f \"$input\" \"$origin_input\"' EXIT\n");
+        script.push_str(&write_input("origin_input", &origin_input(config, input)?));
+    }
+
+    let mut programs = config
+        .origin
+        .first()
+        .map(|origin| (origin.name.as_deref().unwrap_or("origin"), origin))
+        .into_iter()
+        .collect::<Vec<_>>();
+    if let Some(candidate) = candidate {
+        programs.push((candidate.name.as_deref().unwrap_or("candidate"), candidate));
+    }
+    for (idx, (label, program)) in programs.into_iter().enumerate() {
+        // Only the first entry is the origin.
+        let file = if idx == 0 && templated {
+            "origin_input"
+        } else {
+            "input"
+        };
+        script.push_str(&format!("\necho '== {} =='\n", comment(label)));
+        script.push_str(&program_line(config, config_dir, program, input, file)?);
+        script.push_str("echo \"exit: $?\"\n");
+    }
+
+    std::fs::write(path, script)
+        .with_context(|| format!("failed to write reproducer: {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("failed to make {} executable", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Shell that recreates `input` byte for byte in the file named by `$var`: a
+/// quoted heredoc whose delimiter never occurs as a line of the input, or a
+/// quoted `printf` argument when there is no final newline for the heredoc to
+/// end on.
+fn write_input(var: &str, input: &str) -> String {
+    let Some(body) = input.strip_suffix('\n') else {
+        return format!("printf '%s' {} > \"${var}\"\n", shell_quote(input));
+    };
+
+    let mut delimiter = INPUT_DELIMITER.to_string();
+    while input.lines().any(|line| line == delimiter) {
+        delimiter.push('_');
+    }
+    format!("cat > \"${var}\" <<'{delimiter}'\n{body}\n{delimiter}\n")
+}
+
+/// Runs `program` on the file named by `$file`.
+fn program_line(
+    config: &Config,
+    config_dir: &Path,
+    program: &Program,
+    input: &str,
+    file: &str,
+) -> Result<String> {
+    if let Some(source) = &program.expr {
+        // Nothing to run; print the value nado computed for this input.
+        let output = run_origin(config, config_dir, program, input)?;
+        return Ok(format!(
+            "# formula origin: {}\nprintf '%s' {}\n",
+            comment(source),
+            shell_quote(&output.stdout)
+        ));
+    }
+
+    if program.transport == Transport::Socket {
+        return Ok(format!(
+            "# transport = \"socket\": start the program and send \"${file}\" over its socket\n"
+        ));
+    }
+
+    let (program, stdin) = case_program(config, program, input)?;
+    let command = describe_command(&program, config_dir, &RunSettings::new(config))?;
+    if stdin.is_empty() && !input.is_empty() {
+        // Takes the case as `{N}` arguments, already filled in.
+        return Ok(format!("({command}) < /dev/null\n"));
+    }
+    if config.problem.input_file().is_some() {
+        if program.image.is_some() {
+            return Ok(format!(
+                "# input_mode = \"file\": mount \"${file}\" into the container for `{{input}}`\n"
+            ));
+        }
+        // `{input}` sits inside a single-quoted argument, if quoted at all.
+        let command = command.replace("{input}", &format!("'\"${file}\"'"));
+        return Ok(format!("({command}) < /dev/null\n"));
+    }
+    let Some(transform) = &program.input_transform_cmd else {
+        return Ok(format!("({command}) < \"${file}\"\n"));
+    };
+    let dir = program_dir(&program, config_dir);
+    let transform = transform
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(format!(
+        "(cd {} && {transform}) < \"${file}\" | ({command})\n",
+        shell_quote(&dir.to_string_lossy())
+    ))
+}
+
+/// Text safe to place inside a single-quoted echo or a comment line.
+fn comment(text: &str) -> String {
+    text.replace(['\n', '\r'], " ").replace('\'', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_input;
+
+    #[test]
+    fn input_survives_the_shell() {
+        assert_eq!(
+            write_input("input", "1 2\n$HOME `x`\n"),
+            "cat > \"$input\" <<'NADO_INPUT'\n1 2\n$HOME `x`\nNADO_INPUT\n"
+        );
+        assert!(write_input("input", "NADO_INPUT\n").contains("<<'NADO_INPUT_'\n"));
+        assert_eq!(
+            write_input("input", "it's"),
+            "printf '%s' 'it'\\''s' > \"$input\"\n"
+        );
+    }
+}
@@ -1,26 +1,96 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus, Stdio};
-use std::thread;
-use std::time::Duration;
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
+#[cfg(not(unix))]
 use wait_timeout::ChildExt;
 
-use crate::config::{Limits, Program};
+use crate::config::{Config, ContainerRuntime, Limits, Program, Transport};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RunOutput {
     pub status: ExitStatus,
     pub stdout: String,
     pub stderr: String,
     pub timed_out: bool,
+    /// Killed by nado after a [`LineCheck`] rejected its output; `stdout`
+    /// holds everything read up to that point.
+    pub stopped_early: bool,
+    /// Time from spawn until the program exited or was killed.
+    pub duration: Duration,
+    /// Peak resident set size in KiB, where the platform reports it.
+    pub max_rss_kb: Option<u64>,
+}
+
+/// Everything besides the program that decides how the runner starts it:
+/// `[limits]` plus the engine and problem settings that shape each spawn.
+/// Built from the config wherever a program starts, so it always matches it.
+#[derive(Debug, Clone, Copy)]
+pub struct RunSettings<'a> {
+    pub limits: &'a Limits,
+    /// Pin each local program to its worker's CPU (`engine.pin_cpus`).
+    pub pin_cpus: bool,
+    /// Least time between two spawns (`engine.spawn_stagger_ms`).
+    pub spawn_stagger_ms: u64,
+    /// Fail a program that exits before reading all of its input
+    /// (`engine.strict_stdin`).
+    pub strict_stdin: bool,
+    /// Binary that runs `docker` programs (`engine.container_runtime`).
+    pub container_runtime: ContainerRuntime,
+    /// Case file name under `problem.input_mode = "file"`; `None` pipes the
+    /// case to stdin.
+    pub input_file: Option<&'a str>,
+    /// Reported for a socket program stopped once it answered, so it counts
+    /// as a clean exit under `engine.success_exit_codes`.
+    pub clean_exit: ExitStatus,
+}
+
+impl<'a> RunSettings<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            limits: &config.limits,
+            pin_cpus: config.engine.pin_cpus,
+            spawn_stagger_ms: config.engine.spawn_stagger_ms,
+            strict_stdin: config.engine.strict_stdin,
+            container_runtime: config.engine.container_runtime,
+            input_file: config.problem.input_file(),
+            clean_exit: config.clean_exit(),
+        }
+    }
+}
+
+/// Raised by [`abort_all`]: running programs are killed and no new one starts.
+static ABORTED: AtomicBool = AtomicBool::new(false);
+
+/// Kills every running program within one poll interval and makes later
+/// spawns fail, for `engine.hard_deadline_ms`.
+pub fn abort_all() {
+    ABORTED.store(true, Ordering::SeqCst);
+}
+
+pub fn aborted() -> bool {
+    ABORTED.load(Ordering::SeqCst)
 }
 
+/// Clears an earlier [`abort_all`] before a new run starts.
+pub fn reset_abort() {
+    ABORTED.store(false, Ordering::SeqCst);
+}
+
+/// Called with every complete stdout line (without the `\n`) as it arrives;
+/// returning `false` stops the run.
+pub type LineCheck = Box<dyn FnMut(&str) -> bool + Send>;
+
 #[derive(Debug, Clone)]
 struct MountSpec {
     host: String,
@@ -33,131 +103,1201 @@ enum RunMode {
     Docker,
 }
 
+/// Environment variable holding the socket path for `transport = "socket"`.
+const SOCKET_ENV: &str = "NADO_SOCKET";
+/// Argument text replaced by the socket path for `transport = "socket"`.
+const SOCKET_PLACEHOLDER: &str = "{socket}";
+
+/// Argument text replaced by the case file's path under
+/// `problem.input_mode = "file"`.
+const INPUT_PLACEHOLDER: &str = "{input}";
+/// Where a Docker program finds the case file's directory.
+const CONTAINER_INPUT_DIR: &str = "/nado-input";
+
+/// A fully resolved command ready to spawn.
+struct Launch {
+    argv: Vec<String>,
+    mode: RunMode,
+    dir: PathBuf,
+    env: BTreeMap<String, String>,
+    /// CPU to pin a local program to, from [`worker_cpu`].
+    cpu: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct InteractiveOutput {
+    pub program: RunOutput,
+    pub judge: RunOutput,
+}
+
 pub fn run_program(
     program: &Program,
     input: &str,
     config_dir: &Path,
     timeout_ms: u64,
-    limits: &Limits,
+    settings: &RunSettings,
 ) -> Result<RunOutput> {
-    let mounts = parse_mounts(&program.mounts)?;
+    let input = transform_input(program, input, config_dir, timeout_ms)?;
+    let mut launch = resolve_launch(program, config_dir, settings)?;
+    if program.transport == Transport::Socket {
+        return run_socket(&launch, &input, timeout_ms, settings);
+    }
+    let staged = stage_input_file(&mut launch, &input, config_dir, settings)?;
+    let stdin = if staged.is_some() { "" } else { &input };
+    run_command(&launch, stdin, timeout_ms, settings, None)
+}
 
-    if let Some(image) = &program.image {
-        let docker_cmd = build_docker_cmd(image, &program.cmd, &mounts, config_dir, limits)?;
-        return run_command(
-            &docker_cmd,
-            input,
-            config_dir,
-            timeout_ms,
-            limits,
-            RunMode::Docker,
+/// Like [`run_program`], but feeds stdout to `check` line by line and kills
+/// the program as soon as `check` rejects a line, instead of buffering output
+/// that can no longer match.
+pub fn run_program_checked(
+    program: &Program,
+    input: &str,
+    config_dir: &Path,
+    timeout_ms: u64,
+    settings: &RunSettings,
+    check: LineCheck,
+) -> Result<RunOutput> {
+    let input = transform_input(program, input, config_dir, timeout_ms)?;
+    let mut launch = resolve_launch(program, config_dir, settings)?;
+    if program.transport == Transport::Socket {
+        // The answer arrives in one piece once the program closes the
+        // connection, so there are no lines to check early.
+        return run_socket(&launch, &input, timeout_ms, settings);
+    }
+    let staged = stage_input_file(&mut launch, &input, config_dir, settings)?;
+    let stdin = if staged.is_some() { "" } else { &input };
+    run_command(&launch, stdin, timeout_ms, settings, Some(check))
+}
+
+/// Under `problem.input_mode = "file"`, writes `input` to a fresh directory
+/// in `config_dir` and points `{input}` in the argv at it; Docker programs get
+/// the directory mounted read-only at `/nado-input`. Every call gets its own
+/// directory, so parallel workers never share a file, and it is removed when
+/// the returned guard drops.
+fn stage_input_file(
+    launch: &mut Launch,
+    input: &str,
+    config_dir: &Path,
+    settings: &RunSettings,
+) -> Result<Option<tempfile::TempDir>> {
+    let Some(name) = settings.input_file else {
+        return Ok(None);
+    };
+
+    let dir = tempfile::Builder::new()
+        .prefix(".nado-input-")
+        .tempdir_in(config_dir)
+        .context("failed to create input file directory")?;
+    let host_path = dir.path().join(name);
+    fs::write(&host_path, input).context("failed to write input file")?;
+
+    let path = match launch.mode {
+        RunMode::Local => host_path.to_string_lossy().to_string(),
+        RunMode::Docker => format!("{CONTAINER_INPUT_DIR}/{name}"),
+    };
+    for arg in &mut launch.argv {
+        *arg = arg.replace(INPUT_PLACEHOLDER, &path);
+    }
+    if matches!(launch.mode, RunMode::Docker) {
+        // Right after `docker run`, ahead of the image name.
+        let mount = format!("{}:{CONTAINER_INPUT_DIR}:ro", dir.path().display());
+        launch.argv.splice(2..2, ["-v".to_string(), mount]);
+    }
+    Ok(Some(dir))
+}
+
+/// Pipes `input` through the program's `input_transform_cmd`, run locally in
+/// the program's directory, or returns it unchanged when there is none.
+fn transform_input<'a>(
+    program: &Program,
+    input: &'a str,
+    config_dir: &Path,
+    timeout_ms: u64,
+) -> Result<Cow<'a, str>> {
+    let Some(transform) = &program.input_transform_cmd else {
+        return Ok(Cow::Borrowed(input));
+    };
+    if transform.is_empty() {
+        bail!("input_transform_cmd is empty");
+    }
+
+    let dir = program_dir(program, config_dir);
+    let launch = Launch {
+        argv: resolve_local_cmd(transform, &parse_mounts(&program.mounts)?, &dir)?,
+        mode: RunMode::Local,
+        dir,
+        env: program.env.clone(),
+        cpu: None,
+    };
+    // Resource limits are meant for the program itself, not for nado's glue.
+    let limits = Limits::default();
+    let settings = RunSettings {
+        limits: &limits,
+        pin_cpus: false,
+        spawn_stagger_ms: 0,
+        strict_stdin: false,
+        container_runtime: ContainerRuntime::Docker,
+        input_file: None,
+        clean_exit: exit_status(0),
+    };
+    let output = run_command(&launch, input, timeout_ms, &settings, None)
+        .context("input_transform_cmd failed to run")?;
+    if output.timed_out {
+        bail!("input_transform_cmd timed out after {timeout_ms}ms");
+    }
+    if !output.status.success() {
+        bail!(
+            "input_transform_cmd {}: {}",
+            describe_status(&output.status),
+            output.stderr.trim_end()
         );
     }
+    Ok(Cow::Owned(output.stdout))
+}
 
-    if program.cmd.is_empty() {
-        bail!("program cmd is empty");
+/// Runs `program` against an interactive `judge`.
+///
+/// The judge is spawned locally with the path of a file holding the generated
+/// case appended to its argv. The program's stdout is relayed to the judge's
+/// stdin and the judge's stdout back to the program's stdin until both exit.
+/// The judge's exit status is the verdict.
+pub fn run_interactive(
+    program: &Program,
+    judge: &Program,
+    input: &str,
+    config_dir: &Path,
+    timeout_ms: u64,
+    judge_timeout_ms: u64,
+    settings: &RunSettings,
+) -> Result<InteractiveOutput> {
+    if judge.image.is_some() {
+        bail!("judge must run locally, image is not supported");
     }
 
-    let local_cmd = resolve_local_cmd(&program.cmd, &mounts, config_dir)?;
-    run_command(
-        &local_cmd,
-        input,
-        config_dir,
-        timeout_ms,
-        limits,
-        RunMode::Local,
-    )
+    let mut input_file = tempfile::Builder::new()
+        .prefix("nado-judge-")
+        .suffix(".in")
+        .tempfile()
+        .context("failed to create judge input file")?;
+    input_file
+        .write_all(input.as_bytes())
+        .and_then(|_| input_file.flush())
+        .context("failed to write judge input file")?;
+
+    let program_launch = resolve_launch(program, config_dir, settings)?;
+    let mut judge_launch = resolve_launch(judge, config_dir, settings)?;
+    judge_launch
+        .argv
+        .push(input_file.path().to_string_lossy().to_string());
+
+    let mut program_child = spawn_command(&program_launch, settings)?;
+    let mut judge_child = match spawn_command(&judge_launch, settings) {
+        Ok(child) => child,
+        Err(error) => {
+            let _ = program_child.kill();
+            let _ = program_child.wait();
+            return Err(error);
+        }
+    };
+
+    let program_stdin = program_child
+        .take_stdin()
+        .context("failed to capture stdin")?;
+    let program_stdout = program_child
+        .take_stdout()
+        .context("failed to capture stdout")?;
+    let judge_stdin = judge_child
+        .take_stdin()
+        .context("failed to capture judge stdin")?;
+    let judge_stdout = judge_child
+        .take_stdout()
+        .context("failed to capture judge stdout")?;
+
+    let to_judge = thread::spawn(move || relay(program_stdout, judge_stdin));
+    let to_program = thread::spawn(move || relay(judge_stdout, program_stdin));
+    let program_stderr = capture(
+        program_child
+            .take_stderr()
+            .context("failed to capture stderr")?,
+    );
+    let judge_stderr = capture(
+        judge_child
+            .take_stderr()
+            .context("failed to capture judge stderr")?,
+    );
+
+    let started = Instant::now();
+    let (program_status, program_timed_out) = wait_until(
+        &mut program_child,
+        started + Duration::from_millis(timeout_ms),
+    )?;
+    let program_duration = started.elapsed();
+    let (judge_status, judge_timed_out) = wait_until(
+        &mut judge_child,
+        started + Duration::from_millis(judge_timeout_ms),
+    )?;
+    let judge_duration = started.elapsed();
+
+    Ok(InteractiveOutput {
+        program: RunOutput {
+            status: program_status,
+            stdout: join_output(to_judge, "program relay")?,
+            stderr: join_output(program_stderr, "stderr")?,
+            timed_out: program_timed_out,
+            stopped_early: false,
+            duration: program_duration,
+            max_rss_kb: program_child.max_rss_kb(),
+        },
+        judge: RunOutput {
+            status: judge_status,
+            stdout: join_output(to_program, "judge relay")?,
+            stderr: join_output(judge_stderr, "judge stderr")?,
+            timed_out: judge_timed_out,
+            stopped_early: false,
+            duration: judge_duration,
+            max_rss_kb: judge_child.max_rss_kb(),
+        },
+    })
 }
 
-fn run_command(
-    command_argv: &[String],
+/// Runs a `[checker]` on one candidate answer.
+///
+/// The checker is spawned locally with the paths of three files appended to
+/// its argv: the generated case, the candidate's output and the expected
+/// output, in that order. The case is also piped to its stdin. Exit 0 accepts
+/// the answer.
+pub fn run_checker(
+    checker: &Program,
     input: &str,
+    output: &str,
+    answer: &str,
     config_dir: &Path,
     timeout_ms: u64,
-    limits: &Limits,
-    mode: RunMode,
+    settings: &RunSettings,
 ) -> Result<RunOutput> {
+    if checker.image.is_some() {
+        bail!("checker must run locally, image is not supported");
+    }
+
+    let dir = tempfile::Builder::new()
+        .prefix("nado-checker-")
+        .tempdir()
+        .context("failed to create checker directory")?;
+    let mut launch = resolve_launch(checker, config_dir, settings)?;
+    for (name, content) in [("input", input), ("output", output), ("answer", answer)] {
+        let path = dir.path().join(name);
+        fs::write(&path, content)
+            .with_context(|| format!("failed to write checker {name} file"))?;
+        launch.argv.push(path.to_string_lossy().to_string());
+    }
+
+    run_command(&launch, input, timeout_ms, settings, None)
+}
+
+/// Makes sure `image` is available locally before any case runs, pulling it
+/// with exponential backoff so a flaky registry fails once, up front, instead
+/// of as per-case runner errors.
+pub fn ensure_image(image: &str, retries: u32, runtime: ContainerRuntime) -> Result<()> {
+    if image_present(image, runtime)? {
+        return Ok(());
+    }
+
+    let mut backoff = Duration::from_secs(1);
+    let mut last_error = String::new();
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            eprintln!(
+                "retrying pull of {image} in {}s ({attempt}/{retries})",
+                backoff.as_secs()
+            );
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+
+        let pulled = Command::new(runtime.binary())
+            .args(["pull", "--quiet", image])
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|error| runtime_error(runtime, error))?;
+        if pulled.status.success() {
+            return Ok(());
+        }
+        last_error = String::from_utf8_lossy(&pulled.stderr).trim().to_string();
+    }
+
+    bail!(
+        "failed to pull image {image} after {} attempt(s): {last_error}",
+        retries + 1
+    );
+}
+
+/// Whether `image` is already available locally.
+pub fn image_present(image: &str, runtime: ContainerRuntime) -> Result<bool> {
+    let inspected = Command::new(runtime.binary())
+        .args(["image", "inspect", image])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|error| runtime_error(runtime, error))?;
+    Ok(inspected.success())
+}
+
+/// Fails unless the Docker daemon answers, or Podman, which needs no
+/// daemon, can run at all.
+pub fn check_runtime(runtime: ContainerRuntime) -> Result<()> {
+    let args: &[&str] = match runtime {
+        ContainerRuntime::Docker => &["version", "--format", "{{.Server.Version}}"],
+        ContainerRuntime::Podman => &["info"],
+    };
+    let output = Command::new(runtime.binary())
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|error| runtime_error(runtime, error))?;
+    if !output.status.success() {
+        bail!(
+            "{} is not usable: {}",
+            runtime.binary(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn runtime_error(runtime: ContainerRuntime, error: std::io::Error) -> anyhow::Error {
+    let binary = runtime.binary();
+    if error.kind() == ErrorKind::NotFound {
+        anyhow::anyhow!(
+            "{binary} was not found on PATH; install it or change engine.container_runtime"
+        )
+    } else {
+        anyhow::Error::new(error).context(format!("failed to run {binary}"))
+    }
+}
+
+/// Renders an exit status, decoding the terminating signal on unix.
+pub fn describe_status(status: &ExitStatus) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            let core = if status.core_dumped() {
+                ", core dumped"
+            } else {
+                ""
+            };
+            return match signal_name(signal) {
+                Some((name, hint)) => format!("killed by signal {signal} ({name}: {hint}{core})"),
+                None => format!("killed by signal {signal}{core}"),
+            };
+        }
+    }
+
+    status.to_string()
+}
+
+/// Exit code of `status`; on Unix a death by signal N reads as `128 + N`,
+/// the code a shell reports for it.
+pub fn exit_code(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        status
+            .code()
+            .or_else(|| status.signal().map(|signal| 128 + signal))
+    }
+    #[cfg(not(unix))]
+    {
+        status.code()
+    }
+}
+
+/// A status whose [`exit_code`] is `code`.
+pub fn exit_status(code: i32) -> ExitStatus {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        ExitStatus::from_raw((code & 0xff) << 8)
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::ExitStatusExt;
+
+        ExitStatus::from_raw(code as u32)
+    }
+}
+
+/// Whether the program was stopped for using up `limits.cpu_seconds`.
+pub fn cpu_limit_exceeded(status: &ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        status.signal() == Some(libc::SIGXCPU)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        false
+    }
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<(&'static str, &'static str)> {
+    let named = match signal {
+        libc::SIGABRT => ("SIGABRT", "aborted, e.g. failed assertion or panic"),
+        libc::SIGBUS => ("SIGBUS", "bus error"),
+        libc::SIGFPE => ("SIGFPE", "arithmetic error, e.g. division by zero"),
+        libc::SIGILL => ("SIGILL", "illegal instruction"),
+        libc::SIGKILL => ("SIGKILL", "killed, e.g. by timeout or OOM killer"),
+        libc::SIGPIPE => ("SIGPIPE", "wrote to a closed pipe"),
+        libc::SIGSEGV => ("SIGSEGV", "segmentation fault"),
+        libc::SIGTERM => ("SIGTERM", "terminated"),
+        libc::SIGXCPU => ("SIGXCPU", "CPU time limit exceeded"),
+        libc::SIGXFSZ => ("SIGXFSZ", "file size limit exceeded"),
+        _ => return None,
+    };
+    Some(named)
+}
+
+fn resolve_launch(program: &Program, config_dir: &Path, settings: &RunSettings) -> Result<Launch> {
+    let mounts = parse_mounts(&program.mounts)?;
+    let dir = program_dir(program, config_dir);
+    let cpu = worker_cpu(settings);
+
+    if program.cmd.is_empty() && program.image.is_none() {
+        bail!("program cmd is empty");
+    }
+    let cmd = program
+        .wrapper
+        .iter()
+        .flatten()
+        .chain(&program.cmd)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if let Some(image) = &program.image {
+        let mut docker_cmd = build_docker_cmd(image, &cmd, &mounts, &program.env, &dir, settings)?;
+        if let Some(cpu) = cpu {
+            // Right after `docker run`, ahead of the image name.
+            docker_cmd.splice(2..2, ["--cpuset-cpus".to_string(), cpu.to_string()]);
+        }
+        return Ok(Launch {
+            argv: docker_cmd,
+            mode: RunMode::Docker,
+            dir,
+            env: BTreeMap::new(),
+            cpu: None,
+        });
+    }
+
+    let local_cmd = resolve_local_cmd(&cmd, &mounts, &dir)?;
+    Ok(Launch {
+        argv: local_cmd,
+        mode: RunMode::Local,
+        dir,
+        env: program.env.clone(),
+        cpu,
+    })
+}
+
+/// Renders the command line nado will spawn for `program` as a shell
+/// snippet — the full `docker run ...` for images, otherwise the
+/// mount-substituted argv prefixed with its directory and extra environment.
+pub fn describe_command(
+    program: &Program,
+    config_dir: &Path,
+    settings: &RunSettings,
+) -> Result<String> {
+    let launch = resolve_launch(program, config_dir, settings)?;
+    let mut parts = Vec::new();
+    if matches!(launch.mode, RunMode::Local) {
+        parts.push(format!(
+            "cd {} &&",
+            shell_quote(&launch.dir.to_string_lossy())
+        ));
+        for (key, value) in &launch.env {
+            parts.push(format!("{key}={}", shell_quote(value)));
+        }
+    }
+    parts.extend(launch.argv.iter().map(|arg| shell_quote(arg)));
+    Ok(parts.join(" "))
+}
+
+/// Regular files `program` runs from: arguments of a local command that name
+/// a file in its directory, or the host side of a Docker program's mounts.
+pub fn program_files(
+    program: &Program,
+    config_dir: &Path,
+    settings: &RunSettings,
+) -> Result<Vec<PathBuf>> {
+    let launch = resolve_launch(program, config_dir, settings)?;
+    let paths = match launch.mode {
+        RunMode::Local => launch.argv.iter().map(|arg| launch.dir.join(arg)).collect(),
+        RunMode::Docker => parse_mounts(&program.mounts)?
+            .iter()
+            .map(|mount| host_path_from_mount(&mount.host, &launch.dir))
+            .collect::<Vec<_>>(),
+    };
+    Ok(paths.into_iter().filter(|path| path.is_file()).collect())
+}
+
+/// The argv a local `program` is spawned with (wrapper and mounts applied)
+/// and the directory it runs in, or `None` for a Docker program.
+pub fn local_argv(
+    program: &Program,
+    config_dir: &Path,
+    settings: &RunSettings,
+) -> Result<Option<(Vec<String>, PathBuf)>> {
+    let launch = resolve_launch(program, config_dir, settings)?;
+    Ok(match launch.mode {
+        RunMode::Local => Some((launch.argv, launch.dir)),
+        RunMode::Docker => None,
+    })
+}
+
+/// Host side of every mount of `program`, resolved like the runner does.
+pub fn mount_host_paths(program: &Program, config_dir: &Path) -> Result<Vec<PathBuf>> {
+    let dir = program_dir(program, config_dir);
+    Ok(parse_mounts(&program.mounts)?
+        .iter()
+        .map(|mount| host_path_from_mount(&mount.host, &dir))
+        .collect())
+}
+
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Directory a program runs in and resolves its mounts against: its `workdir`
+/// relative to the config dir, or the config dir itself.
+pub fn program_dir(program: &Program, config_dir: &Path) -> PathBuf {
+    let Some(workdir) = &program.workdir else {
+        return config_dir.to_path_buf();
+    };
+
+    let dir = config_dir.join(workdir);
+    fs::canonicalize(&dir).unwrap_or(dir)
+}
+
+fn spawn_command(launch: &Launch, settings: &RunSettings) -> Result<Process> {
+    let limits = settings.limits;
+    let command_argv = &launch.argv;
     if command_argv.is_empty() {
         bail!("empty command");
     }
 
+    stagger_spawn(settings.spawn_stagger_ms);
+    if aborted() {
+        bail!("run aborted: hard deadline reached");
+    }
+
     let mut command = Command::new(&command_argv[0]);
     command
         .args(&command_argv[1..])
-        .current_dir(config_dir)
+        .current_dir(&launch.dir)
+        .envs(&launch.env)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
     #[cfg(unix)]
-    if matches!(mode, RunMode::Local) {
+    if matches!(launch.mode, RunMode::Local) {
         let limits = limits.clone();
+        let cpu = launch.cpu;
         unsafe {
-            command.pre_exec(move || apply_limits(&limits));
+            command.pre_exec(move || {
+                apply_limits(&limits)?;
+                if let Some(cpu) = cpu {
+                    pin_to_cpu(cpu);
+                }
+                Ok(())
+            });
         }
     }
-
-    let mut child = command
+    let child = command
         .spawn()
         .with_context(|| format!("failed to spawn command: {}", command_argv.join(" ")))?;
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin
-            .write_all(input.as_bytes())
-            .context("failed to write stdin")?;
+    let local = matches!(launch.mode, RunMode::Local);
+    #[cfg(windows)]
+    {
+        let mut process = Process {
+            child,
+            local,
+            job: None,
+        };
+        if local {
+            match JobObject::for_child(&process.child, limits) {
+                Ok(job) => process.job = Some(job),
+                // Without limits the job only helps clean up grandchildren,
+                // which is not worth failing the run over.
+                Err(_) if !has_job_limits(limits) => {}
+                Err(error) => {
+                    let _ = process.kill();
+                    let _ = process.wait();
+                    return Err(error).context("failed to apply [limits] via a job object");
+                }
+            }
+        }
+        Ok(process)
+    }
+    #[cfg(not(windows))]
+    {
+        #[cfg(not(unix))]
+        let _ = limits;
+        Ok(Process {
+            child,
+            local,
+            #[cfg(unix)]
+            reaped: None,
+        })
     }
+}
 
-    let stdout = child.stdout.take().context("failed to capture stdout")?;
-    let stderr = child.stderr.take().context("failed to capture stderr")?;
+/// A spawned program. On Windows a local program runs in a Job Object that
+/// carries its `[limits]`, and killing it terminates the whole job, so
+/// processes it started do not outlive it. On Unix it is reaped with
+/// `wait4`, which also reports its peak memory.
+struct Process {
+    child: Child,
+    /// Run directly rather than through a container runtime, whose client
+    /// process says nothing about the program's memory.
+    local: bool,
+    /// Exit status and peak RSS in KiB, once reaped.
+    #[cfg(unix)]
+    reaped: Option<(ExitStatus, u64)>,
+    #[cfg(windows)]
+    job: Option<JobObject>,
+}
 
-    let stdout_handle = thread::spawn(move || {
-        let mut buf = Vec::new();
-        let _ = std::io::BufReader::new(stdout).read_to_end(&mut buf);
-        buf
-    });
+impl Process {
+    fn kill(&mut self) -> std::io::Result<()> {
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            job.terminate();
+        }
+        // Once reaped, the pid may already belong to another process.
+        #[cfg(unix)]
+        if self.reaped.is_some() {
+            return Ok(());
+        }
+        self.child.kill()
+    }
 
-    let stderr_handle = thread::spawn(move || {
-        let mut buf = Vec::new();
-        let _ = std::io::BufReader::new(stderr).read_to_end(&mut buf);
-        buf
+    /// Peak resident set size in KiB, once a local program has been reaped.
+    fn max_rss_kb(&self) -> Option<u64> {
+        #[cfg(unix)]
+        return self.reaped.filter(|_| self.local).map(|(_, rss)| rss);
+        #[cfg(not(unix))]
+        {
+            let _ = self.local;
+            None
+        }
+    }
+
+    fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.child.stdin.take()
+    }
+
+    fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.child.stdout.take()
+    }
+
+    fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.child.stderr.take()
+    }
+
+    /// Waits up to `timeout` for the program to exit.
+    ///
+    /// Unix polls `wait4` (see [`Self::reap`]) with pauses that double from
+    /// 50µs up to 2ms: a quick program is reaped within microseconds, while a
+    /// long one costs at most 500 cheap syscalls a second and is noticed up
+    /// to 2ms late, which is all the error its measured time gets.
+    fn wait_timeout(&mut self, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+        #[cfg(unix)]
+        {
+            let deadline = Instant::now() + timeout;
+            let mut pause = Duration::from_micros(50);
+            loop {
+                if let Some(status) = self.try_wait()? {
+                    return Ok(Some(status));
+                }
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Ok(None);
+                }
+                thread::sleep(pause.min(remaining));
+                pause = (pause * 2).min(Duration::from_millis(2));
+            }
+        }
+        #[cfg(not(unix))]
+        self.child.wait_timeout(timeout)
+    }
+
+    fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        #[cfg(unix)]
+        return self
+            .reap(0)?
+            .ok_or_else(|| std::io::Error::other("wait4 returned without a status"));
+        #[cfg(not(unix))]
+        self.child.wait()
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        #[cfg(unix)]
+        return self.reap(libc::WNOHANG);
+        #[cfg(not(unix))]
+        self.child.try_wait()
+    }
+
+    /// Reaps the program with `wait4` rather than `std`'s `waitpid`, which
+    /// drops the resource usage that carries its peak memory.
+    #[cfg(unix)]
+    fn reap(&mut self, flags: libc::c_int) -> std::io::Result<Option<ExitStatus>> {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some((status, _)) = self.reaped {
+            return Ok(Some(status));
+        }
+        let pid = libc::pid_t::try_from(self.child.id()).map_err(std::io::Error::other)?;
+        let mut raw = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            match unsafe { libc::wait4(pid, &mut raw, flags, &mut usage) } {
+                0 => return Ok(None),
+                -1 => {
+                    let error = std::io::Error::last_os_error();
+                    if error.kind() != ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        // Linux reports ru_maxrss in KiB, macOS in bytes.
+        let rss = u64::try_from(usage.ru_maxrss).unwrap_or(0);
+        #[cfg(target_os = "macos")]
+        let rss = rss / 1024;
+        let status = ExitStatus::from_raw(raw);
+        self.reaped = Some((status, rss));
+        Ok(Some(status))
+    }
+}
+
+/// Waits for the next free spawn slot so spawns from all workers are at
+/// least `stagger_ms` apart. Only the spawn is paced; running programs and
+/// the other workers' comparisons carry on meanwhile.
+fn stagger_spawn(stagger_ms: u64) {
+    static NEXT_SLOT: Mutex<Option<Instant>> = Mutex::new(None);
+    if stagger_ms == 0 {
+        return;
+    }
+
+    let slot = {
+        let mut next = NEXT_SLOT.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let slot = next.map_or(now, |next| next.max(now));
+        *next = Some(slot + Duration::from_millis(stagger_ms));
+        slot
+    };
+    thread::sleep(slot.saturating_duration_since(Instant::now()));
+}
+
+fn run_command(
+    launch: &Launch,
+    input: &str,
+    timeout_ms: u64,
+    settings: &RunSettings,
+    check: Option<LineCheck>,
+) -> Result<RunOutput> {
+    let mut child = spawn_command(launch, settings)?;
+
+    // Feed stdin from its own thread so a program that fills its stdout pipe
+    // before draining stdin cannot deadlock against us.
+    let stdin_handle = child.take_stdin().map(|mut stdin| {
+        let input = input.as_bytes().to_vec();
+        thread::spawn(move || stdin.write_all(&input))
     });
 
-    let duration = Duration::from_millis(timeout_ms);
-    let mut timed_out = false;
+    let stdout = child.take_stdout().context("failed to capture stdout")?;
+    let stderr = child.take_stderr().context("failed to capture stderr")?;
 
-    let status = match child.wait_timeout(duration)? {
-        Some(status) => status,
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(timeout_ms);
+    let stderr_handle = capture(stderr);
+    let (stdout_handle, status, timed_out, stopped_early) = match check {
         None => {
-            timed_out = true;
-            let _ = child.kill();
-            child.wait().context("failed waiting killed process")?
+            let stdout_handle = capture(stdout);
+            let (status, timed_out) = wait_until(&mut child, deadline)?;
+            (stdout_handle, status, timed_out, false)
+        }
+        Some(check) => {
+            let rejected = Arc::new(AtomicBool::new(false));
+            let stdout_handle = capture_checked(stdout, check, Arc::clone(&rejected));
+            let (status, timed_out) = wait_until_rejected(&mut child, deadline, &rejected)?;
+            let stopped_early = rejected.load(Ordering::SeqCst) && !timed_out;
+            (stdout_handle, status, timed_out, stopped_early)
         }
     };
+    let duration = started.elapsed();
 
-    let stdout = stdout_handle
-        .join()
-        .map_err(|_| anyhow::anyhow!("stdout thread panicked"))?;
-    let stderr = stderr_handle
-        .join()
-        .map_err(|_| anyhow::anyhow!("stderr thread panicked"))?;
+    if let Some(handle) = stdin_handle {
+        let written = handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("stdin thread panicked"))?;
+        // Killing the program closes its stdin, so a failed write is expected then.
+        if !timed_out && !stopped_early {
+            match written {
+                // Exiting once the answer is known, without reading the rest
+                // of the input, is legitimate; its output is judged as usual.
+                Err(error) if error.kind() == ErrorKind::BrokenPipe => {
+                    if settings.strict_stdin {
+                        bail!(
+                            "program exited without reading all of its input (engine.strict_stdin)"
+                        );
+                    }
+                }
+                written => written.context("failed to write stdin")?,
+            }
+        }
+    }
 
     Ok(RunOutput {
         status,
-        stdout: String::from_utf8_lossy(&stdout).to_string(),
-        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        stdout: join_output(stdout_handle, "stdout")?,
+        stderr: join_output(stderr_handle, "stderr")?,
         timed_out,
+        stopped_early,
+        duration,
+        max_rss_kb: child.max_rss_kb(),
     })
 }
 
+/// Runs `launch` with `transport = "socket"`: nado listens on a fresh Unix
+/// socket, passes its path as `NADO_SOCKET` and in place of `{socket}` in the
+/// argv, sends `input` over the first connection and reads the answer until
+/// the program closes it. The answer becomes `stdout`; the program's own
+/// stdout is discarded. A program still running after answering is stopped,
+/// as service-style programs never exit on their own.
+#[cfg(unix)]
+fn run_socket(
+    launch: &Launch,
+    input: &str,
+    timeout_ms: u64,
+    settings: &RunSettings,
+) -> Result<RunOutput> {
+    use std::os::unix::net::UnixListener;
+
+    let socket_dir = tempfile::Builder::new()
+        .prefix("nado-socket-")
+        .tempdir()
+        .context("failed to create socket dir")?;
+    let socket_path = socket_dir.path().join("case.sock");
+    let listener = UnixListener::bind(&socket_path)
+        .with_context(|| format!("failed to listen on {}", socket_path.display()))?;
+    listener
+        .set_nonblocking(true)
+        .context("failed to configure socket")?;
+
+    let socket = socket_path.to_string_lossy().to_string();
+    let mut env = launch.env.clone();
+    env.insert(SOCKET_ENV.to_string(), socket.clone());
+    let launch = Launch {
+        argv: launch
+            .argv
+            .iter()
+            .map(|arg| arg.replace(SOCKET_PLACEHOLDER, &socket))
+            .collect(),
+        mode: RunMode::Local,
+        dir: launch.dir.clone(),
+        env,
+        cpu: launch.cpu,
+    };
+
+    let mut child = spawn_command(&launch, settings)?;
+    drop(child.take_stdin());
+    let stdout_handle = capture(child.take_stdout().context("failed to capture stdout")?);
+    let stderr_handle = capture(child.take_stderr().context("failed to capture stderr")?);
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(timeout_ms);
+
+    let (answer, mut timed_out, connected) =
+        match exchange_over_socket(&listener, &mut child, input, deadline, settings) {
+            Ok(exchanged) => exchanged,
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = stdout_handle.join();
+                let _ = stderr_handle.join();
+                return Err(error);
+            }
+        };
+
+    let status = match child.try_wait().context("failed to poll program")? {
+        Some(status) => status,
+        None if timed_out || !connected => {
+            let (status, timed) = wait_until(&mut child, deadline)?;
+            timed_out |= timed;
+            status
+        }
+        None => {
+            // Answered: a service that keeps running has done its job.
+            let _ = child.kill();
+            child.wait().context("failed to wait for program")?;
+            settings.clean_exit
+        }
+    };
+    let duration = started.elapsed();
+    join_output(stdout_handle, "stdout")?;
+    Ok(RunOutput {
+        status,
+        stdout: String::from_utf8_lossy(&answer).into_owned(),
+        stderr: join_output(stderr_handle, "stderr")?,
+        timed_out,
+        stopped_early: false,
+        duration,
+        max_rss_kb: child.max_rss_kb(),
+    })
+}
+
+/// Waits for the program to connect, sends it the case and reads its answer
+/// until it closes the connection or `deadline` passes. Returns the answer,
+/// whether the deadline passed, and whether the program connected at all.
+#[cfg(unix)]
+fn exchange_over_socket(
+    listener: &std::os::unix::net::UnixListener,
+    child: &mut Process,
+    input: &str,
+    deadline: Instant,
+    settings: &RunSettings,
+) -> Result<(Vec<u8>, bool, bool)> {
+    const POLL: Duration = Duration::from_millis(10);
+
+    // Wait for the program to connect, exit, or run out of time.
+    let mut stream = loop {
+        match listener.accept() {
+            Ok((stream, _)) => break stream,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+            Err(error) => return Err(error).context("failed to accept socket connection"),
+        }
+        if child
+            .try_wait()
+            .context("failed to poll program")?
+            .is_some()
+            || Instant::now() >= deadline
+            || aborted()
+        {
+            return Ok((Vec::new(), false, false));
+        }
+        thread::sleep(POLL);
+    };
+
+    stream
+        .set_nonblocking(false)
+        .context("failed to configure socket")?;
+    let mut writer = stream.try_clone().context("failed to clone socket")?;
+    let input = input.as_bytes().to_vec();
+    // Written from its own thread, like stdin, so a program that answers
+    // before reading everything cannot deadlock against us.
+    let writer_handle = thread::spawn(move || {
+        let written = writer.write_all(&input);
+        let _ = writer.shutdown(std::net::Shutdown::Write);
+        written
+    });
+
+    let mut answer = Vec::new();
+    let mut timed_out = false;
+    let mut chunk = [0u8; 8192];
+    let read = loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || aborted() {
+            timed_out = true;
+            break Ok(());
+        }
+        if let Err(error) = stream.set_read_timeout(Some(remaining.min(POLL * 10))) {
+            break Err(error).context("failed to configure socket");
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => break Ok(()),
+            Ok(read) => answer.extend_from_slice(&chunk[..read]),
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(error) => break Err(error).context("failed to read socket answer"),
+        }
+    };
+    if timed_out || read.is_err() {
+        // Unblocks the writer thread if the program never reads.
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    let written = writer_handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("socket writer thread panicked"))?;
+    read?;
+    if !timed_out {
+        match written {
+            Err(error) if error.kind() == ErrorKind::BrokenPipe => {
+                if settings.strict_stdin {
+                    bail!("program closed the socket without reading all of its input (engine.strict_stdin)");
+                }
+            }
+            written => written.context("failed to write to socket")?,
+        }
+    }
+    Ok((answer, timed_out, true))
+}
+
+#[cfg(not(unix))]
+fn run_socket(
+    _launch: &Launch,
+    _input: &str,
+    _timeout_ms: u64,
+    _settings: &RunSettings,
+) -> Result<RunOutput> {
+    bail!("transport = \"socket\" needs Unix domain sockets, which this platform lacks");
+}
+
+/// [`wait_until`] that also kills the child once `rejected` is raised.
+fn wait_until_rejected(
+    child: &mut Process,
+    deadline: Instant,
+    rejected: &AtomicBool,
+) -> Result<(ExitStatus, bool)> {
+    const POLL: Duration = Duration::from_millis(10);
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || aborted() {
+            return wait_until(child, deadline);
+        }
+        if let Some(status) = child.wait_timeout(remaining.min(POLL))? {
+            return Ok((status, false));
+        }
+        if rejected.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let status = child.wait().context("failed waiting killed process")?;
+            return Ok((status, false));
+        }
+    }
+}
+
+fn wait_until(child: &mut Process, deadline: Instant) -> Result<(ExitStatus, bool)> {
+    const POLL: Duration = Duration::from_millis(10);
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            let _ = child.kill();
+            let status = child.wait().context("failed waiting killed process")?;
+            return Ok((status, true));
+        }
+        if let Some(status) = child.wait_timeout(remaining.min(POLL))? {
+            return Ok((status, false));
+        }
+        if aborted() {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("run aborted: hard deadline reached");
+        }
+    }
+}
+
+fn capture<R: Read + Send + 'static>(reader: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = BufReader::new(reader).read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// Reads `reader` line by line through `check`, raising `rejected` and
+/// stopping at the first rejected line.
+fn capture_checked<R: Read + Send + 'static>(
+    reader: R,
+    mut check: LineCheck,
+    rejected: Arc<AtomicBool>,
+) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            let start = buf.len();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let Some(line) = buf[start..].strip_suffix(b"\n") else {
+                break;
+            };
+            if !check(&String::from_utf8_lossy(line)) {
+                rejected.store(true, Ordering::SeqCst);
+                break;
+            }
+        }
+        buf
+    })
+}
+
+/// Copies `reader` into `writer` chunk by chunk, returning everything read.
+/// Once the writer is gone the rest of the stream is still drained and recorded.
+fn relay<R: Read, W: Write>(mut reader: R, writer: W) -> Vec<u8> {
+    let mut writer = Some(writer);
+    let mut transcript = Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        transcript.extend_from_slice(&chunk[..read]);
+
+        if let Some(out) = writer.as_mut() {
+            if out
+                .write_all(&chunk[..read])
+                .and_then(|_| out.flush())
+                .is_err()
+            {
+                writer = None;
+            }
+        }
+    }
+
+    transcript
+}
+
+fn join_output(handle: JoinHandle<Vec<u8>>, name: &str) -> Result<String> {
+    let buf = handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("{name} thread panicked"))?;
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
 fn build_docker_cmd(
     image: &str,
     cmd: &[String],
     mounts: &[MountSpec],
+    env: &BTreeMap<String, String>,
     config_dir: &Path,
-    limits: &Limits,
+    settings: &RunSettings,
 ) -> Result<Vec<String>> {
     let mut argv = vec![
-        "docker".to_string(),
+        settings.container_runtime.binary().to_string(),
         "run".to_string(),
         "--rm".to_string(),
         "-i".to_string(),
@@ -165,16 +1305,26 @@ fn build_docker_cmd(
         "none".to_string(),
     ];
 
-    if let Some(memory_mb) = limits.memory_mb {
+    if let Some(memory_mb) = settings.limits.memory_mb {
         argv.push("--memory".to_string());
         argv.push(format!("{memory_mb}m"));
     }
 
-    if let Some(nproc) = limits.nproc {
+    if let Some(nproc) = settings.limits.nproc {
         argv.push("--pids-limit".to_string());
         argv.push(nproc.to_string());
     }
 
+    if let Some(nice) = settings.limits.nice {
+        argv.push("--cpu-shares".to_string());
+        argv.push(cpu_shares_for_nice(nice).to_string());
+    }
+
+    for (key, value) in env {
+        argv.push("-e".to_string());
+        argv.push(format!("{key}={value}"));
+    }
+
     for mount in mounts {
         argv.push("-v".to_string());
         argv.push(render_docker_mount(mount, config_dir)?);
@@ -186,6 +1336,13 @@ fn build_docker_cmd(
     Ok(argv)
 }
 
+/// Maps a niceness to the CFS weight the kernel gives it (1024 at nice 0,
+/// roughly 1.25x per step), which is what `--cpu-shares` sets for a container.
+fn cpu_shares_for_nice(nice: i32) -> u64 {
+    let nice = nice.clamp(-20, 19);
+    (1024.0 / 1.25f64.powi(nice)).round().max(2.0) as u64
+}
+
 fn render_docker_mount(mount: &MountSpec, config_dir: &Path) -> Result<String> {
     let host_path = host_path_from_mount(&mount.host, config_dir);
     let host_path = fs::canonicalize(&host_path).unwrap_or(host_path);
@@ -269,10 +1426,93 @@ fn parse_mounts(mounts: &[String]) -> Result<Vec<MountSpec>> {
     Ok(parsed)
 }
 
+/// Configured `[limits]` this process cannot apply as given, one message each.
+/// An unprivileged process cannot raise a hard limit or its own priority.
+#[cfg(unix)]
+pub fn unapplicable_limits(limits: &Limits) -> Vec<String> {
+    let mut problems = Vec::new();
+    if unsafe { libc::geteuid() } == 0 {
+        return problems;
+    }
+
+    let requested = [
+        ("cpu_seconds", libc::RLIMIT_CPU, limits.cpu_seconds, 1),
+        ("memory_mb", libc::RLIMIT_AS, limits.memory_mb, 1024 * 1024),
+        (
+            "file_size_kb",
+            libc::RLIMIT_FSIZE,
+            limits.file_size_kb,
+            1024,
+        ),
+        ("nofile", libc::RLIMIT_NOFILE, limits.nofile, 1),
+        ("nproc", libc::RLIMIT_NPROC, limits.nproc, 1),
+    ];
+    for (name, resource, value, unit) in requested {
+        let Some(value) = value else { continue };
+        let mut current = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+            problems.push(format!("limits.{name}: not supported on this platform"));
+            continue;
+        }
+        let hard = current.rlim_max;
+        if hard != libc::RLIM_INFINITY && value.saturating_mul(unit) as libc::rlim_t > hard {
+            problems.push(format!(
+                "limits.{name} = {value} is above the hard limit ({}); programs will fail to spawn",
+                hard / unit as libc::rlim_t
+            ));
+        }
+    }
+    if let Some(nice) = limits.nice.filter(|&nice| nice < 0) {
+        problems.push(format!(
+            "limits.nice = {nice} needs privileges to raise priority; it is ignored"
+        ));
+    }
+    problems
+}
+
+#[cfg(windows)]
+pub fn unapplicable_limits(limits: &Limits) -> Vec<String> {
+    let ignored = [
+        ("file_size_kb", limits.file_size_kb.is_some()),
+        ("nofile", limits.nofile.is_some()),
+        ("nice", limits.nice.is_some()),
+    ];
+    ignored
+        .iter()
+        .filter(|(_, set)| *set)
+        .map(|(name, _)| format!("limits.{name}: not supported on Windows; it is ignored"))
+        .collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn unapplicable_limits(limits: &Limits) -> Vec<String> {
+    let set = [
+        limits.cpu_seconds.is_some(),
+        limits.memory_mb.is_some(),
+        limits.file_size_kb.is_some(),
+        limits.nofile.is_some(),
+        limits.nproc.is_some(),
+        limits.nice.is_some(),
+    ];
+    if set.contains(&true) {
+        vec!["[limits] are not applied on this platform".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
 #[cfg(unix)]
 fn apply_limits(limits: &Limits) -> std::io::Result<()> {
     if let Some(cpu_seconds) = limits.cpu_seconds {
-        set_rlimit(libc::RLIMIT_CPU, cpu_seconds as libc::rlim_t)?;
+        // The kernel sends SIGXCPU at the soft limit but SIGKILL at the hard
+        // one; a second of headroom makes the overrun recognizable. Without
+        // room under the inherited hard limit, the plain limit still applies.
+        let soft = cpu_seconds as libc::rlim_t;
+        set_rlimit_range(libc::RLIMIT_CPU, soft, soft.saturating_add(1))
+            .or_else(|_| set_rlimit(libc::RLIMIT_CPU, soft))?;
     }
     if let Some(memory_mb) = limits.memory_mb {
         let bytes = (memory_mb * 1024 * 1024) as libc::rlim_t;
@@ -288,18 +1528,167 @@ fn apply_limits(limits: &Limits) -> std::io::Result<()> {
     if let Some(nproc) = limits.nproc {
         set_rlimit(libc::RLIMIT_NPROC, nproc as libc::rlim_t)?;
     }
+    if let Some(nice) = limits.nice {
+        // Best effort: lowering priority always works, raising it needs
+        // privileges, and neither is worth failing the run over.
+        unsafe {
+            libc::setpriority(libc::PRIO_PROCESS as _, 0, nice);
+        }
+    }
 
     Ok(())
 }
 
+/// Whether `limits` sets anything a Job Object enforces.
+#[cfg(windows)]
+fn has_job_limits(limits: &Limits) -> bool {
+    limits.cpu_seconds.is_some() || limits.memory_mb.is_some() || limits.nproc.is_some()
+}
+
+/// Owned handle to a Job Object. Closing it kills whatever still runs in it.
+#[cfg(windows)]
+struct JobObject(windows_sys::Win32::Foundation::HANDLE);
+
+// A job handle may be used and closed from any thread.
+#[cfg(windows)]
+unsafe impl Send for JobObject {}
+#[cfg(windows)]
+unsafe impl Sync for JobObject {}
+
+#[cfg(windows)]
+impl JobObject {
+    /// Puts `child` in a new job limited by `limits`: `memory_mb` per
+    /// process, `nproc` active processes and `cpu_seconds` of user time per
+    /// process. `file_size_kb`, `nofile` and `nice` have no job equivalent
+    /// and are ignored. The child runs unconfined for the moment between
+    /// spawning and joining the job.
+    fn for_child(child: &Child, limits: &Limits) -> std::io::Result<Self> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_ACTIVE_PROCESS, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+            JOB_OBJECT_LIMIT_PROCESS_MEMORY, JOB_OBJECT_LIMIT_PROCESS_TIME,
+        };
+
+        let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+        let job = JobObject(handle);
+
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        let basic = &mut info.BasicLimitInformation;
+        basic.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        if let Some(cpu_seconds) = limits.cpu_seconds {
+            // In 100ns units.
+            basic.PerProcessUserTimeLimit = (cpu_seconds as i64).saturating_mul(10_000_000);
+            basic.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_TIME;
+        }
+        if let Some(nproc) = limits.nproc {
+            basic.ActiveProcessLimit = nproc.min(u32::MAX as u64) as u32;
+            basic.LimitFlags |= JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+        }
+        if let Some(memory_mb) = limits.memory_mb {
+            info.ProcessMemoryLimit = (memory_mb as usize).saturating_mul(1024 * 1024);
+            info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+        }
+
+        let set = unsafe {
+            SetInformationJobObject(
+                job.0,
+                JobObjectExtendedLimitInformation,
+                &info as *const _ as *const std::ffi::c_void,
+                std::mem::size_of_val(&info) as u32,
+            )
+        };
+        if set == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle() as _) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(job)
+    }
+
+    fn terminate(&self) {
+        // Best effort: the process may already be gone.
+        unsafe {
+            windows_sys::Win32::System::JobObjects::TerminateJobObject(self.0, 1);
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for JobObject {
+    fn drop(&mut self) {
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+/// CPU for the calling worker thread when `engine.pin_cpus` is set: its
+/// worker index wrapped onto the CPUs this process may run on.
+#[cfg(target_os = "linux")]
+fn worker_cpu(settings: &RunSettings) -> Option<usize> {
+    if !settings.pin_cpus {
+        return None;
+    }
+    let worker = rayon::current_thread_index()?;
+
+    let allowed = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return None;
+        }
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect::<Vec<_>>()
+    };
+    (!allowed.is_empty()).then(|| allowed[worker % allowed.len()])
+}
+
+#[cfg(not(target_os = "linux"))]
+fn worker_cpu(_settings: &RunSettings) -> Option<usize> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) {
+    // Best effort, like `nice`: an unpinned run is still a valid run.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn pin_to_cpu(_cpu: usize) {}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+#[cfg(unix)]
+fn set_rlimit(resource: RlimitResource, value: libc::rlim_t) -> std::io::Result<()> {
+    set_rlimit_range(resource, value, value)
+}
+
 #[cfg(unix)]
-fn set_rlimit(resource: libc::c_int, value: libc::rlim_t) -> std::io::Result<()> {
+fn set_rlimit_range(
+    resource: RlimitResource,
+    soft: libc::rlim_t,
+    hard: libc::rlim_t,
+) -> std::io::Result<()> {
     let lim = libc::rlimit {
-        rlim_cur: value,
-        rlim_max: value,
+        rlim_cur: soft,
+        rlim_max: hard,
     };
 
-    let code = unsafe { libc::setrlimit(resource as _, &lim) };
+    let code = unsafe { libc::setrlimit(resource, &lim) };
     if code == 0 {
         return Ok(());
     }
@@ -312,3 +1701,30 @@ fn set_rlimit(resource: libc::c_int, value: libc::rlim_t) -> std::io::Result<()>
 
     Err(err)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{run_program, RunSettings};
+    use crate::config::Config;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn local_programs_report_their_peak_memory() {
+        let config: Config =
+            toml::from_str("[problem]\n[[origin]]\ncmd = [\"sh\", \"-c\", \"cat; sleep 0.05\"]\n")
+                .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let output = run_program(
+            &config.origin[0],
+            "5\n",
+            dir.path(),
+            5000,
+            &RunSettings::new(&config),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, "5\n");
+        assert!(output.status.success());
+        assert!(output.max_rss_kb.is_some_and(|kb| kb > 0), "{output:?}");
+    }
+}
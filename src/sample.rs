@@ -0,0 +1,24 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::{self, Seed};
+use crate::engine::generate_cases;
+
+/// Seed of the preview case unless `--seed` says otherwise, so repeated
+/// previews show the same case.
+const SAMPLE_SEED: u64 = 1;
+
+/// Prints one randomly generated case exactly as programs receive it.
+pub fn run(config_path: &Path, seed: Option<Seed>) -> Result<i32> {
+    let (mut config, _) = config::load(config_path)?;
+    config.engine.seed = seed.unwrap_or(Seed::Fixed(SAMPLE_SEED));
+    // Edge cases come first and are mostly minimums; a random case shows the
+    // layout better.
+    config.pbt.enabled = false;
+    config.engine.cases = 1;
+
+    let (inputs, _) = generate_cases(&config)?;
+    print!("{}", inputs[0]);
+    Ok(0)
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Directory (relative to the config dir) where nado keeps state between runs.
+pub const STATE_DIR: &str = ".nado";
+const LAST_FAILURE_FILE: &str = "last-failure.toml";
+const PASSED_FILE: &str = "passed.toml";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailureRecord {
+    pub case_index: usize,
+    pub candidate_name: String,
+    pub reason: String,
+    pub input: String,
+}
+
+pub fn save_last_failure(config_dir: &Path, record: &FailureRecord) -> Result<()> {
+    let path = last_failure_path(config_dir);
+    let dir = config_dir.join(STATE_DIR);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create state dir: {}", dir.display()))?;
+
+    let raw = toml::to_string(record).context("failed to serialize failure record")?;
+    std::fs::write(&path, raw)
+        .with_context(|| format!("failed to write failure record: {}", path.display()))
+}
+
+pub fn load_last_failure(config_dir: &Path) -> Result<FailureRecord> {
+    let path = last_failure_path(config_dir);
+    let raw = std::fs::read_to_string(&path)
+        .with_context(|| format!("no recorded failure at {} (run nado first)", path.display()))?;
+    toml::from_str(&raw)
+        .with_context(|| format!("failed to parse failure record: {}", path.display()))
+}
+
+pub fn clear_last_failure(config_dir: &Path) -> Result<()> {
+    let path = last_failure_path(config_dir);
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error)
+            .with_context(|| format!("failed to remove failure record: {}", path.display())),
+    }
+}
+
+/// Fingerprints of the candidates that passed their latest run, by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PassedCandidates {
+    #[serde(default)]
+    pub candidates: BTreeMap<String, String>,
+}
+
+/// Loads the passed-candidate record; a missing file means nothing passed yet.
+pub fn load_passed(config_dir: &Path) -> Result<PassedCandidates> {
+    let path = config_dir.join(STATE_DIR).join(PASSED_FILE);
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(PassedCandidates::default())
+        }
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("failed to read pass record: {}", path.display()))
+        }
+    };
+    toml::from_str(&raw).with_context(|| format!("failed to parse pass record: {}", path.display()))
+}
+
+pub fn save_passed(config_dir: &Path, passed: &PassedCandidates) -> Result<()> {
+    let path = config_dir.join(STATE_DIR).join(PASSED_FILE);
+    let dir = config_dir.join(STATE_DIR);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create state dir: {}", dir.display()))?;
+
+    let raw = toml::to_string(passed).context("failed to serialize pass record")?;
+    std::fs::write(&path, raw)
+        .with_context(|| format!("failed to write pass record: {}", path.display()))
+}
+
+/// 64-bit FNV-1a, stable across runs and toolchains unlike `DefaultHasher`.
+#[derive(Debug, Clone, Copy)]
+pub struct Fingerprint(u64);
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fingerprint {
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // Separate fields so ("ab", "c") and ("a", "bc") differ.
+        self.0 = self.0.rotate_left(1) ^ bytes.len() as u64;
+    }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    pub fn hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+fn last_failure_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(STATE_DIR).join(LAST_FAILURE_FILE)
+}
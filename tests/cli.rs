@@ -0,0 +1,348 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn nado(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nado"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("run nado")
+}
+
+#[test]
+fn random_seed_note_stays_off_stdout() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("nado.toml"),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9\n\
+         [[origin]]\ncmd = [\"cat\"]\n[[candidate]]\ncmd = [\"cat\"]\n\
+         [engine]\ncases = 3\nseed = \"random\"\n",
+    )
+    .expect("write config");
+
+    let output = nado(dir.path(), &["nado.toml", "--format", "json"]);
+    let stdout = String::from_utf8(output.stdout).expect("utf-8");
+    let report: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is JSON");
+    assert_eq!(report["summary"]["verdict"], "PASS", "{stdout}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("using seed="));
+
+    let output = nado(dir.path(), &["sample", "--seed", "random"]);
+    let stdout = String::from_utf8(output.stdout).expect("utf-8");
+    let n = stdout.trim_end().parse::<u32>().expect("just the case");
+    assert!((1..=9).contains(&n), "{stdout}");
+}
+
+#[test]
+fn subcommands_reject_flags_they_cannot_use() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    write_config(dir.path(), "cmd = [\"cat\"]", "cases = 2");
+
+    for args in [
+        &["gen", "--junit", "junit.xml"][..],
+        &["gen", "--input", "5"],
+        &["export", "--to", "out", "--format", "json"],
+        &["export", "--to", "out", "--report", "report.json"],
+        &["run", ".", "--junit", "junit.xml"],
+        &["run", ".", "--only-case", "1"],
+    ] {
+        let output = nado(dir.path(), args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{args:?} was accepted");
+        assert!(stderr.contains("unexpected argument"), "{args:?}: {stderr}");
+    }
+    assert!(nado(dir.path(), &["gen", "--cases", "1"]).status.success());
+    assert!(nado(dir.path(), &["run", ".", "--repeat-run", "2"])
+        .status
+        .success());
+}
+
+#[test]
+fn candidate_passing_on_retry_passes_with_one_retry() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    // Wrong on its first run of the case, right from then on.
+    std::fs::write(
+        dir.path().join("nado.toml"),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9\n\
+         [[origin]]\ncmd = [\"cat\"]\n\
+         [[candidate]]\nname = \"flaky\"\n\
+         cmd = [\"sh\", \"-c\", \"if [ -e seen ]; then cat; else touch seen; echo 0; fi\"]\n\
+         [engine]\ncases = 1\nseed = 1\nretries = 2\n",
+    )
+    .expect("write config");
+
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("- flaky: PASS ("), "{stdout}");
+    assert!(stdout.contains(", 1 case(s) retried)"), "{stdout}");
+}
+
+#[test]
+fn retries_rerun_only_the_candidate_and_never_a_crash() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    // Each program appends a line to its own file every time it runs.
+    std::fs::write(
+        dir.path().join("nado.toml"),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9\n\
+         [[origin]]\ncmd = [\"sh\", \"-c\", \"echo run >> origin; cat\"]\n\
+         [[candidate]]\nname = \"wrong\"\n\
+         cmd = [\"sh\", \"-c\", \"echo run >> wrong; echo 0\"]\n\
+         [[candidate]]\nname = \"crash\"\n\
+         cmd = [\"sh\", \"-c\", \"echo run >> crash; exit 1\"]\n\
+         [engine]\ncases = 1\nseed = 1\nretries = 2\n",
+    )
+    .expect("write config");
+
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let runs = |name: &str| {
+        std::fs::read_to_string(dir.path().join(name))
+            .expect("program ran")
+            .lines()
+            .count()
+    };
+    assert_eq!(runs("origin"), 1, "{stdout}");
+    assert_eq!(runs("wrong"), 3, "{stdout}");
+    assert_eq!(runs("crash"), 1, "{stdout}");
+}
+
+#[test]
+fn success_exit_codes_without_zero_keep_formula_origins_clean() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("nado.toml"),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9\n\
+         [[problem.examples]]\ninput = \"5\\n\"\noutput = \"5\\n\"\n\
+         [[origin]]\nexpr = \"n\"\n\
+         [[candidate]]\nname = \"exits-42\"\ncmd = [\"sh\", \"-c\", \"cat; exit 42\"]\n\
+         [[candidate]]\nname = \"exits-0\"\ncmd = [\"cat\"]\n\
+         [engine]\ncases = 3\nseed = 1\nsuccess_exit_codes = [42]\nstop_on_first_fail = false\n",
+    )
+    .expect("write config");
+
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("- exits-42: PASS ("), "{stdout}");
+    assert!(stdout.contains("- exits-0: FAIL ("), "{stdout}");
+    assert!(
+        stdout.contains("candidate exited with exit status: 0"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn output_regex_failure_leaves_the_origin_out() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("nado.toml"),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9\n\
+         [[origin]]\ncmd = [\"cat\"]\n\
+         [[candidate]]\ncmd = [\"sh\", \"-c\", \"echo LR; echo Lx\"]\n\
+         [checker]\noutput_regex = \"^[LR]+$\"\n\
+         [engine]\ncases = 1\nseed = 1\n",
+    )
+    .expect("write config");
+
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            r#"reason: output does not match `^[LR]+$` (line 2: "Lx" does not match on its own)"#
+        ),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("origin stdout"), "{stdout}");
+    assert!(!stdout.contains("diff (origin"), "{stdout}");
+}
+
+fn write_config(dir: &Path, candidate: &str, engine: &str) {
+    std::fs::create_dir_all(dir).expect("create config dir");
+    std::fs::write(
+        dir.join("nado.toml"),
+        format!(
+            "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9\n\
+             [[origin]]\ncmd = [\"cat\"]\n\
+             [[candidate]]\n{candidate}\n\
+             [engine]\nseed = 1\n{engine}\n"
+        ),
+    )
+    .expect("write config");
+}
+
+#[test]
+fn hard_deadline_stops_the_run_early() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    write_config(
+        dir.path(),
+        "name = \"slow\"\ncmd = [\"sh\", \"-c\", \"sleep 1; cat\"]",
+        "cases = 50\nhard_deadline_ms = 300",
+    );
+
+    let started = std::time::Instant::now();
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("deadline reached: engine.hard_deadline_ms=300 passed after 0 of 50"),
+        "{stdout}"
+    );
+    assert!(started.elapsed().as_secs() < 10, "{stdout}");
+}
+
+#[test]
+fn deadline_of_one_config_spares_the_next_in_a_batch() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    write_config(
+        &dir.path().join("a"),
+        "name = \"slow\"\ncmd = [\"sh\", \"-c\", \"sleep 1; cat\"]",
+        "cases = 50\nhard_deadline_ms = 300",
+    );
+    write_config(
+        &dir.path().join("b"),
+        "name = \"quick\"\ncmd = [\"cat\"]",
+        "cases = 5",
+    );
+
+    let output = nado(dir.path(), &["run", "a", "b"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (first, second) = stdout
+        .split_once("== b/nado.toml ==")
+        .expect("both configs ran");
+    assert!(first.contains("deadline reached"), "{stdout}");
+    assert!(!second.contains("deadline reached"), "{stdout}");
+    assert!(
+        second.contains("- quick: PASS (avg") && second.contains("over 5 case(s)"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn unread_input_passes_unless_strict_stdin() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    // Far more input than a pipe buffers, which the candidate never reads.
+    let write = |engine: &str| {
+        std::fs::write(
+            dir.path().join("nado.toml"),
+            format!(
+                "[problem.inputs.n]\ntype = \"integer\"\nmin = 200000\nmax = 200000\n\
+                 [problem.inputs.xs]\ntype = \"array\"\nlen = \"n\"\n\
+                 element = {{ type = \"integer\", min = 100000000, max = 999999999 }}\n\
+                 [[origin]]\ncmd = [\"sh\", \"-c\", \"cat > /dev/null; echo 1\"]\n\
+                 [[candidate]]\nname = \"early\"\ncmd = [\"echo\", \"1\"]\n\
+                 [engine]\ncases = 1\nseed = 1\n{engine}\n"
+            ),
+        )
+        .expect("write config");
+    };
+
+    write("");
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("- early: PASS ("), "{stdout}");
+
+    write("strict_stdin = true");
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{stdout}");
+    assert!(
+        stdout.contains("program exited without reading all of its input (engine.strict_stdin)"),
+        "{stdout}"
+    );
+}
+
+#[cfg(unix)]
+fn write_socket_config(dir: &Path, inputs: &str, client: &str, engine: &str) {
+    std::fs::write(
+        dir.join("nado.toml"),
+        format!(
+            "{inputs}\n[[origin]]\ncmd = [\"cat\"]\n\
+             [[candidate]]\nname = \"service\"\ntransport = \"socket\"\n\
+             cmd = [\"python3\", \"-c\", {client:?}]\n\
+             [engine]\ncases = 1\nseed = 1\n{engine}\n"
+        ),
+    )
+    .expect("write config");
+}
+
+#[cfg(unix)]
+#[test]
+fn socket_transport_answers_over_the_connection() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let client = "import os, socket\n\
+                  s = socket.socket(socket.AF_UNIX)\n\
+                  s.connect(os.environ['NADO_SOCKET'])\n\
+                  data = b''\n\
+                  while chunk := s.recv(4096): data += chunk\n\
+                  s.sendall(data)\n\
+                  print('ignored')\n";
+    write_socket_config(
+        dir.path(),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9",
+        client,
+        "",
+    );
+
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("- service: PASS ("), "{stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn socket_service_that_keeps_running_is_stopped_after_answering() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    // Answers, closes the connection, then runs on like a long-lived server.
+    let client = "import os, socket, time\n\
+                  s = socket.socket(socket.AF_UNIX)\n\
+                  s.connect(os.environ['NADO_SOCKET'])\n\
+                  data = b''\n\
+                  while chunk := s.recv(4096): data += chunk\n\
+                  s.sendall(data)\n\
+                  s.close()\n\
+                  time.sleep(30)\n";
+    write_socket_config(
+        dir.path(),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 1\nmax = 9",
+        client,
+        "timeout_ms = 10000",
+    );
+
+    let started = std::time::Instant::now();
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("- service: PASS ("), "{stdout}");
+    assert!(started.elapsed().as_secs() < 10, "{stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn socket_runner_error_kills_the_program() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    // Answers without reading its input, then lingers.
+    let client = "import os, socket, time\n\
+                  s = socket.socket(socket.AF_UNIX)\n\
+                  s.connect(os.environ['NADO_SOCKET'])\n\
+                  s.sendall(b'1')\n\
+                  s.close()\n\
+                  open('pid', 'w').write(str(os.getpid()))\n\
+                  time.sleep(30)\n";
+    write_socket_config(
+        dir.path(),
+        "[problem.inputs.n]\ntype = \"integer\"\nmin = 200000\nmax = 200000\n\
+         [problem.inputs.xs]\ntype = \"array\"\nlen = \"n\"\n\
+         element = { type = \"integer\", min = 100000000, max = 999999999 }",
+        client,
+        "strict_stdin = true\ntimeout_ms = 10000",
+    );
+
+    let output = nado(dir.path(), &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("engine.strict_stdin"), "{stdout}");
+    let pid = std::fs::read_to_string(dir.path().join("pid")).expect("pid written");
+    let alive = Command::new("kill")
+        .args(["-0", pid.trim()])
+        .status()
+        .expect("run kill");
+    assert!(!alive.success(), "program {pid} outlived nado");
+}
@@ -0,0 +1,13 @@
+import sys
+
+case_path, output_path, answer_path = sys.argv[1:4]
+with open(case_path) as case:
+    n = int(case.read())
+with open(output_path) as output:
+    tokens = output.read().split()
+
+if len(tokens) != 1 or not tokens[0].isdigit():
+    sys.exit(f"expected one number, got {tokens!r}")
+d = int(tokens[0])
+if not (1 < d <= n and n % d == 0):
+    sys.exit(f"{d} is not a divisor of {n} greater than 1")
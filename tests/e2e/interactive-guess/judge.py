@@ -0,0 +1,18 @@
+import sys
+
+n, x = map(int, open(sys.argv[1]).read().split())
+x = min(x, n)
+limit = n.bit_length() + 1
+
+print(n, flush=True)
+for _ in range(limit):
+    line = sys.stdin.readline()
+    if not line:
+        sys.exit("program closed stdout before guessing")
+    guess = int(line)
+    if guess == x:
+        print("=", flush=True)
+        sys.exit(0)
+    print("<" if x < guess else ">", flush=True)
+
+sys.exit(f"no correct guess within {limit} tries")
@@ -0,0 +1,14 @@
+STDOUT.sync = true
+n = gets.to_i
+lo, hi = 1, n
+loop do
+  mid = (lo + hi) / 2
+  puts mid
+  reply = gets.strip
+  break if reply == "="
+  if reply == "<"
+    hi = mid - 1
+  else
+    lo = mid + 1
+  end
+end